@@ -3,14 +3,10 @@
 // main.rs
 //
 
-static mut VERBOSE: bool = true;
-
 macro_rules! log {
-    ($message:expr $(,$arg:expr)*) => {
-        unsafe {
-            if VERBOSE == true {
-                println!($message, $($arg,)*);
-            }
+    ($verbose:expr, $message:expr $(,$arg:expr)*) => {
+        if $verbose {
+            println!($message, $($arg,)*);
         }
     };
 }
@@ -18,7 +14,7 @@ macro_rules! log {
 // include the lib.rs file
 extern crate yasl_compiler;
 
-use yasl_compiler::compile_file;
+use yasl_compiler::{compile_file_with, compile_expression_with, ParserOptions, ParserResult};
 
 // Include the io lib
 use std::io;
@@ -28,26 +24,111 @@ fn main() {
     // Check for an argument
     let mut i = 0;
     let mut maybe_file: Option<String> = None;
+    let mut maybe_expression: Option<String> = None;
+    let mut reading_expression = false;
+    let mut reading_define = false;
+    let mut reading_registers = false;
+    let mut reading_instructions = false;
+    let mut options = ParserOptions::default();
     for argument in env::args() {
         if i == 0 {
             // Do nothing, its how to program was invoked
+        } else if reading_expression {
+            // The argument after --expr is the expression to compile
+            maybe_expression = Some(argument.clone());
+            reading_expression = false;
+        } else if reading_define {
+            // The argument after -D is the symbol to define
+            options.defines.push(argument.clone());
+            reading_define = false;
+        } else if reading_registers {
+            // The argument after --registers is the number of registers
+            match argument.parse::<u32>() {
+                Ok(n) => options.registers = n,
+                Err(_) => println!("<YASLC> Invalid number of registers \"{}\", ignoring it.", argument),
+            }
+            reading_registers = false;
+        } else if reading_instructions {
+            // The argument after --instructions is the comma separated instruction set
+            options.instruction_set = Some(argument.split(',').map(|m| m.trim().to_string()).collect());
+            reading_instructions = false;
         } else {
             // Check for flags
             if argument == "-v" {
-                unsafe {
-                    VERBOSE = true;
-                }
+                options.verbose = true;
+            } else if argument == "--cfg" {
+                options.emit_cfg = true;
+            } else if argument == "--labels" {
+                options.emit_labels = true;
+            } else if argument == "--stack-depth" {
+                options.emit_stack_depth = true;
+            } else if argument == "-O" {
+                options.set_optimization_level(1);
+            } else if let Some(level) = argument.strip_prefix("-O").and_then(|l| l.parse::<u32>().ok()) {
+                options.set_optimization_level(level);
+            } else if argument == "--warn-shadowing" {
+                options.warn_shadowing = true;
+            } else if argument == "--warn-unassigned" {
+                options.warn_unassigned = true;
+            } else if argument == "--warn-unused" {
+                options.warn_unused = true;
+            } else if argument == "--warn-constant-conditions" {
+                options.warn_constant_conditions = true;
+            } else if argument == "--warnings-as-errors" {
+                options.warnings_as_errors = true;
+            } else if argument == "--debug-info" {
+                options.debug_info = true;
+            } else if argument == "--symbols" {
+                options.emit_symbols = true;
+            } else if argument == "--listing" {
+                options.source_listing = true;
+            } else if argument == "--line-comments" {
+                options.line_comments = true;
+            } else if argument == "--indent" {
+                options.indent = true;
+            } else if argument == "--diagnostics=json" {
+                options.json_diagnostics = true;
+            } else if argument == "--recover" {
+                options.recover = true;
+            } else if argument == "--strict" {
+                options.strict = true;
+            } else if argument == "--strict-numbers" {
+                options.strict_numbers = true;
+            } else if argument == "--strict-keywords" {
+                options.strict_keywords = true;
+            } else if argument == "--emit-comments-only" {
+                options.comments_only = true;
+            } else if argument == "--args" {
+                options.arguments = true;
+            } else if argument == "--print-bools" {
+                options.print_bools = true;
+            } else if argument == "--share-strings" {
+                options.share_strings = true;
+            } else if argument == "--expr" {
+                reading_expression = true;
+            } else if argument == "-D" {
+                reading_define = true;
+            } else if argument == "--registers" {
+                reading_registers = true;
+            } else if argument == "--instructions" {
+                reading_instructions = true;
             } else {
-                log!("Compiling file \"{}\"", argument);
+                log!(options.verbose, "Compiling file \"{}\"", argument);
                 maybe_file = Some(argument.clone());
             }
         }
 
-        log!("Argument {}: {}", i, argument);
+        log!(options.verbose, "Argument {}: {}", i, argument);
 
         i += 1;
     }
 
+    if let Some(expression) = maybe_expression {
+        log!(options.verbose, "Compiling expression \"{}\"", expression);
+        exit_with(compile_expression_with(expression, options));
+        return;
+    }
+
     let mut file_name = match maybe_file {
         Some(f) => f,
         None => {
@@ -77,5 +158,14 @@ fn main() {
         }
     }
 
-    compile_file(file_name);
+    exit_with(compile_file_with(file_name, options));
+}
+
+/// Exits with a failure status unless the compile was successful, so scripts can tell when
+/// a program did not compile.
+fn exit_with(result: ParserResult) {
+    match result {
+        ParserResult::Success => {},
+        _ => std::process::exit(1),
+    }
 }
@@ -6,13 +6,22 @@
 /// generation for the files.
 
 
+#[cfg(test)]
+mod tests;
+
+mod preprocessor;
 mod scanner;
 mod token;
 
-pub use lexer::token::{Token, TokenType, KeywordType};
+pub use lexer::preprocessor::preprocess;
+pub use lexer::token::{Token, TokenType, KeywordType, number_value, real_value};
+
+pub use lexer::scanner::TokenStream;
 
 use lexer::scanner::Scanner;
 
+use std::io;
+
 /// LexerResult is either Ok and includes a vector of the tokens that were
 /// returned from the lexical analysis or has an error and returns the
 /// appropriate error.
@@ -21,28 +30,76 @@ pub enum LexerResult {
     Err(LexerError),
 }
 
-/// LexerError corresponds to a file error, usually at the OS level.
+/// LexerError corresponds to a file error, usually at the OS level, or input that can not
+/// be split into tokens.
+#[derive(Debug)]
 pub enum LexerError {
-    FileError,
+    /// The file could not be opened or read, with the error the OS gave.
+    FileError(io::Error),
     StdinError,
+
+    /// A (* *) comment was never closed, with the line and column where it started.
+    UnterminatedComment(u32, u32),
+}
+
+/// The options of lexical analysis, each of which is off by default.
+#[derive(Clone, Default)]
+pub struct LexerOptions {
+    /// Lex a number immediately followed by letters, like "123abc", as a single invalid token
+    /// instead of a number and an identifier.
+    pub strict_numbers: bool,
+
+    /// Only accept the exact spelling of each keyword, so "boolean" is an identifier instead
+    /// of a synonym for "bool".
+    pub strict_keywords: bool,
+
+    /// Log every token as it is generated.
+    pub verbose: bool,
+}
+
+/// read_string_filtered does lexical analysis on the source string like read_string_with
+/// and returns Ok((tokens, invalid)) with the Invalid tokens taken out of the tokens and
+/// listed separately, so tools can skip bad tokens.
+pub fn read_string_filtered(source: String, options: &LexerOptions) -> Result<(Vec<Token>, Vec<Token>), LexerError> {
+    let mut scanner = Scanner::new_from_string(source);
+    scanner.set_options(options);
+    scanner.read_file_filtered()
+}
+
+/// stream_string returns a TokenStream which does lexical analysis on the source string
+/// one token at a time as they are asked for, using the options.
+pub fn stream_string(source: String, options: &LexerOptions) -> TokenStream {
+    let mut scanner = Scanner::new_from_string(source);
+    scanner.set_options(options);
+    scanner.into_stream()
+}
+
+/// read_string does lexical analysis on the source string, which is also how a file is
+/// read once its contents are loaded.
+pub fn read_string(source: String) -> LexerResult {
+    read_string_with(source, &LexerOptions::default())
+}
+
+/// read_string_with does lexical analysis on the source string instead of a file and
+/// returns the result, using the options.
+pub fn read_string_with(source: String, options: &LexerOptions) -> LexerResult {
+    let scanner = Scanner::new_from_string(source);
+    read_scanner(scanner, options)
+}
+
+/// read_string_in_file does lexical analysis on the source string like read_string_with
+/// and marks every token as being from the source file with the identifier.
+pub fn read_string_in_file(source: String, options: &LexerOptions, file: u32) -> LexerResult {
+    let mut scanner = Scanner::new_from_string(source);
+    scanner.set_file(file);
+    read_scanner(scanner, options)
 }
 
-/// read_file takes a file name as an input and attempts to do lexical analysis
-/// on it using the scanner submodule, then returns the result based on
-/// what is returned.
-pub fn read_file(file_name: String) -> LexerResult {
-
-    if let Some(scanner) = Scanner::new_from_file(file_name) {
-        match scanner.read_file() {
-            Ok(tokens) => {
-                return LexerResult::Ok(tokens);
-            },
-            Err(e) => {
-                println!("<YASLC/Lexer> Error reading file: {}", e);
-                return LexerResult::Err(LexerError::FileError);
-            }
-        };
-    } else {
-        return LexerResult::Err(LexerError::FileError);
+/// Reads every token of the scanner using the options.
+fn read_scanner(mut scanner: Scanner, options: &LexerOptions) -> LexerResult {
+    scanner.set_options(options);
+    match scanner.read_file() {
+        Ok(tokens) => LexerResult::Ok(tokens),
+        Err(e) => LexerResult::Err(e),
     }
 }
@@ -0,0 +1,79 @@
+/// lexer/preprocessor.rs
+///
+/// The preprocessor module evaluates conditional compilation directives over the token
+/// stream before it is parsed, removing the tokens which are not included.
+///
+/// The directives are "#if NAME", "#else" and "#endif", where NAME is included if it is
+/// one of the defined symbols. Directives can be nested.
+
+use lexer::token::{Token, TokenType};
+
+/// A single #if which has not been closed by an #endif yet.
+struct Condition {
+    /// Whether the tokens of the current branch are included.
+    including: bool,
+
+    /// Whether the tokens of the enclosing branch are included.
+    enclosing: bool,
+
+    /// Whether the #else of the condition has been found.
+    in_else: bool,
+}
+
+/// Returns the tokens with the directives evaluated against the defined symbols, or
+/// Err(e) where e describes the unbalanced or unrecognized directive.
+pub fn preprocess(tokens: Vec<Token>, defines: &[String]) -> Result<Vec<Token>, String> {
+    let mut result = Vec::<Token>::new();
+    let mut conditions = Vec::<Condition>::new();
+    let mut tokens = tokens.into_iter();
+
+    while let Some(t) = tokens.next() {
+        let including = match conditions.last() {
+            Some(c) => c.including,
+            None => true,
+        };
+
+        if !t.is_type(TokenType::Directive) {
+            if including {
+                result.push(t);
+            }
+            continue;
+        }
+
+        match &*t.lexeme() {
+            "#if" => {
+                let name = match tokens.next() {
+                    Some(ref n) if n.is_type(TokenType::Identifier) => n.lexeme(),
+                    _ => return Err(format!("Expected a name after #if at ({}, {}).", t.line(), t.column())),
+                };
+
+                conditions.push(Condition {
+                    including: including && defines.contains(&name),
+                    enclosing: including,
+                    in_else: false,
+                });
+            },
+            "#else" => {
+                match conditions.last_mut() {
+                    Some(ref mut c) if !c.in_else => {
+                        c.including = c.enclosing && !c.including;
+                        c.in_else = true;
+                    },
+                    _ => return Err(format!("Found #else without an #if at ({}, {}).", t.line(), t.column())),
+                };
+            },
+            "#endif" => {
+                if conditions.pop().is_none() {
+                    return Err(format!("Found #endif without an #if at ({}, {}).", t.line(), t.column()));
+                }
+            },
+            d => return Err(format!("Unrecognized directive {} at ({}, {}).", d, t.line(), t.column())),
+        };
+    }
+
+    if !conditions.is_empty() {
+        return Err(format!("Expected #endif for {} #if directive(s) before the end of the input.", conditions.len()));
+    }
+
+    Ok(result)
+}
@@ -7,16 +7,18 @@
 
 // Include the token struct and functions
 use lexer::token::*;
+use lexer::{LexerError, LexerOptions};
 
 // Include input methods and string classes
-use std::io::{Read};
-use std::fs::File;
+use std::io::{Cursor, Read};
+use std::collections::VecDeque;
+use std::vec;
 
 /// Scanner is the struct responsible for handling and returning the token set based on the
 /// input file, as well as reading the file.
 pub struct Scanner {
-    /// The file associated with this scanner.
-    file: File,
+    /// The input associated with this scanner, either a file or a string.
+    input: Box<dyn Read>,
 
     // Used to construct tokens
     // We store the token_builder, which already stores line and column number,
@@ -35,59 +37,100 @@ pub struct Scanner {
 
     /// the set of tokens from the last input, most useful when using stdin
     pub new_tokens: Vec<Token>,
+
+    /// Whether a number immediately followed by letters is an invalid token.
+    strict_numbers: bool,
+
+    /// Whether only the exact spelling of each keyword is accepted.
+    strict_keywords: bool,
+
+    /// The identifier of the source file, which is set on every token when there is one.
+    file: Option<u32>,
+
+    /// Whether each token is logged as it is generated.
+    verbose: bool,
 }
 
 impl Scanner {
-    /// Creates a new Scanner from the file_string and returns it.
-    pub fn new_from_file(file_string: String) -> Option<Scanner> {
-        // Open the file so we can set it as a property
-        let file = match File::open(file_string.clone()) {
-            Ok(f) => f,
-            Err(e) => {
-                println!("Error opening file \"{}\": {}", file_string, e);
-                return None;
-            },
-        };
+    /// Creates a new Scanner which reads the source string instead of a file.
+    pub fn new_from_string(source: String) -> Scanner {
+        Scanner::new_from_input(Box::new(Cursor::new(source.into_bytes())))
+    }
 
+    /// Creates a new Scanner reading from the input.
+    fn new_from_input(input: Box<dyn Read>) -> Scanner {
         // Set the line number and column number
         let line_number = 1;
         let column_number = 1;
         let token_builder = TokenBuilder::new(column_number, line_number);
 
-        Some(Scanner {
-            file: file,
+        Scanner {
+            input: input,
             token_builder: token_builder,
             line_number: line_number,
             column_number: column_number,
             tokens: Vec::<Token>::new(),
             new_tokens: Vec::<Token>::new(),
-        })
+            strict_numbers: false,
+            strict_keywords: false,
+            file: None,
+            verbose: false,
+        }
+    }
+
+    /// Sets the options of the lexical analysis, which apply to every token after this.
+    pub fn set_options(&mut self, options: &LexerOptions) {
+        self.strict_numbers = options.strict_numbers;
+        self.strict_keywords = options.strict_keywords;
+        self.verbose = options.verbose;
+        self.token_builder.set_strict_numbers(self.strict_numbers);
+        self.token_builder.set_strict_keywords(self.strict_keywords);
+    }
+
+    /// Sets the identifier of the source file the tokens are from.
+    pub fn set_file(&mut self, file: u32) {
+        self.file = Some(file);
     }
 
     /// Reads the file for this scanner and returns Ok(tokens) where tokens
-    /// is a list of tokens or Err(error message) where error message is an
-    /// string describing the error. Consumes the scanner.
-    pub fn read_file(mut self) -> Result<Vec<Token>, String> {
-        // Read the string to a file
-        let mut buffer = String::new();
+    /// is a list of tokens or Err(error) where error is the LexerError describing
+    /// what went wrong. Consumes the scanner.
+    pub fn read_file(self) -> Result<Vec<Token>, LexerError> {
+        let mut stream = self.into_stream();
+        let tokens = stream.by_ref().collect();
+        match stream.take_error() {
+            Some(e) => Err(e),
+            None => Ok(tokens),
+        }
+    }
 
-        // Read the file to the buffer
-        match self.file.read_to_string(&mut buffer){
-            Ok(_) => {
-                // println!("File read of size {}", size);
-            },
+    /// Reads the input for this scanner and returns a TokenStream which splits it into
+    /// tokens as they are asked for. Consumes the scanner.
+    pub fn into_stream(mut self) -> TokenStream {
+        let mut buffer = String::new();
+        let error = match self.input.read_to_string(&mut buffer) {
+            Ok(_) => None,
             Err(e) => {
-                // println!(, e);
-                return Err(format!("{}", e));
+                println!("<YASLC/Lexer> Error reading input: {}", e);
+                Some(LexerError::FileError(e))
             }
         };
 
-        // Input the file one character at a time
-        for c in buffer.chars() {
-            self.push_char(c);
+        TokenStream {
+            finished: error.is_some(),
+            scanner: self,
+            chars: buffer.chars().collect::<Vec<char>>().into_iter(),
+            pending: VecDeque::new(),
+            error: error,
         }
+    }
 
-        Ok(self.tokens)
+    /// Reads the file for this scanner like read_file but returns Ok((tokens, invalid))
+    /// where tokens are only the valid tokens and invalid are the Invalid tokens which were
+    /// left out of them. Consumes the scanner.
+    pub fn read_file_filtered(self) -> Result<(Vec<Token>, Vec<Token>), LexerError> {
+        let tokens = self.read_file()?;
+        Ok(tokens.into_iter().partition(|t| !t.is_type(TokenType::Invalid)))
     }
 
     // Commented out to suppress warnings, will be re-implemented later
@@ -128,6 +171,8 @@ impl Scanner {
         if let Some(t) = token {
             self.push_token(t);
             self.token_builder = TokenBuilder::new(self.column_number, self.line_number);
+            self.token_builder.set_strict_numbers(self.strict_numbers);
+            self.token_builder.set_strict_keywords(self.strict_keywords);
         }
 
         // If we need to push the cursor back, we just re-read the current character
@@ -148,9 +193,7 @@ impl Scanner {
 
     /// Pushes the token onto the list.
     fn push_token(&mut self, t: Token) {
-        // Comment this line to stop printing tokens when they are generated
-        let debug = false;
-        if debug == true {
+        if self.verbose {
             println!("<YASLC/lexer> Generated token: {}", t);
         }
 
@@ -158,3 +201,74 @@ impl Scanner {
         self.tokens.push(t);
     }
 }
+
+/// TokenStream is an iterator over the tokens of a scanner's input, which only scans as
+/// far as it needs to for the next token. An error found while scanning ends the stream
+/// and can be taken from it afterwards.
+pub struct TokenStream {
+    /// The scanner the characters are pushed into.
+    scanner: Scanner,
+
+    /// The characters of the input which have not been scanned yet.
+    chars: vec::IntoIter<char>,
+
+    /// The tokens which have been scanned but not returned yet.
+    pending: VecDeque<Token>,
+
+    /// Whether the end of the input has been scanned.
+    finished: bool,
+
+    /// The error which ended the stream, if there was one.
+    error: Option<LexerError>,
+}
+
+impl TokenStream {
+    /// Returns the error which ended the stream, if there was one, leaving None in its
+    /// place.
+    pub fn take_error(&mut self) -> Option<LexerError> {
+        self.error.take()
+    }
+
+    /// Scans the end of the input, finishing any token which runs to the very end of it.
+    fn finish(&mut self) {
+        self.finished = true;
+
+        // Finish a token which runs to the very end of the input, such as a number
+        self.scanner.push_char('\n');
+
+        // A string that never closed is invalid where it started instead of being dropped
+        if let Some(t) = self.scanner.token_builder.unterminated_string() {
+            self.scanner.push_token(t);
+        }
+
+        // A (* *) or { } comment that never closed runs to the end of the input
+        if let Some((line, column)) = self.scanner.token_builder.open_comment() {
+            self.error = Some(LexerError::UnterminatedComment(line, column));
+        }
+    }
+}
+
+impl Iterator for TokenStream {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            if let Some(mut t) = self.pending.pop_front() {
+                if let Some(file) = self.scanner.file {
+                    t.set_file(file);
+                }
+                return Some(t);
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            match self.chars.next() {
+                Some(c) => self.scanner.push_char(c),
+                None => self.finish(),
+            };
+            self.pending.extend(self.scanner.tokens.drain(..));
+        }
+    }
+}
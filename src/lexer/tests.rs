@@ -0,0 +1,411 @@
+/// lexer/tests.rs
+///
+/// This file contains unit tests for the lexer, the tokens it reads from a source and the
+/// evaluation of the preprocessor directives among them.
+
+use super::*;
+
+/// Returns the tokens of the source with the directives evaluated for the defines.
+fn preprocessed(source: &str, defines: &[&str]) -> Result<Vec<Token>, String> {
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    let defines: Vec<String> = defines.iter().map(|d| d.to_string()).collect();
+    preprocess(tokens, &defines)
+}
+
+/// *************************************
+/// ********** Block Comments ***********
+/// *************************************
+
+#[test]
+// Checks that (* *) comments nest and that a ( before anything else is still a parenthesis
+fn nested_paren_comments() {
+    let source = "x (* outer (* inner *) still outer *) = (y) (**)";
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    let lexemes: Vec<String> = tokens.iter().map(|t| t.lexeme()).collect();
+    assert_eq!(lexemes, vec!["x", "=", "(", "y", ")"]);
+    assert!(matches!(tokens[2].token_type(), TokenType::LeftParen));
+    assert_eq!(tokens[1].column(), 39);
+}
+
+#[test]
+// Checks that a comment which is never closed reports where it started
+fn unterminated_paren_comment() {
+    let source = "x = 1;\n  (* one (* two *) never closed";
+    match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Err(LexerError::UnterminatedComment(line, column)) => {
+            assert_eq!((line, column), (2, 3));
+        },
+        _ => panic!("The unterminated comment was not reported"),
+    }
+}
+
+#[test]
+// Checks that { } comments nest one and two levels deep
+fn nested_curly_comments() {
+    for source in &["x { outer { inner } still outer } = y", "x { one { two { three } two } one } = y"] {
+        let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+            LexerResult::Ok(t) => t,
+            LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+        };
+
+        let lexemes: Vec<String> = tokens.iter().map(|t| t.lexeme()).collect();
+        assert_eq!(lexemes, vec!["x", "=", "y"]);
+    }
+}
+
+#[test]
+// Checks that a { } comment which is never closed reports where it started
+fn unterminated_curly_comment() {
+    let source = "x = 1;\n    { one { two } never closed";
+    match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Err(LexerError::UnterminatedComment(line, column)) => {
+            assert_eq!((line, column), (2, 5));
+        },
+        _ => panic!("The unterminated comment was not reported"),
+    }
+}
+
+/// *************************************
+/// ****** Conditional Compilation ******
+/// *************************************
+
+#[test]
+// Checks that unbalanced directives are errors
+fn conditional_compilation_unbalanced() {
+    assert!(preprocessed("#if DEBUG begin end", &[]).is_err());
+    assert!(preprocessed("begin #endif end", &[]).is_err());
+    assert!(preprocessed("#if A #else #else #endif", &[]).is_err());
+    assert!(preprocessed("#if A #if B #endif #endif", &["B"]).unwrap().is_empty());
+}
+
+/// *************************************
+/// ********* Malformed Numbers *********
+/// *************************************
+
+#[test]
+// Checks that "123abc" is one invalid token with strict numbers, which says why it is
+// invalid, and split otherwise
+fn number_followed_by_letters() {
+    let tokens = |options: LexerOptions| -> Vec<Token> {
+        match read_string_with("x = 123abc;".to_string(), &options) {
+            LexerResult::Ok(t) => t,
+            LexerResult::Err(_) => panic!("Unable to read the test source"),
+        }
+    };
+    let lexemes = |tokens: Vec<Token>| -> Vec<(String, bool)> {
+        tokens.iter().map(|t| (t.lexeme(), t.is_type(TokenType::Invalid))).collect()
+    };
+
+    let strict = tokens(LexerOptions { strict_numbers: true, ..LexerOptions::default() });
+    assert_eq!(strict[3].message(), None);
+    assert_eq!(strict[2].message().unwrap(), "(1, 5) \"123abc\" is a number followed by letters, which is not a valid number or identifier.");
+    let strict = lexemes(strict);
+    assert_eq!(strict[2], ("123abc".to_string(), true));
+    assert_eq!(strict.len(), 4);
+
+    let split = lexemes(tokens(LexerOptions::default()));
+    assert_eq!(split[2], ("123".to_string(), false));
+    assert_eq!(split[3], ("abc".to_string(), false));
+
+    // Strict keywords leave numbers alone
+    let split = lexemes(tokens(LexerOptions { strict_keywords: true, ..LexerOptions::default() }));
+    assert_eq!(split[2], ("123".to_string(), false));
+}
+
+/// *************************************
+/// ********** Token Comparison *********
+/// *************************************
+
+#[test]
+// Checks that tokens are the same token when only their positions differ
+fn same_token_ignores_position() {
+    let a = Token::new_with(1, 1, "x".to_string(), TokenType::Identifier);
+    let b = Token::new_with(7, 12, "x".to_string(), TokenType::Identifier);
+    assert!(a.same_token(&b));
+    assert!(b.same_token(&a));
+
+    let c = Token::new_with(1, 1, "y".to_string(), TokenType::Identifier);
+    assert!(!a.same_token(&c));
+
+    let d = Token::new_with(1, 1, "x".to_string(), TokenType::String);
+    assert!(!a.same_token(&d));
+
+    // The same source read with different spacing gives the same tokens
+    let read = |source: &str| match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+    let tight = read("x=1+2;");
+    let spaced = read("\n  x = 1 +\n 2 ;");
+    assert_eq!(tight.len(), spaced.len());
+    assert!(tight.iter().zip(spaced.iter()).all(|(t, s)| t.same_token(s)));
+}
+
+/// *************************************
+/// ********** Identifiers **************
+/// *************************************
+
+#[test]
+// Checks that identifiers may have underscores and digits after their first letter, but
+// may not start with an underscore
+fn identifier_characters() {
+    let source = "my_var x1 count_2_ if_ _leading";
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    let lexemes: Vec<String> = tokens.iter().map(|t| t.lexeme()).collect();
+    assert_eq!(lexemes, vec!["my_var", "x1", "count_2_", "if_", "_", "leading"]);
+    for t in &tokens[..4] {
+        assert!(matches!(t.token_type(), TokenType::Identifier), "{}", t);
+    }
+    assert!(matches!(tokens[4].token_type(), TokenType::Invalid));
+}
+
+/// *************************************
+/// ********** String Literals **********
+/// *************************************
+
+#[test]
+// Checks that a string which is never closed is an invalid token where it started
+fn unterminated_string() {
+    let source = "print 1;\n  print \"hello";
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    let last = tokens.last().unwrap();
+    assert!(matches!(last.token_type(), TokenType::Invalid));
+    assert_eq!((last.line(), last.column()), (2, 9));
+    assert_eq!(last.lexeme(), "\"hello");
+    assert_eq!(last.message().unwrap(), "Unterminated string starting at (2, 9).");
+}
+
+#[test]
+// Checks that escape sequences in a string are replaced by the characters they stand for
+fn string_escapes() {
+    let source = r#"print "a\nb" "tab\t\\ \"quoted\"" "bad\q""#;
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    assert_eq!(tokens[1].lexeme(), "\"a\nb\"");
+    assert!(tokens[1].lexeme().contains('\n'));
+    assert_eq!(tokens[2].lexeme(), "\"tab\t\\ \"quoted\"\"");
+    assert!(matches!(tokens[2].token_type(), TokenType::String));
+    assert!(matches!(tokens[3].token_type(), TokenType::Invalid));
+    assert_eq!(tokens.len(), 4);
+}
+
+/// *************************************
+/// ********** Boolean Synonym **********
+/// *************************************
+
+#[test]
+// Checks that boolean is read as the keyword bool, unless strict keywords only accept bool
+fn boolean_synonym() {
+    let types = |source: &str, options: LexerOptions| -> Vec<TokenType> {
+        match read_string_with(source.to_string(), &options) {
+            LexerResult::Ok(t) => t.iter().map(|t| t.token_type()).collect(),
+            LexerResult::Err(_) => panic!("Unable to read the test source"),
+        }
+    };
+    let strict_keywords = LexerOptions { strict_keywords: true, ..LexerOptions::default() };
+    let strict_numbers = LexerOptions { strict_numbers: true, ..LexerOptions::default() };
+    assert!(types("boolean bool", LexerOptions::default()) == vec![TokenType::Keyword(KeywordType::Bool); 2]);
+    assert!(types("boolean bool", strict_numbers) == vec![TokenType::Keyword(KeywordType::Bool); 2]);
+    assert!(types("boolean bool", strict_keywords) == vec![TokenType::Identifier, TokenType::Keyword(KeywordType::Bool)]);
+}
+
+/// *************************************
+/// ********** Chars **********
+/// *************************************
+
+#[test]
+// Checks that a char literal is a single token and that an empty or long one is invalid
+fn char_literal_tokens() {
+    let source = "c = 'x'; d = '' 'ab'";
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    assert!(tokens[2].is_type(TokenType::Char));
+    assert_eq!(tokens[2].lexeme(), "'x'");
+    assert!(tokens[6].is_type(TokenType::Invalid));
+}
+
+/// *************************************
+/// ****** Hex And Binary Numbers *******
+/// *************************************
+
+#[test]
+// Checks that hexadecimal and binary numbers are single number tokens with their values,
+// and that a prefix without digits is invalid
+fn hex_and_binary_tokens() {
+    let source = "0xFF 0b101 0 07 0x 0b2;";
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    assert!(tokens[0].is_type(TokenType::Number));
+    assert_eq!(tokens[0].lexeme(), "0xFF");
+    assert_eq!(tokens[0].number_value(), Some(255));
+    assert_eq!(tokens[1].lexeme(), "0b101");
+    assert_eq!(tokens[1].number_value(), Some(5));
+    assert_eq!(tokens[2].number_value(), Some(0));
+
+    // A leading zero is still its own number
+    assert_eq!(tokens[3].lexeme(), "0");
+    assert_eq!(tokens[4].lexeme(), "7");
+
+    assert!(tokens[5].is_type(TokenType::Invalid));
+    assert_eq!(tokens[5].lexeme(), "0x");
+    assert!(tokens[6].is_type(TokenType::Invalid));
+    assert_eq!(tokens[6].lexeme(), "0b");
+    assert_eq!(tokens[7].lexeme(), "2");
+}
+
+/// *************************************
+/// *********** Token Stream ************
+/// *************************************
+
+#[test]
+// Checks that streaming the tokens of a program yields each token with its position, skipping
+// comments and finishing one which runs to the end of the source
+fn token_stream_tokens() {
+    let source = "program p;\nvar x: int;\n(* a comment *)\nbegin\n    x = 12 + 3;\n    print \"done\";\n    print x\nend.\n42";
+
+    let mut stream = stream_string(source.to_string(), &LexerOptions::default());
+    let first = stream.next().unwrap();
+    assert!(first.is_type(TokenType::Keyword(KeywordType::Program)));
+    assert_eq!((first.line(), first.column()), (1, 1));
+
+    let streamed: Vec<(String, u32, u32)> = stream.map(|t| (t.lexeme(), t.line(), t.column())).collect();
+    let expected = vec![
+        ("p", 1, 9), (";", 1, 10),
+        ("var", 2, 1), ("x", 2, 5), (":", 2, 6), ("int", 2, 8), (";", 2, 11),
+        ("begin", 4, 1),
+        ("x", 5, 5), ("=", 5, 7), ("12", 5, 9), ("+", 5, 12), ("3", 5, 14), (";", 5, 15),
+        ("print", 6, 5), ("\"done\"", 6, 11), (";", 6, 17),
+        ("print", 7, 5), ("x", 7, 11),
+        ("end", 8, 1), (".", 8, 4),
+        ("42", 9, 1),
+    ];
+    let expected: Vec<(String, u32, u32)> = expected.into_iter().map(|(l, line, column)| (l.to_string(), line, column)).collect();
+    assert_eq!(streamed, expected);
+
+    let types: Vec<TokenType> = stream_string("x = \"done\"; 42".to_string(), &LexerOptions::default()).map(|t| t.token_type()).collect();
+    assert!(types == vec![TokenType::Identifier, TokenType::Assign, TokenType::String, TokenType::Semicolon, TokenType::Number]);
+
+    let mut stream = stream_string("x (* never closed".to_string(), &LexerOptions::default());
+    assert_eq!(stream.by_ref().count(), 1);
+    assert!(matches!(stream.take_error(), Some(LexerError::UnterminatedComment(1, 3))));
+}
+
+/// *************************************
+/// ************ Token Spans ************
+/// *************************************
+
+#[test]
+// Checks that tokens end just after their last character, including an identifier which
+// is ended by the character after it and a string which runs over several lines
+fn token_spans() {
+    let tokens = match read_string_with("x = count;\ny = \"ab\ncd\" + 12".to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source"),
+    };
+    let spans: Vec<(String, u32, u32, u32, u32)> = tokens.iter()
+        .map(|t| (t.lexeme(), t.line(), t.column(), t.end_line(), t.end_column()))
+        .collect();
+
+    assert_eq!(spans[2], ("count".to_string(), 1, 5, 1, 10));
+    assert_eq!(spans[3], (";".to_string(), 1, 10, 1, 11));
+    assert_eq!(spans[6], ("\"ab\ncd\"".to_string(), 2, 5, 3, 4));
+    assert_eq!(spans[8], ("12".to_string(), 3, 7, 3, 9));
+}
+
+/// *************************************
+/// ************ Lone Slash *************
+/// *************************************
+
+#[test]
+// Checks that a slash which does not start a comment is a single invalid token, whose
+// message suggests div, and does not swallow the character after it
+fn lone_slash() {
+    let tokens = match read_string_with("a / b\nc /d // e".to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source"),
+    };
+    let lexemes: Vec<String> = tokens.iter().map(|t| t.lexeme()).collect();
+    assert_eq!(lexemes, vec!["a", "/", "b", "c", "/", "d"]);
+
+    assert!(tokens[1].is_type(TokenType::Invalid) && tokens[4].is_type(TokenType::Invalid));
+    assert_eq!((tokens[1].column(), tokens[1].end_column()), (3, 4));
+
+    let message = tokens[1].message().unwrap();
+    assert!(message.starts_with("(1, 3)"));
+    assert!(message.contains("`div`"));
+    assert!(tokens[0].message().is_none());
+
+    let tokens = match read_string_with("a ? b".to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source"),
+    };
+    assert!(tokens[1].is_type(TokenType::Invalid));
+    assert!(!tokens[1].message().unwrap().contains("div"));
+}
+
+/// *************************************
+/// *********** Real Numbers ************
+/// *************************************
+
+#[test]
+// Checks that a number with a decimal point is a single number token and that a point
+// without digits after it is invalid
+fn real_tokens() {
+    let source = "3.14 0.5 12 1.;";
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    assert!(tokens[0].is_type(TokenType::Number));
+    assert_eq!(tokens[0].lexeme(), "3.14");
+    assert_eq!(tokens[0].number_value(), None);
+    assert_eq!(tokens[1].lexeme(), "0.5");
+    assert_eq!(tokens[2].number_value(), Some(12));
+
+    assert!(tokens[3].is_type(TokenType::Invalid));
+    assert_eq!(tokens[3].lexeme(), "1.");
+    assert!(tokens[4].is_type(TokenType::Semicolon));
+}
+
+/// *************************************
+/// ********** Percent Operator *********
+/// *************************************
+
+#[test]
+// Checks that % is a token of its own
+fn percent_token() {
+    let source = "x%3 mod";
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+    assert_eq!(tokens.len(), 4);
+    assert!(tokens[1].is_type(TokenType::Percent));
+    assert_eq!(tokens[1].lexeme(), "%");
+}
@@ -20,6 +20,12 @@ pub enum TokenType {
     // String
     String,
 
+    // A single character between single quotes
+    Char,
+
+    // Preprocessor directive, such as #if
+    Directive,
+
     // Punctuation
     Semicolon,
     Colon,
@@ -27,13 +33,21 @@ pub enum TokenType {
     Comma,
     LeftParen,
     RightParen,
+    LeftBracket,
+    RightBracket,
 
     // Operators
     Plus,
     Minus,
     Star,
+    Percent,
     Assign,
 
+    // Compound assignments
+    PlusAssign,
+    MinusAssign,
+    StarAssign,
+
     // Comparators
     GreaterThan,
     LessThan,
@@ -57,6 +71,8 @@ impl fmt::Display for TokenType {
             &TokenType::String => {
                 write!(f, "STRING")
             },
+            &TokenType::Char => write!(f, "CHAR"),
+            &TokenType::Directive => write!(f, "DIRECTIVE"),
 
             &TokenType::Semicolon => write!(f, "SEMI"),
             &TokenType::Colon => write!(f, "COLON"),
@@ -64,11 +80,17 @@ impl fmt::Display for TokenType {
             &TokenType::Comma => write!(f, "COMMA"),
             &TokenType::LeftParen => write!(f, "LPAREN"),
             &TokenType::RightParen => write!(f, "RPAREN"),
+            &TokenType::LeftBracket => write!(f, "LBRACKET"),
+            &TokenType::RightBracket => write!(f, "RBRACKET"),
 
             &TokenType::Plus => write!(f, "PLUS"),
             &TokenType::Minus => write!(f, "MINUS"),
             &TokenType::Star => write!(f, "STAR"),
+            &TokenType::Percent => write!(f, "PERCENT"),
             &TokenType::Assign => write!(f, "EQUALS"),
+            &TokenType::PlusAssign => write!(f, "PLUSEQUALS"),
+            &TokenType::MinusAssign => write!(f, "MINUSEQUALS"),
+            &TokenType::StarAssign => write!(f, "STAREQUALS"),
 
             &TokenType::GreaterThan => write!(f, "GREATERTHAN"),
             &TokenType::LessThan => write!(f, "LESSTHAN"),
@@ -89,24 +111,41 @@ pub enum KeywordType {
     Const,
     Begin,
     Print,
+    Write,
+    Return,
+    Swap,
     End,
     Div,
     Mod,
     Var,
     Int,
     Bool,
+    Char,
+    Str,
+    Real,
     Proc,
     If,
     Then,
     Else,
+    Elsif,
     While,
     Do,
+    For,
+    To,
     Prompt,
     And,
     Or,
     Not,
     True,
     False,
+    Asm,
+    BAnd,
+    BOr,
+    BXor,
+    Shl,
+    Shr,
+    Array,
+    Of,
 }
 
 impl fmt::Display for KeywordType {
@@ -117,24 +156,41 @@ impl fmt::Display for KeywordType {
             Const => write!(f, "CONST"),
             Begin => write!(f, "BEGIN"),
             Print => write!(f, "PRINT"),
+            Write => write!(f, "WRITE"),
+            Return => write!(f, "RETURN"),
+            Swap => write!(f, "SWAP"),
             End => write!(f, "END"),
             Div => write!(f, "DIV"),
             Mod => write!(f, "MOD"),
             Var => write!(f, "VAR"),
             Int => write!(f, "INT"),
             Bool => write!(f, "BOOL"),
+            Char => write!(f, "CHAR"),
+            Str => write!(f, "STRING"),
+            Real => write!(f, "REAL"),
             Proc => write!(f, "PROC"),
             If => write!(f, "IF"),
             Then => write!(f, "THEN"),
             Else => write!(f, "ELSE"),
+            Elsif => write!(f, "ELSIF"),
             While => write!(f, "WHILE"),
             Do => write!(f, "DO"),
+            For => write!(f, "FOR"),
+            To => write!(f, "TO"),
             Prompt => write!(f, "PROMPT"),
             And => write!(f, "AND"),
             Or => write!(f, "OR"),
             Not => write!(f, "NOT"),
             True => write!(f, "TRUE"),
+            Asm => write!(f, "ASM"),
             False => write!(f, "FALSE"),
+            BAnd => write!(f, "BAND"),
+            BOr => write!(f, "BOR"),
+            BXor => write!(f, "BXOR"),
+            Shl => write!(f, "SHL"),
+            Shr => write!(f, "SHR"),
+            Array => write!(f, "ARRAY"),
+            Of => write!(f, "OF"),
         }
     }
 }
@@ -151,17 +207,36 @@ pub struct Token {
     /// The column where the token starts.
     column: u32,
 
+    /// The line where the token ends.
+    end_line: u32,
+
+    /// The column just after the last character of the token.
+    end_column: u32,
+
     /// The lexeme associated with this token.
-    lexeme: String
+    lexeme: String,
+
+    /// The identifier of the source file the token is from, when several sources are
+    /// compiled together.
+    file: Option<u32>,
+
+    /// Why the token is invalid, for an invalid token the lexer can explain.
+    message: Option<String>,
 }
 
 impl Token {
+    /// Returns a new token on a single line, which ends just after the last character of the
+    /// lexeme.
     pub fn new_with(line: u32, column: u32, lexeme: String, token_type: TokenType) -> Token {
         Token {
             token_type: token_type,
             line: line,
             column: column,
-            lexeme: lexeme
+            end_line: line,
+            end_column: column + lexeme.chars().count() as u32,
+            lexeme: lexeme,
+            file: None,
+            message: None,
         }
     }
 
@@ -189,6 +264,108 @@ impl Token {
     pub fn column(&self) -> u32 {
         self.column
     }
+
+    /// Returns the line where the token ends.
+    pub fn end_line(&self) -> u32 {
+        self.end_line
+    }
+
+    /// Returns the column just after the last character of the token, so a token which
+    /// starts and ends at the same column is empty.
+    pub fn end_column(&self) -> u32 {
+        self.end_column
+    }
+
+    /// Returns the identifier of the source file the token is from, if it was set.
+    pub fn file(&self) -> Option<u32> {
+        self.file
+    }
+
+    /// Sets the identifier of the source file the token is from.
+    pub fn set_file(&mut self, file: u32) {
+        self.file = Some(file);
+    }
+
+    /// Returns Some(m) where m is why the token is invalid, or None if it is valid or the
+    /// lexer has no explanation for it.
+    pub fn message(&self) -> Option<String> {
+        self.message.clone()
+    }
+
+    /// Returns the value of a number token, which may be written in hexadecimal as "0xFF" or
+    /// in binary as "0b101", or None if it is not a number or does not fit in an int.
+    pub fn number_value(&self) -> Option<i32> {
+        match self.token_type {
+            TokenType::Number => number_value(&self.lexeme),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the other token has the same type and lexeme, wherever either of
+    /// them is in the source.
+    pub fn same_token(&self, other: &Token) -> bool {
+        self.token_type == other.token_type && self.lexeme == other.lexeme
+    }
+}
+
+/// Returns the character the escape sequence of a backslash followed by c stands for inside
+/// a string, or None if it is not a valid escape.
+fn escaped_char(c: char) -> Option<char> {
+    match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        '\\' => Some('\\'),
+        '"' => Some('"'),
+        _ => None,
+    }
+}
+
+/// Returns the value of the lexeme of a number, which is decimal unless it starts with "0x"
+/// for hexadecimal or "0b" for binary, or None if it is not a number which fits in an int.
+pub fn number_value(lexeme: &str) -> Option<i32> {
+    let (digits, radix) = match lexeme.get(..2) {
+        Some("0x") | Some("0X") => (&lexeme[2..], 16),
+        Some("0b") | Some("0B") => (&lexeme[2..], 2),
+        _ => (lexeme, 10),
+    };
+    if radix != 10 && digits.starts_with(['-', '+']) {
+        return None;
+    }
+    i32::from_str_radix(digits, radix).ok()
+}
+
+/// Returns the value of the lexeme of a real number, which has a decimal point as in
+/// "3.14", or None if it is not a real number.
+pub fn real_value(lexeme: &str) -> Option<f64> {
+    if !lexeme.contains('.') {
+        return None;
+    }
+    lexeme.parse::<f64>().ok()
+}
+
+/// Returns the message for the invalid token with the lexeme at the line and column, which
+/// suggests `div` for a slash since YASL does not divide with one.
+fn unaccepted_message(line: u32, column: u32, lexeme: &str) -> String {
+    match lexeme {
+        "/" => format!("({}, {}) \"/\" is not an operator, divide with `div` instead, as in \"a div b\", or start a comment with \"//\".",
+            line, column),
+        _ => format!("Invalid token found at ({}, {}) with lexeme \"{}\".", line, column, lexeme),
+    }
+}
+
+/// Returns the message for the invalid token with the lexeme at the line and column, which
+/// the lexer accepted as a whole but is not a valid string or number.
+fn invalid_message(line: u32, column: u32, lexeme: &str) -> String {
+    match lexeme {
+        l if l.starts_with('"') => format!("({}, {}) The string {} has an unknown escape sequence.",
+            line, column, lexeme),
+        "0x" | "0X" | "0b" | "0B" => format!("({}, {}) \"{}\" is the prefix of a number without any digits.",
+            line, column, lexeme),
+        l if l.ends_with('.') => format!("({}, {}) \"{}\" is a number with a decimal point but no digits after it.",
+            line, column, lexeme),
+        _ => format!("({}, {}) \"{}\" is a number followed by letters, which is not a valid number or identifier.",
+            line, column, lexeme),
+    }
 }
 
 impl fmt::Display for Token {
@@ -209,10 +386,22 @@ pub struct TokenBuilder {
     /// The starting column number for the current token.
     column: u32,
 
+    /// The line of the next character, where the current token ends so far.
+    end_line: u32,
+
+    /// The column of the next character, where the current token ends so far.
+    end_column: u32,
+
     /// The current lexeme for the token.
     ///
     /// This is pushed onto as characters are input.
     lexeme: String,
+
+    /// Whether a number immediately followed by letters is an invalid token.
+    strict_numbers: bool,
+
+    /// Whether only the exact spelling of each keyword is accepted.
+    strict_keywords: bool,
 }
 
 impl TokenBuilder {
@@ -221,13 +410,29 @@ impl TokenBuilder {
         TokenBuilder {
             line: line,
             column: column,
+            end_line: line,
+            end_column: column,
 
             lexeme: String::new(),
 
             token_state: TokenState::Start,
+
+            strict_numbers: false,
+            strict_keywords: false,
         }
     }
 
+    /// Sets whether a number immediately followed by letters is an invalid token instead
+    /// of a number and an identifier.
+    pub fn set_strict_numbers(&mut self, strict_numbers: bool) {
+        self.strict_numbers = strict_numbers;
+    }
+
+    /// Sets whether keyword synonyms such as "boolean" are identifiers instead.
+    pub fn set_strict_keywords(&mut self, strict_keywords: bool) {
+        self.strict_keywords = strict_keywords;
+    }
+
     /// Returns true if the TokenBuilder is at the start state, false otherwise.
     pub fn is_start(&self) -> bool {
         match self.token_state {
@@ -236,17 +441,68 @@ impl TokenBuilder {
         }
     }
 
+    /// Returns Some((line, column)) where the (* *) or { } comment the TokenBuilder is
+    /// inside of started, or None if it is not inside one.
+    pub fn open_comment(&self) -> Option<(u32, u32)> {
+        match self.token_state {
+            TokenState::CommentCurly(_)
+            | TokenState::CommentParen(_)
+            | TokenState::CommentParenOpen(_)
+            | TokenState::CommentParenClose(_) => Some((self.line, self.column)),
+            _ => None,
+        }
+    }
+
+    /// Returns Some(t) where t is an invalid token at the start of the string the
+    /// TokenBuilder is inside of when the input ends, with everything after its opening
+    /// quote but the final newline, or None if it is not inside one. The token's message
+    /// says where the string starts.
+    pub fn unterminated_string(&self) -> Option<Token> {
+        match self.token_state {
+            TokenState::String | TokenState::StringEscape(_) | TokenState::InvalidString => {
+                Some(Token {
+                    token_type: TokenType::Invalid,
+                    line: self.line,
+                    column: self.column,
+                    end_line: self.end_line,
+                    end_column: self.end_column,
+                    lexeme: self.lexeme.trim_end_matches('\n').to_string(),
+                    file: None,
+                    message: Some(format!("Unterminated string starting at ({}, {}).", self.line, self.column)),
+                })
+            },
+            _ => None,
+        }
+    }
+
     /// Takes a character and pushes it to the lexeme and advances the state,
     /// returns true if it reaches a final (accepting or invalid) state as well as
     /// Some(t) where t is the generated token.
     pub fn push_char(&mut self, c: char) -> (Option<Token>, bool) {
         // Advance the state based on the character
-        self.token_state = self.token_state.next_state(c);
-        let mut pushback = false;
+        let escaping = matches!(self.token_state, TokenState::StringEscape(_));
+        self.token_state = self.token_state.next_state(c, self.strict_numbers);
+
+        // A character which is pushed back is read again for the next token, so it does not
+        // extend this one. The character after a lone slash is pushed back so the slash is
+        // invalid by itself.
+        let pushback = match self.token_state {
+            TokenState::Accept(TokenAction::AcceptPushback, _) => true,
+            TokenState::Unaccepted => self.lexeme == "/",
+            _ => false,
+        };
+        if !pushback {
+            self.advance(c);
+        }
 
         match self.token_state {
             TokenState::Start => self.lexeme = String::new(),
 
+            // The backslash of an escape is not part of the lexeme, the character it stands
+            // for is
+            TokenState::StringEscape(_) => {},
+            _ if escaping => self.lexeme.push(escaped_char(c).unwrap_or(c)),
+
             _ => {self.lexeme.push(c)}
         }
 
@@ -258,7 +514,6 @@ impl TokenBuilder {
             TokenState::Accept(action, _) => {
                 let final_lexeme = match action {
                     TokenAction::AcceptPushback => {
-                        pushback = true;
                         self.lexeme.pop();
                         self.lexeme.clone()
                     }
@@ -268,14 +523,23 @@ impl TokenBuilder {
                 };
 
 
+                let message = match self.token_state {
+                    TokenState::Accept(_, TokenType::Invalid) => Some(invalid_message(self.line, self.column, &final_lexeme)),
+                    _ => None,
+                };
+
                 let result = match action {
                     _ => {
                         Some(Token {
                            token_type: self.final_type(),
                            line: self.line,
                            column: self.column,
+                           end_line: self.end_line,
+                           end_column: self.end_column,
                            // Check if the action requires that we push back
-                           lexeme: final_lexeme
+                           lexeme: final_lexeme,
+                           file: None,
+                           message: message,
                        })
                     }
                 };
@@ -284,16 +548,20 @@ impl TokenBuilder {
             },
 
             TokenState::Unaccepted => {
+                if pushback {
+                    self.lexeme.pop();
+                }
                 let lexeme = self.lexeme.clone();
 
-                println!("<YASLC/Lexer> Warning: Invalid token found at ({}, {}) with lexeme \"{}\".",
-                    self.line, self.column, lexeme);
-
                 Some(Token {
                     token_type: TokenType::Invalid,
                     line: self.line,
                     column: self.column,
+                    end_line: self.end_line,
+                    end_column: self.end_column,
+                    message: Some(unaccepted_message(self.line, self.column, &lexeme)),
                     lexeme: lexeme,
+                    file: None,
                 })
             }
 
@@ -336,24 +604,42 @@ impl TokenBuilder {
             "const" => Some(Const),
             "begin" => Some(Begin),
             "print" => Some(Print),
+            "write" => Some(Write),
+            "return" => Some(Return),
+            "swap" => Some(Swap),
             "end" => Some(End),
             "div" => Some(Div),
             "mod" => Some(Mod),
             "var" => Some(Var),
             "int" => Some(Int),
             "bool" => Some(Bool),
+            "char" => Some(Char),
+            "boolean" if !self.strict_keywords => Some(Bool),
+            "string" => Some(Str),
+            "real" => Some(Real),
             "proc" => Some(Proc),
             "if" => Some(If),
             "then" => Some(Then),
             "else" => Some(Else),
+            "elsif" => Some(Elsif),
             "while" => Some(While),
             "do" => Some(Do),
+            "for" => Some(For),
+            "to" => Some(To),
             "prompt" => Some(Prompt),
             "and" => Some(And),
             "or" => Some(Or),
             "not" => Some(Not),
             "true" => Some(True),
             "false" => Some(False),
+            "asm" => Some(Asm),
+            "band" => Some(BAnd),
+            "bor" => Some(BOr),
+            "bxor" => Some(BXor),
+            "shl" => Some(Shl),
+            "shr" => Some(Shr),
+            "array" => Some(Array),
+            "of" => Some(Of),
             _ => None,
         }
     }
@@ -361,11 +647,23 @@ impl TokenBuilder {
     /// Returns the line where the current token started.
     pub fn line(&mut self, line: u32) {
         self.line = line;
+        self.end_line = line;
     }
 
     /// Returns the column where the current column started.
     pub fn column(&mut self, column: u32) {
         self.column = column;
+        self.end_column = column;
+    }
+
+    /// Moves the end of the current token past the character.
+    fn advance(&mut self, c: char) {
+        if c == '\n' {
+            self.end_line += 1;
+            self.end_column = 1;
+        } else {
+            self.end_column += 1;
+        }
     }
 }
 
@@ -383,17 +681,57 @@ enum TokenState {
     Identifier, // 1
 
     Number, // 2
+    MalformedNumber,
+
+    // After the decimal point of a real number and after the digits following it
+    NumberPoint,
+    Fraction,
+
+    // After a leading 0, which may start a hexadecimal or binary number
+    Zero,
+
+    // After the 0x of a hexadecimal number and after its digits
+    HexStart,
+    Hex,
+
+    // After the 0b of a binary number and after its digits
+    BinaryStart,
+    Binary,
 
     String, // 3
 
-    CommentCurly, // 5
+    // After a backslash inside a string, whether the string is still valid, and inside a
+    // string after an escape which is not valid
+    StringEscape(bool),
+    InvalidString,
+
+    // After the opening quote of a char and after its character
+    CharStart,
+    CharEnd,
+
+    Directive,
+
+    // The comment depth of a { } comment
+    CommentCurly(u32), // 5
     CommentSlashStart, // 6
     CommentSlash, // 7
 
+    // The comment depth of a (* *) comment, after a ( and after a * inside it
+    CommentParen(u32),
+    CommentParenOpen(u32),
+    CommentParenClose(u32),
+
+    ParenStart,
+
     GTStart,
     LTStart,
     EqualStart,
 
+    // After an operator which may be the start of a compound assignment, as in "+="
+    PlusStart,
+    MinusStart,
+    StarStart,
+
     Accept(TokenAction, TokenType),
     Unaccepted,
 }
@@ -408,7 +746,7 @@ enum TokenAction {
 
 impl TokenState {
     /// Returns the next state given the current state and the input character.
-    fn next_state(&self, input: char) -> TokenState {
+    fn next_state(&self, input: char, strict_numbers: bool) -> TokenState {
         match *self {
             // Starting state
             TokenState::Start => {
@@ -419,12 +757,16 @@ impl TokenState {
                     TokenState::Identifier
                 } else if let Some(input_digit) = input.to_digit(10) {
                     if input_digit == 0 {
-                        TokenState::Accept(TokenAction::Accept, TokenType::Number)
+                        TokenState::Zero
                     } else {
                         TokenState::Number
                     }
                 } else if input == '"' {
                     TokenState::String
+                } else if input == '\'' {
+                    TokenState::CharStart
+                } else if input == '#' {
+                    TokenState::Directive
                 } else if input == '.' {
                     TokenState::Accept(TokenAction::Accept, TokenType::Period)
                 } else if input == ';' {
@@ -432,11 +774,13 @@ impl TokenState {
                 } else if input == ',' {
                     TokenState::Accept(TokenAction::Accept, TokenType::Comma)
                 } else if input == '+' {
-                    TokenState::Accept(TokenAction::Accept, TokenType::Plus)
+                    TokenState::PlusStart
                 } else if input == '-' {
-                    TokenState::Accept(TokenAction::Accept, TokenType::Minus)
+                    TokenState::MinusStart
                 } else if input == '*' {
-                    TokenState::Accept(TokenAction::Accept, TokenType::Star)
+                    TokenState::StarStart
+                } else if input == '%' {
+                    TokenState::Accept(TokenAction::Accept, TokenType::Percent)
                 } else if input == '>' {
                     TokenState::GTStart
                 } else if input == '<' {
@@ -448,11 +792,15 @@ impl TokenState {
                 }else if input == '/' {
                     TokenState::CommentSlashStart
                 } else if input == '{' {
-                    TokenState::CommentCurly
+                    TokenState::CommentCurly(1)
                 } else if input == '(' {
-                    TokenState::Accept(TokenAction::Accept, TokenType::LeftParen)
+                    TokenState::ParenStart
                 } else if input == ')' {
                     TokenState::Accept(TokenAction::Accept, TokenType::RightParen)
+                } else if input == '[' {
+                    TokenState::Accept(TokenAction::Accept, TokenType::LeftBracket)
+                } else if input == ']' {
+                    TokenState::Accept(TokenAction::Accept, TokenType::RightBracket)
                 }
                 else {
                     let i = input as u8;
@@ -462,7 +810,7 @@ impl TokenState {
             },
 
             TokenState::Identifier => {
-                if input.is_alphabetic(){
+                if input.is_alphabetic() || input == '_' {
                     TokenState::Identifier
                 } else if let Some(_) = input.to_digit(10) {
                     TokenState::Identifier
@@ -474,24 +822,134 @@ impl TokenState {
             TokenState::Number => {
                 if let Some(_) = input.to_digit(10) {
                     TokenState::Number
+                } else if input == '.' {
+                    TokenState::NumberPoint
+                } else if strict_numbers && input.is_alphabetic() {
+                    TokenState::MalformedNumber
                 } else {
                     TokenState::Accept(TokenAction::AcceptPushback, TokenType::Number)
                 }
             }
 
+            TokenState::Zero => {
+                if input == 'x' || input == 'X' {
+                    TokenState::HexStart
+                } else if input == 'b' || input == 'B' {
+                    TokenState::BinaryStart
+                } else if input == '.' {
+                    TokenState::NumberPoint
+                } else if strict_numbers && input.is_alphanumeric() {
+                    TokenState::MalformedNumber
+                } else {
+                    TokenState::Accept(TokenAction::AcceptPushback, TokenType::Number)
+                }
+            }
+
+            TokenState::NumberPoint | TokenState::Fraction => {
+                if input.is_ascii_digit() {
+                    TokenState::Fraction
+                } else if let TokenState::NumberPoint = *self {
+                    TokenState::Accept(TokenAction::AcceptPushback, TokenType::Invalid)
+                } else if strict_numbers && input.is_alphabetic() {
+                    TokenState::MalformedNumber
+                } else {
+                    TokenState::Accept(TokenAction::AcceptPushback, TokenType::Number)
+                }
+            }
+
+            TokenState::HexStart | TokenState::Hex => {
+                if input.is_ascii_hexdigit() {
+                    TokenState::Hex
+                } else if let TokenState::HexStart = *self {
+                    TokenState::Accept(TokenAction::AcceptPushback, TokenType::Invalid)
+                } else if strict_numbers && input.is_alphanumeric() {
+                    TokenState::MalformedNumber
+                } else {
+                    TokenState::Accept(TokenAction::AcceptPushback, TokenType::Number)
+                }
+            }
+
+            TokenState::BinaryStart | TokenState::Binary => {
+                if input == '0' || input == '1' {
+                    TokenState::Binary
+                } else if let TokenState::BinaryStart = *self {
+                    TokenState::Accept(TokenAction::AcceptPushback, TokenType::Invalid)
+                } else if strict_numbers && input.is_alphanumeric() {
+                    TokenState::MalformedNumber
+                } else {
+                    TokenState::Accept(TokenAction::AcceptPushback, TokenType::Number)
+                }
+            }
+
+            TokenState::MalformedNumber => {
+                if input.is_alphanumeric() {
+                    TokenState::MalformedNumber
+                } else {
+                    TokenState::Accept(TokenAction::AcceptPushback, TokenType::Invalid)
+                }
+            }
+
+            TokenState::Directive => {
+                if input.is_alphabetic() {
+                    TokenState::Directive
+                } else {
+                    TokenState::Accept(TokenAction::AcceptPushback, TokenType::Directive)
+                }
+            },
+
             TokenState::String => {
                 if input == '"' {
                     TokenState::Accept(TokenAction::Accept, TokenType::String)
+                } else if input == '\\' {
+                    TokenState::StringEscape(true)
                 } else {
                     TokenState::String
                 }
             },
 
-            TokenState::CommentCurly => {
-                if input == '}' {
+            TokenState::StringEscape(valid) => {
+                if valid && escaped_char(input).is_some() {
+                    TokenState::String
+                } else {
+                    TokenState::InvalidString
+                }
+            },
+
+            TokenState::InvalidString => {
+                if input == '"' {
+                    TokenState::Accept(TokenAction::Accept, TokenType::Invalid)
+                } else if input == '\\' {
+                    TokenState::StringEscape(false)
+                } else {
+                    TokenState::InvalidString
+                }
+            },
+
+            TokenState::CharStart => {
+                if input == '\'' || input == '\n' {
+                    TokenState::Unaccepted
+                } else {
+                    TokenState::CharEnd
+                }
+            },
+
+            TokenState::CharEnd => {
+                if input == '\'' {
+                    TokenState::Accept(TokenAction::Accept, TokenType::Char)
+                } else {
+                    TokenState::Unaccepted
+                }
+            },
+
+            TokenState::CommentCurly(depth) => {
+                if input == '{' {
+                    TokenState::CommentCurly(depth + 1)
+                } else if input == '}' && depth == 1 {
                     TokenState::Start
+                } else if input == '}' {
+                    TokenState::CommentCurly(depth - 1)
                 } else {
-                    TokenState::CommentCurly
+                    TokenState::CommentCurly(depth)
                 }
             },
 
@@ -511,6 +969,48 @@ impl TokenState {
                 }
             }
 
+            TokenState::ParenStart => {
+                if input == '*' {
+                    TokenState::CommentParen(1)
+                } else {
+                    TokenState::Accept(TokenAction::AcceptPushback, TokenType::LeftParen)
+                }
+            },
+
+            TokenState::CommentParen(depth) => {
+                if input == '(' {
+                    TokenState::CommentParenOpen(depth)
+                } else if input == '*' {
+                    TokenState::CommentParenClose(depth)
+                } else {
+                    TokenState::CommentParen(depth)
+                }
+            },
+
+            TokenState::CommentParenOpen(depth) => {
+                if input == '*' {
+                    TokenState::CommentParen(depth + 1)
+                } else if input == '(' {
+                    TokenState::CommentParenOpen(depth)
+                } else {
+                    TokenState::CommentParen(depth)
+                }
+            },
+
+            TokenState::CommentParenClose(depth) => {
+                if input == ')' && depth == 1 {
+                    TokenState::Start
+                } else if input == ')' {
+                    TokenState::CommentParen(depth - 1)
+                } else if input == '*' {
+                    TokenState::CommentParenClose(depth)
+                } else if input == '(' {
+                    TokenState::CommentParenOpen(depth)
+                } else {
+                    TokenState::CommentParen(depth)
+                }
+            },
+
             TokenState::GTStart => {
                 if input == '=' {
                     return TokenState::Accept(TokenAction::Accept, TokenType::GreaterThanOrEqual);
@@ -529,6 +1029,20 @@ impl TokenState {
                 }
             }
 
+            TokenState::PlusStart | TokenState::MinusStart | TokenState::StarStart => {
+                let (operator, assign) = match *self {
+                    TokenState::PlusStart => (TokenType::Plus, TokenType::PlusAssign),
+                    TokenState::MinusStart => (TokenType::Minus, TokenType::MinusAssign),
+                    _ => (TokenType::Star, TokenType::StarAssign),
+                };
+
+                if input == '=' {
+                    TokenState::Accept(TokenAction::Accept, assign)
+                } else {
+                    TokenState::Accept(TokenAction::AcceptPushback, operator)
+                }
+            }
+
             TokenState::EqualStart => {
                 if input == '=' {
                     return TokenState::Accept(TokenAction::Accept, TokenType::EqualTo);
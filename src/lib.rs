@@ -1,29 +1,361 @@
 mod lexer;
 mod parser;
 
-use lexer::{LexerResult, LexerError};
-use lexer::read_file;
-
-pub use parser::{Parser, ParserResult};
-
-pub fn compile_file(file_name: String) -> ParserResult {
-    let tokens = match read_file(file_name) {
-        LexerResult::Ok(t) => t,
-        LexerResult::Err(e) => {
-            match e {
-                LexerError::FileError => println!("<YASLC> Encountered a file error!"),
-                LexerError::StdinError => println!("<YASLC> Encountered an error with stdin!"),
-            };
-
-            println!("<YASLC/Lexer> Error reading file. Attempting to find the error...");
-            let os_error = std::io::Error::last_os_error();
-            println!("This is the last OS error we could find: {}", os_error);
+use lexer::LexerResult;
+use lexer::{read_string, read_string_with, read_string_filtered, read_string_in_file, stream_string, preprocess};
+
+pub use lexer::{Token, TokenType, KeywordType, TokenStream, LexerError, LexerOptions};
+pub use parser::{Diagnostic, Severity, Parser, ParserOptions, ParserResult, Syntax, Symbol, SymbolTable, SymbolType, SymbolValueType, type_of, run, from_ir_json};
+
+use std::fmt;
+
+/// CompileOptions holds everything that changes how a program is compiled and where it
+/// is written.
+#[derive(Clone)]
+pub struct CompileOptions {
+    /// The options for the parser.
+    pub parser: ParserOptions,
+
+    /// The name of the file to write the compiled program to.
+    pub output: String,
+
+    /// Compile the program without writing any files.
+    pub dry_run: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> CompileOptions {
+        CompileOptions {
+            parser: ParserOptions::default(),
+            output: "out.pal".to_string(),
+            dry_run: false,
+        }
+    }
+}
+
+impl CompileOptions {
+    /// Returns the optimization level of the parser options.
+    pub fn optimization_level(&self) -> u32 {
+        self.parser.optimization_level()
+    }
+
+    /// Sets the optimization level of the parser options, see
+    /// ParserOptions::set_optimization_level.
+    pub fn set_optimization_level(&mut self, level: u32) {
+        self.parser.set_optimization_level(level);
+    }
+}
+
+/// CompileOutput is the result of a successful compile.
+pub struct CompileOutput {
+    /// The lines of the compiled program.
+    pub program: Vec<String>,
+
+    /// The compiled program as the text of the file it is written to.
+    pub text: String,
+
+    /// The warnings found while compiling.
+    pub warnings: Vec<String>,
+}
+
+/// CompileError is the reason a compile failed.
+#[derive(Debug)]
+pub enum CompileError {
+    /// The source could not be read or preprocessed.
+    Input(String),
+
+    /// The program is not valid, with the errors that were found.
+    Program(Vec<String>),
+
+    /// The source has a token which is not valid, at the line and column with the lexeme.
+    Lexical { line: u32, column: u32, lexeme: String },
+
+    /// The parser stopped at a token it did not expect, at the line and column with the
+    /// lexeme it found.
+    UnexpectedToken { line: u32, column: u32, found: String },
+
+    /// The program uses a name which was never declared.
+    UndeclaredSymbol { name: String },
+
+    /// The compiled program could not be written.
+    Output(String),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CompileError::Input(ref e) => write!(f, "Unable to read the input: {}", e),
+            CompileError::Program(ref errors) => write!(f, "The program has {} error(s)", errors.len()),
+            CompileError::Lexical { line, column, ref lexeme } => write!(f, "({}, {}) Invalid token \"{}\"", line, column, lexeme),
+            CompileError::UnexpectedToken { line, column, ref found } => write!(f, "({}, {}) Unexpected token \"{}\"", line, column, found),
+            CompileError::UndeclaredSymbol { ref name } => write!(f, "`{}` has not been declared", name),
+            CompileError::Output(ref e) => write!(f, "Unable to write the output: {}", e),
+        }
+    }
+}
+
+/// RunError is the reason running a program failed.
+#[derive(Debug)]
+pub enum RunError {
+    /// The program could not be compiled.
+    Compile(CompileError),
+
+    /// The compiled program stopped with an error while it was running.
+    Runtime(String),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RunError::Compile(ref e) => write!(f, "{}", e),
+            RunError::Runtime(ref e) => write!(f, "Runtime error: {}", e),
+        }
+    }
+}
+
+/// Compiles the file with the given name, writes the program and returns Ok(asm) where asm
+/// is the text of the program, or Err(e) where e is why it could not be compiled.
+pub fn compile_file(file_name: String) -> Result<String, CompileError> {
+    let source = read_source(&file_name)?;
+    compile_tokens(read_string(source.clone()), &source, CompileOptions::default()).map(|o| o.text)
+}
+
+/// Compiles the file with the given name using the options for the parser.
+pub fn compile_file_with(file_name: String, options: ParserOptions) -> ParserResult {
+    let options = CompileOptions {
+        parser: options,
+        ..CompileOptions::default()
+    };
+    result_of(read_source(&file_name).and_then(|source| {
+        compile_tokens(read_string_with(source.clone(), &options.parser.lexer_options()), &source, options)
+    }))
+}
+
+/// Compiles the source without reading or writing any files, for hosts such as a web
+/// playground where there is no filesystem.
+pub fn compile_string(source: String) -> ParserResult {
+    let options = CompileOptions {
+        dry_run: true,
+        ..CompileOptions::default()
+    };
+    result_of(compile_tokens(read_string(source.clone()), &source, options))
+}
+
+/// Compiles the source without reading or writing any files and returns Ok(asm) where asm
+/// is the text of the compiled program, or Err(r) where r is why it did not compile.
+pub fn compile_string_to_asm(source: String) -> Result<String, ParserResult> {
+    let options = CompileOptions {
+        dry_run: true,
+        ..CompileOptions::default()
+    };
+    match compile_tokens(read_string(source.clone()), &source, options) {
+        Ok(output) => Ok(output.text),
+        Err(_) => Err(ParserResult::Unexpected),
+    }
+}
+
+/// Compiles the source using the options and returns the compiled program, or Err(e)
+/// where e is the reason it could not be compiled.
+pub fn compile_with_options(source: String, options: CompileOptions) -> Result<CompileOutput, CompileError> {
+    compile_tokens(read_string_with(source.clone(), &options.parser.lexer_options()), &source, options)
+}
+
+/// Compiles several sources as a single program, one after the other, where each source is
+/// given with the name of its file so warnings and errors say which file they are in.
+pub fn compile_sources(sources: Vec<(String, String)>, options: CompileOptions) -> Result<CompileOutput, CompileError> {
+    let mut tokens = Vec::<Token>::new();
+    let mut file_names = Vec::<String>::new();
+    for (n, (name, source)) in sources.into_iter().enumerate() {
+        match tokens_of(read_string_in_file(source, &options.parser.lexer_options(), n as u32)) {
+            Ok(t) => tokens.extend(t),
+            Err(CompileError::Input(e)) => return Err(CompileError::Input(format!("{} {}", name, e))),
+            Err(e) => return Err(e),
+        };
+        file_names.push(name);
+    }
+
+    compile_parsed(tokens, "", file_names, options)
+}
+
+/// Compiles the file with the given name without writing anything, then runs it with no
+/// input and returns everything it output.
+pub fn run_file(file_name: String) -> Result<String, RunError> {
+    let options = CompileOptions {
+        dry_run: true,
+        ..CompileOptions::default()
+    };
+
+    let output = match read_source(&file_name).and_then(|source| compile_tokens(read_string(source.clone()), &source, options)) {
+        Ok(o) => o,
+        Err(e) => return Err(RunError::Compile(e)),
+    };
+
+    run(&output.program, "").map_err(RunError::Runtime)
+}
+
+/// Compiles a single expression into a program which prints its value.
+pub fn compile_expression(expression: String) -> ParserResult {
+    compile_expression_with(expression, ParserOptions::default())
+}
+
+/// Compiles a single expression into a program which prints its value using the options for
+/// the parser.
+pub fn compile_expression_with(expression: String, options: ParserOptions) -> ParserResult {
+    let options = CompileOptions {
+        parser: ParserOptions {
+            expression_only: true,
+            ..options
+        },
+        ..CompileOptions::default()
+    };
+    result_of(compile_tokens(read_string_with(expression.clone(), &options.parser.lexer_options()), &expression, options))
+}
+
+/// Splits the source into tokens for tools such as highlighters and returns Ok(tokens),
+/// where invalid tokens are kept as TokenType::Invalid so they can be marked, or Err(e)
+/// where e is why the source can not be split into tokens.
+pub fn tokenize(source: String) -> Result<Vec<Token>, LexerError> {
+    match read_string(source) {
+        LexerResult::Ok(t) => Ok(t),
+        LexerResult::Err(e) => Err(e),
+    }
+}
+
+/// Splits the source into tokens for tools such as highlighters and returns Some((tokens,
+/// invalid)) where tokens are the valid tokens and invalid are the invalid ones which were
+/// left out, or None if the source can not be split into tokens at all.
+pub fn tokenize_filtered(source: String) -> Option<(Vec<Token>, Vec<Token>)> {
+    read_string_filtered(source, &LexerOptions::default()).ok()
+}
+
+/// Returns an iterator over the tokens of the source which splits it into tokens as they
+/// are asked for, so tools can stop early without scanning the whole source.
+pub fn tokenize_stream(source: String) -> TokenStream {
+    stream_string(source, &LexerOptions::default())
+}
+
+/// Preprocesses and parses the tokens without writing the compiled program anywhere, with
+/// no symbols defined for #if. Errors in the tokens are returned as results instead of
+/// panicking, so any tokens can be given, which makes it suitable for fuzzing the parser.
+pub fn parse_tokens(tokens: Vec<Token>) -> ParserResult {
+    let tokens = match preprocess(tokens, &[]) {
+        Ok(t) => t,
+        Err(e) => {
+            println!("<YASLC/Preprocessor> Error: {}", e);
             return ParserResult::Unexpected;
         }
     };
 
-    println!("<YASLC> Successful lexical analysis of file. Parsing.");
+    Parser::new_with_tokens(tokens).compile()
+}
+
+/// Parses the result of lexical analysis of the source using the options and writes the
+/// program unless it is a dry run.
+fn compile_tokens(lexer_result: LexerResult, source: &str, options: CompileOptions) -> Result<CompileOutput, CompileError> {
+    let tokens = tokens_of(lexer_result)?;
+    compile_parsed(tokens, source, Vec::new(), options)
+}
+
+/// Reads the file with the given name and returns Ok(source), or Err(e) where e is the
+/// error the OS gave when it could not be read.
+fn read_source(file_name: &str) -> Result<String, CompileError> {
+    std::fs::read_to_string(file_name).map_err(|e| {
+        println!("<YASLC> Encountered a file error: {}", e);
+        CompileError::Input(format!("{}", e))
+    })
+}
+
+/// Returns Ok(tokens) with the tokens of the result of lexical analysis, or Err(e) where e
+/// is why the input could not be read.
+fn tokens_of(lexer_result: LexerResult) -> Result<Vec<Token>, CompileError> {
+    match lexer_result {
+        LexerResult::Ok(t) => Ok(t),
+        LexerResult::Err(LexerError::UnterminatedComment(line, column)) => {
+            let error = format!("({}, {}) Unterminated comment", line, column);
+            println!("<YASLC/Lexer> Error: {}", error);
+            Err(CompileError::Input(error))
+        },
+        LexerResult::Err(LexerError::FileError(e)) => {
+            println!("<YASLC> Encountered a file error: {}", e);
+            Err(CompileError::Input(format!("{}", e)))
+        },
+        LexerResult::Err(LexerError::StdinError) => {
+            println!("<YASLC> Encountered an error with stdin!");
+            Err(CompileError::Input("Unable to read stdin".to_string()))
+        },
+    }
+}
+
+/// Preprocesses and parses the tokens of the source, whose files are named by file_names,
+/// using the options and writes the program unless it is a dry run.
+fn compile_parsed(tokens: Vec<Token>, source: &str, file_names: Vec<String>, options: CompileOptions) -> Result<CompileOutput, CompileError> {
+    let tokens = match preprocess(tokens, &options.parser.defines) {
+        Ok(t) => t,
+        Err(e) => {
+            println!("<YASLC/Preprocessor> Error: {}", e);
+            return Err(CompileError::Input(e));
+        }
+    };
+
+    for m in tokens.iter().filter(|t| t.is_type(TokenType::Invalid)).filter_map(|t| t.message()) {
+        println!("<YASLC/Lexer> Error: {}", m);
+    }
+    if let Some(t) = tokens.iter().find(|t| t.is_type(TokenType::Invalid)) {
+        return Err(CompileError::Lexical {
+            line: t.line(),
+            column: t.column(),
+            lexeme: t.lexeme(),
+        });
+    }
+
+    println!("<YASLC> Successful lexical analysis of input. Parsing.");
+
+    let mut parser = Parser::new_with_options(tokens, options.parser);
+    parser.set_source(source);
+    parser.set_file_names(file_names);
+    match parser.compile() {
+        ParserResult::Success => {},
+        _ => return Err(failure_of(&parser)),
+    };
+
+    if !options.dry_run {
+        if let Err(e) = parser.write_files(&options.output) {
+            return Err(CompileError::Output(format!("{}", e)));
+        }
+    }
+
+    Ok(CompileOutput {
+        program: parser.listing(),
+        text: parser.compiled_output(),
+        warnings: parser.warnings().to_vec(),
+    })
+}
+
+/// Returns why the parser failed, the errors it diagnosed if there were any, otherwise the
+/// name which was not declared or the token it stopped at.
+fn failure_of(parser: &Parser) -> CompileError {
+    if !parser.errors().is_empty() {
+        return CompileError::Program(parser.errors().to_vec());
+    }
+
+    if let Some(name) = parser.undeclared() {
+        return CompileError::UndeclaredSymbol { name: name.to_string() };
+    }
+
+    match parser.unexpected_token() {
+        Some(t) => CompileError::UnexpectedToken {
+            line: t.line(),
+            column: t.column(),
+            found: t.lexeme(),
+        },
+        None => CompileError::Program(Vec::new()),
+    }
+}
 
-    let mut parser = Parser::new_with_tokens(tokens);
-    parser.parse()
+/// Returns the parser result for the result of a compile.
+fn result_of(result: Result<CompileOutput, CompileError>) -> ParserResult {
+    match result {
+        Ok(_) => ParserResult::Success,
+        Err(_) => ParserResult::Unexpected,
+    }
 }
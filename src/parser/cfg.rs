@@ -0,0 +1,94 @@
+/// parser/cfg.rs
+///
+/// The cfg module splits the generated program into basic blocks and finds the edges
+/// between them, producing a control flow graph that can be rendered in DOT format.
+
+use super::instruction::Instruction;
+
+/// BasicBlock is a run of operations that is only entered at its first operation and
+/// only left after its last.
+pub struct BasicBlock {
+    /// The label of the first operation in the block, if it has one.
+    pub label: Option<String>,
+
+    /// The operations in the block, in order.
+    pub instructions: Vec<Instruction>,
+}
+
+/// ControlFlowGraph is the list of basic blocks of a program and the edges between them.
+pub struct ControlFlowGraph {
+    /// The basic blocks in program order.
+    pub blocks: Vec<BasicBlock>,
+
+    /// The edges as (from, to) indices into blocks.
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl ControlFlowGraph {
+    /// Returns the control flow graph for the given list of instructions. Comments, blank
+    /// lines and data declarations are not part of any block.
+    pub fn from_instructions(instructions: &[Instruction]) -> ControlFlowGraph {
+        let mut blocks = Vec::<BasicBlock>::new();
+
+        // Blocks start at labels and after any jump, branch or terminator
+        let mut start_next = true;
+        for i in instructions.iter().filter(|i| i.is_op()) {
+            if start_next || i.label().is_some() {
+                blocks.push(BasicBlock {
+                    label: i.label().map(|l| l.to_string()),
+                    instructions: Vec::<Instruction>::new(),
+                });
+            }
+
+            start_next = i.branch_target().is_some() || i.is_terminator();
+
+            let last = blocks.len() - 1;
+            blocks[last].instructions.push(i.clone());
+        }
+
+        let mut edges = Vec::<(usize, usize)>::new();
+        for (n, b) in blocks.iter().enumerate() {
+            let last = match b.instructions.last() {
+                Some(i) => i,
+                None => continue,
+            };
+
+            if let Some(target) = last.branch_target() {
+                if let Some(t) = blocks.iter().position(|b| b.label.as_deref() == Some(target)) {
+                    edges.push((n, t));
+                }
+            }
+
+            if !last.is_terminator() && n + 1 < blocks.len() {
+                edges.push((n, n + 1));
+            }
+        }
+
+        ControlFlowGraph {
+            blocks: blocks,
+            edges: edges,
+        }
+    }
+
+    /// Returns the graph in DOT format, one line per entry.
+    pub fn to_dot(&self) -> Vec<String> {
+        let mut lines = Vec::<String>::new();
+        lines.push("digraph cfg {".to_string());
+        lines.push("    node [shape=box, fontname=monospace];".to_string());
+
+        for (n, b) in self.blocks.iter().enumerate() {
+            let mut text = String::new();
+            for i in b.instructions.iter() {
+                text.push_str(&format!("{}\\l", i));
+            }
+            lines.push(format!("    b{} [label=\"{}\"];", n, text));
+        }
+
+        for &(from, to) in self.edges.iter() {
+            lines.push(format!("    b{} -> b{};", from, to));
+        }
+
+        lines.push("}".to_string());
+        lines
+    }
+}
@@ -0,0 +1,68 @@
+/// parser/diagnostic.rs
+///
+/// The diagnostic module holds the errors and warnings found while compiling, which are
+/// written for people by default or as JSON lines for tools such as editors.
+
+use super::ir::quoted;
+
+use std::fmt;
+
+/// Severity is how serious a diagnostic is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Diagnostic is a single error or warning with where it was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    pub message: String,
+
+    /// The name of the source file, if the program was compiled from several.
+    pub file: Option<String>,
+
+    /// The line and column of the token it is about, if there is one.
+    pub position: Option<(u32, u32)>,
+}
+
+impl Diagnostic {
+    /// Returns the diagnostic as a single line JSON object with the fields severity,
+    /// message, file, line and column, where any which are unknown are null.
+    pub fn to_json(&self) -> String {
+        let file = match self.file {
+            Some(ref f) => quoted(f),
+            None => "null".to_string(),
+        };
+        let (line, column) = match self.position {
+            Some((l, c)) => (l.to_string(), c.to_string()),
+            None => ("null".to_string(), "null".to_string()),
+        };
+
+        format!("{{\"severity\": {}, \"message\": {}, \"file\": {}, \"line\": {}, \"column\": {}}}",
+                quoted(&self.severity.to_string()), quoted(&self.message), file, line, column)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref file) = self.file {
+            write!(f, "{} ", file)?;
+        }
+        if let Some((line, column)) = self.position {
+            write!(f, "({}, {}) ", line, column)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
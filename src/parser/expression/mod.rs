@@ -1,4 +1,4 @@
-/// parser/expression.rs
+/// parser/expression/mod.rs
 ///
 /// The expression module contains the expression parser and all the implementation
 /// for the expression parser.
@@ -8,49 +8,51 @@ mod tests;
 
 pub use super::{Token, TokenType, KeywordType};
 pub use super::{Symbol, SymbolTable, SymbolType, SymbolValueType};
+use super::{number_value, real_value};
 use super::CommandBuilder;
 
+use std::cell::Cell;
 use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::fmt;
 
-/// Set to true if you want the expression parser to print its process.
-static mut VERBOSE: bool = true;
-
+/// Prints the message when verbose is true, without a newline after NNL.
 macro_rules! log {
-    ($message:expr $(,$arg:expr)*) => {
-        unsafe {
-            if VERBOSE == true {
-                println!($message, $($arg,)*);
-            }
+    (NNL $verbose:expr, $message:expr $(,$arg:expr)*) => {
+        if $verbose {
+            print!($message, $($arg,)*);
         }
     };
 
-    (NNL $message:expr $(,$arg:expr)*) => {
-        unsafe {
-            if VERBOSE == true {
-                print!($message, $($arg,)*);
-            }
+    ($verbose:expr, $message:expr $(,$arg:expr)*) => {
+        if $verbose {
+            println!($message, $($arg,)*);
         }
     };
 }
 
 // Helper function
 pub fn type_for_string(l: &String) -> Option<SymbolValueType> {
-    // If the lexeme is numeric it's a number, otherwise if its "true"/"false its a boolean"
+    // If the lexeme is quoted it's a char, if it is numeric it's a number, otherwise if its
+    // "true"/"false its a boolean"
     // if its neither then crash
-    match l.parse::<i32>() {
-        Ok(_) => {
+    if l.starts_with('\'') {
+        return Some(SymbolValueType::Char);
+    }
+
+    match number_value(l) {
+        Some(_) => {
             // Its a number
-            log!("Determined that the type for string {} is int.", l);
             Some(SymbolValueType::Int)
         },
-        Err(_) => {
-            // It is not a number, check if it is a boolean
-            if l == "true" {
-                log!("Determined that the type for string {} is bool.", l);
+        None => {
+            // It is not a number, check if it is a real number or a boolean
+            if real_value(l).is_some() {
+                Some(SymbolValueType::Real)
+            } else if l == "true" {
                 Some(SymbolValueType::Bool)
             } else if l == "false" {
-                log!("Determined that the type for string {} is bool.", l);
                 Some(SymbolValueType::Bool)
             } else {
                 // We don't know what it is, crash.
@@ -61,6 +63,37 @@ pub fn type_for_string(l: &String) -> Option<SymbolValueType> {
     }
 }
 
+/// Returns the value of a static operand for an immediate, which is the character code of a
+/// char literal, the bits of the float of a real literal as an int and the operand itself
+/// otherwise.
+pub fn immediate_value(l: &str) -> String {
+    if let Some(r) = real_value(l) {
+        return ((r as f32).to_bits() as i32).to_string();
+    }
+
+    match l.strip_prefix('\'').and_then(|c| c.chars().next()) {
+        Some(c) => (c as u32).to_string(),
+        None => l.to_string(),
+    }
+}
+
+/// Returns the value type of the expression given by tokens using the symbol table, without
+/// generating any code. Useful for tooling which needs to know the type of an expression.
+pub fn type_of(table: SymbolTable, tokens: Vec<Token>) -> Result<SymbolValueType, String> {
+    match ExpressionParser::new(table, tokens) {
+        Some(e) => e.value_type(),
+        None => Err("<YASLC/ExpressionParser> Unable to parse the expression.".to_string()),
+    }
+}
+
+/// Returns true if t is one of the bitwise operators on integers, band, bor, bxor, shl and
+/// shr.
+fn is_bitwise(t: &TokenType) -> bool {
+    matches!(*t, TokenType::Keyword(KeywordType::BAnd) | TokenType::Keyword(KeywordType::BOr)
+             | TokenType::Keyword(KeywordType::BXor) | TokenType::Keyword(KeywordType::Shl)
+             | TokenType::Keyword(KeywordType::Shr))
+}
+
 #[derive(Eq, PartialEq, Clone)]
 enum OType {
     // String is the value of the variable
@@ -90,15 +123,32 @@ enum Expression {
     /// A combined expression using three other expressions, in
     /// operand - operator - operand format.
     Combined(Symbol),
+
+    /// A parenthesis grouping a subexpression, with the TokenType LeftParen or RightParen.
+    /// There are none left once the expressions are in postfix order.
+    Paren(TokenType),
+
+    /// A minus with no left operand, which negates the operand after it, as in "-5" or
+    /// "x * -y". It binds tighter than any other operator.
+    Negation,
 }
 
 impl Expression {
+    /// Whether the two expressions are binary operators of the same precedence, which
+    /// are each ordered below the other.
+    fn same_precedence(&self, other: &Expression) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Less) && other.partial_cmp(self) == Some(Ordering::Less)
+    }
+
     /// Creates a new expression from a token and returns Some(e) where e is a valid expression,
     /// or returns None if the expression is not valid given the token.
     fn from_token(t: Token) -> Option<Expression> {
         match t.token_type() {
-            // Constant numbers
-            TokenType::Number => Some(Expression::Operand(OType::Static(t.lexeme()))),
+            // Constant numbers, which are written in decimal from here on
+            TokenType::Number => match t.number_value() {
+                Some(v) => Some(Expression::Operand(OType::Static(v.to_string()))),
+                None => Some(Expression::Operand(OType::Static(t.lexeme()))),
+            },
 
             // Operators
             TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Keyword(KeywordType::Div)
@@ -108,11 +158,23 @@ impl Expression {
 
             // Boolean exclusive operators
             TokenType::Keyword(KeywordType::And) | TokenType::Keyword(KeywordType::Or)
-                => Some(Expression::Operator(t.token_type())),
+            | TokenType::Keyword(KeywordType::Not) => Some(Expression::Operator(t.token_type())),
+
+            // Bitwise operators on integers
+            ref t if is_bitwise(t) => Some(Expression::Operator(t.clone())),
+
+            // % is another spelling of mod
+            TokenType::Percent => Some(Expression::Operator(TokenType::Keyword(KeywordType::Mod))),
+
+            // Grouping
+            TokenType::LeftParen | TokenType::RightParen => Some(Expression::Paren(t.token_type())),
 
             // Variables and Constants
             TokenType::Identifier => Some(Expression::Operand(OType::Variable(t.lexeme()))),
 
+            // Char literals keep their quotes so their type is known
+            TokenType::Char => Some(Expression::Operand(OType::Static(t.lexeme()))),
+
             // true and false
             TokenType::Keyword(KeywordType::True) => Some(Expression::Operand(OType::Static(format!("true")))),
             TokenType::Keyword(KeywordType::False) => Some(Expression::Operand(OType::Static(format!("false")))),
@@ -128,6 +190,15 @@ impl PartialOrd for Expression {
         use self::Expression::*;
 
         match self {
+            // unary - (5)
+            &Negation => {
+                match other {
+                    &Negation => Some(Ordering::Less),
+                    &Operator(_) | &Operand(_) => Some(Ordering::Greater),
+                    _ => None,
+                }
+            },
+
             // *, div, mod (4)
             &Operator(TokenType::Star) | &Operator(TokenType::Keyword(KeywordType::Div))
             | &Operator(TokenType::Keyword(KeywordType::Mod)) => {
@@ -138,6 +209,8 @@ impl PartialOrd for Expression {
 
                     &Operator(TokenType::Plus) | &Operator(TokenType::Minus) => Some(Ordering::Greater),
 
+                    &Operator(ref t) if is_bitwise(t) => Some(Ordering::Greater),
+
                     &Operator(TokenType::GreaterThan) | &Operator(TokenType::LessThan)
                     | &Operator(TokenType::GreaterThanOrEqual) | &Operator(TokenType::LessThanOrEqual)
                         => Some(Ordering::Greater),
@@ -163,6 +236,32 @@ impl PartialOrd for Expression {
 
                     &Operator(TokenType::Plus) | &Operator(TokenType::Minus) => Some(Ordering::Less),
 
+                    &Operator(ref t) if is_bitwise(t) => Some(Ordering::Greater),
+
+                    &Operator(TokenType::GreaterThan) | &Operator(TokenType::LessThan)
+                    | &Operator(TokenType::GreaterThanOrEqual) | &Operator(TokenType::LessThanOrEqual)
+                        => Some(Ordering::Greater),
+
+                    &Operator(TokenType::EqualTo) | &Operator(TokenType::NotEqualTo) => Some(Ordering::Greater),
+
+                    &Operator(TokenType::Keyword(KeywordType::And)) | &Operator(TokenType::Keyword(KeywordType::Or))
+                        => Some(Ordering::Greater),
+
+                    _ => None,
+                }
+            },
+
+            // band, bor, bxor, shl, shr, below arithmetic but above comparisons
+            &Operator(ref t) if is_bitwise(t) => {
+                match other {
+                    &Operator(TokenType::Star) | &Operator(TokenType::Keyword(KeywordType::Div))
+                    | &Operator(TokenType::Keyword(KeywordType::Mod))
+                    | &Operand(_) => Some(Ordering::Less),
+
+                    &Operator(TokenType::Plus) | &Operator(TokenType::Minus) => Some(Ordering::Less),
+
+                    &Operator(ref o) if is_bitwise(o) => Some(Ordering::Less),
+
                     &Operator(TokenType::GreaterThan) | &Operator(TokenType::LessThan)
                     | &Operator(TokenType::GreaterThanOrEqual) | &Operator(TokenType::LessThanOrEqual)
                         => Some(Ordering::Greater),
@@ -186,6 +285,8 @@ impl PartialOrd for Expression {
 
                     &Operator(TokenType::Plus) | &Operator(TokenType::Minus) => Some(Ordering::Less),
 
+                    &Operator(ref t) if is_bitwise(t) => Some(Ordering::Less),
+
                     &Operator(TokenType::GreaterThan) | &Operator(TokenType::LessThan)
                     | &Operator(TokenType::GreaterThanOrEqual) | &Operator(TokenType::LessThanOrEqual)
                         => Some(Ordering::Less),
@@ -207,6 +308,8 @@ impl PartialOrd for Expression {
 
                     &Operator(TokenType::Plus) | &Operator(TokenType::Minus) => Some(Ordering::Less),
 
+                    &Operator(ref t) if is_bitwise(t) => Some(Ordering::Less),
+
                     &Operator(TokenType::GreaterThan) | &Operator(TokenType::LessThan)
                     | &Operator(TokenType::GreaterThanOrEqual) | &Operator(TokenType::LessThanOrEqual)
                         => Some(Ordering::Less),
@@ -222,7 +325,29 @@ impl PartialOrd for Expression {
                 }
             }
 
-            &Operator(TokenType::Keyword(KeywordType::And)) | &Operator(TokenType::Keyword(KeywordType::Or)) => Some(Ordering::Less),
+            // not (1), binds tighter than and/or but looser than comparisons so
+            // "not a and b" is "(not a) and b" and "not x > 3" is "not (x > 3)"
+            &Operator(TokenType::Keyword(KeywordType::Not)) => {
+                match other {
+                    &Operator(TokenType::Keyword(KeywordType::And)) | &Operator(TokenType::Keyword(KeywordType::Or))
+                        => Some(Ordering::Greater),
+
+                    &Operator(_) | &Operand(_) => Some(Ordering::Less),
+
+                    _ => None,
+                }
+            },
+
+            // and (0), which binds tighter than or so "a or b and c" is "a or (b and c)"
+            &Operator(TokenType::Keyword(KeywordType::And)) => {
+                match other {
+                    &Operator(TokenType::Keyword(KeywordType::Or)) => Some(Ordering::Greater),
+                    _ => Some(Ordering::Less),
+                }
+            },
+
+            // or (0)
+            &Operator(TokenType::Keyword(KeywordType::Or)) => Some(Ordering::Less),
 
             &Operand(_) => {
                 // Any number
@@ -256,12 +381,37 @@ impl fmt::Display for Expression {
                 }
             },
             &Expression::Combined(ref s) => {
-                write!(f, "<Expr: Combined, {:?}>", s)
+                write!(f, "<Expr: Combined, {}>", s)
+            },
+            &Expression::Paren(ref t) => {
+                write!(f, "<Expr: Paren, {}>", t)
+            },
+            &Expression::Negation => {
+                write!(f, "<Expr: Negation>")
             },
         }
     }
 }
 
+/// Call is a call of a function in an expression, which the parser replaces with a single
+/// operand named after the call.
+#[derive(Clone)]
+pub struct Call {
+    /// The name of the function.
+    pub function: String,
+
+    /// The tokens of each argument, in which the calls are already replaced by their
+    /// operands too.
+    pub arguments: Vec<Vec<Token>>,
+
+    /// The global the function stores its result in.
+    pub result: Symbol,
+
+    /// The most bytes of temps and arguments on the expression stack below the frame of
+    /// the function where it is called, which is shared with the clones of the call.
+    pub below: Rc<Cell<u32>>,
+}
+
 /// ExpressionParser validates the syntax of an expression as well as reduces it and
 /// manages memory allocation for temporary variables used for arithmatic.
 pub struct ExpressionParser {
@@ -273,46 +423,99 @@ pub struct ExpressionParser {
     // The stack used when reducing the expression to one symbol
     stack: Vec<Expression>,
 
+    /// The mark of the commands where each expression on the stack ends, which is where the
+    /// code of the expression after it starts.
+    ends: Vec<(usize, Option<String>)>,
+
     table: SymbolTable,
+
+    /// Whether not of a constant is computed at compile time, operations on two literals
+    /// always are.
+    fold: bool,
+
+    /// The line and column of the first token of the expression, if it has any.
+    position: Option<(u32, u32)>,
+
+    /// The function calls of the expression by the names of the operands standing for them.
+    calls: HashMap<String, Call>,
+
+    /// Whether the expression parser logs its progress.
+    verbose: bool,
 }
 
 impl ExpressionParser {
     /// Creates a new ExpressionParser given the tokens and parses through them. It returns
     /// Some(e) where e is a valid expression parser if there is no error and None otherwise.
+    /// The expression parser logs its progress when the table does.
     pub fn new(table: SymbolTable, tokens: Vec<Token>) -> Option<ExpressionParser> {
+        let verbose = table.verbose();
+        let position = tokens.first().map(|t| (t.line(), t.column()));
+
         // Convert the tokens into expressions
-        let expressions = match ExpressionParser::tokens_to_expressions(tokens) {
+        let expressions = match ExpressionParser::tokens_to_expressions(tokens, verbose) {
             Some(e) => e,
             None => return None,
         };
 
         // Convert infix notation to reverse polish notation
-        let postfix_exp = match ExpressionParser::expressions_to_postfix(expressions) {
+        let postfix_exp = match ExpressionParser::expressions_to_postfix(expressions, verbose) {
             Some(e) => e,
             None => return None,
         };
 
         Some(ExpressionParser {
-            commands: CommandBuilder::new(),
+            commands: CommandBuilder::new(verbose, table.syntax()),
             expressions: postfix_exp,
             stack: Vec::<Expression>::new(),
+            ends: Vec::new(),
             table: table,
+            fold: false,
+            position: position,
+            calls: HashMap::new(),
+            verbose: verbose,
         })
     }
 
+    /// Sets whether not of a constant is computed at compile time instead of generating code
+    /// for it.
+    pub fn set_folding(&mut self, fold: bool) {
+        self.fold = fold;
+    }
+
+    /// Sets the function calls of the expression by the names of the operands standing for
+    /// them. The functions use the registers after R1 too, so no temp is held in one.
+    pub fn set_calls(&mut self, calls: HashMap<String, Call>) {
+        if !calls.is_empty() {
+            self.table.set_registers(2);
+        }
+        self.calls = calls;
+    }
+
     pub fn parse(mut self) -> Result<(Symbol, CommandBuilder), String> {
-        if self.expressions.len() == 1 {
+        let call = match self.expressions.first() {
+            Some(&Expression::Operand(OType::Variable(ref l))) => self.calls.contains_key(l),
+            _ => false,
+        };
+
+        if self.expressions.len() == 1 && !call {
             match self.expressions.remove(0) {
                 Expression::Operand(o_type) => {
                     match o_type {
                         OType::Variable(l) => {
-                            let f_symbol = self.table.get(&*l).unwrap();
-                            return Ok((f_symbol.clone(), self.commands));
+                            let f_symbol = match self.table.get_cloned(&l) {
+                                Some(s) => s,
+                                None => return Err(format!("Attempted to use variable '{}' that has not been declared!", l)),
+                            };
+                            return Ok((f_symbol, self.commands));
                             // self.commands.push_command(format!("movw "))
                         },
                         OType::Static(l) => {
-                            let t = self.table.temp(SymbolType::Constant(type_for_string(&l).unwrap()));
-                            self.commands.push_command(format!("movw #{} +0@R1", l));
+                            let v_type = match type_for_string(&l) {
+                                Some(v) => v,
+                                None => return Err(format!("Unable to determine the type of value '{}'.", l)),
+                            };
+                            let t = self.table.temp(SymbolType::Constant(v_type));
+                            self.commands.push_command(format!("movw {} {}", self.table.syntax().immediate(immediate_value(&l)), t.location()));
                             return Ok((t, self.commands));
                         }
                     }
@@ -321,41 +524,213 @@ impl ExpressionParser {
             };
         }
 
-        let f_symbol = match self.reduce_expression_stack() {
-            Some(s) => s,
-            None => {
-                return Err(format!("<YASLC/ExpressionParser> Attempted to get final symbol for expression but none was found!"));
-            }
-        };
+        // Move the register up by 1
+        self.table.up_register();
+
+        let f_symbol = self.reduce_expression_stack()?;
 
-        // // Now that we have one single expression, move it to the SP
-        let sp_mov = format!("movw {} +0@R1", f_symbol.location());
-        self.push_command(sp_mov);
+        // Now that we have one single expression, move it to the SP unless it is already there
+        let syntax = self.table.syntax();
+        let top = syntax.offset(0, &syntax.register(1));
+        if f_symbol.location() != top {
+            let sp_mov = format!("movw {} {}", f_symbol.location(), top);
+            self.push_command(sp_mov);
+        }
 
         Ok((f_symbol, self.commands))
     }
 
+    /// Returns the most labels for booleans parsing the expression takes from the symbol
+    /// table, one for each comparison and each and/or, including those in the arguments of
+    /// the calls.
+    pub fn bool_temps(&self) -> usize {
+        self.expressions.iter().map(|e| match *e {
+            Expression::Operator(TokenType::Plus) | Expression::Operator(TokenType::Minus)
+            | Expression::Operator(TokenType::Star) | Expression::Operator(TokenType::Keyword(KeywordType::Div))
+            | Expression::Operator(TokenType::Keyword(KeywordType::Mod))
+            | Expression::Operator(TokenType::Keyword(KeywordType::Not)) => 0,
+            Expression::Operator(ref t) if is_bitwise(t) => 0,
+            Expression::Operator(_) => 1,
+            Expression::Operand(OType::Variable(ref l)) if self.calls.contains_key(l) => {
+                self.calls[l].arguments.iter().map(|a| {
+                    match ExpressionParser::new(self.table.clone(), a.clone()) {
+                        Some(mut e) => {
+                            e.calls = self.calls.clone();
+                            e.bool_temps()
+                        },
+                        None => 0,
+                    }
+                }).sum()
+            },
+            _ => 0,
+        }).sum()
+    }
+
+    /// Returns the type the expression evaluates to without generating any code for it, or
+    /// Err(e) where e describes why the expression can not be typed.
+    pub fn value_type(&self) -> Result<SymbolValueType, String> {
+        let mut types = Vec::<SymbolValueType>::new();
+
+        for e in self.expressions.iter() {
+            match *e {
+                Expression::Operand(OType::Static(ref l)) => {
+                    match type_for_string(l) {
+                        Some(t) => types.push(t),
+                        None => return Err(format!("<YASLC/ExpressionParser> Unable to determine the type of '{}'.", l)),
+                    }
+                },
+                Expression::Operand(OType::Variable(ref l)) if self.calls.contains_key(l) => {
+                    match self.calls[l].result.symbol_type {
+                        SymbolType::Variable(ref t) | SymbolType::Constant(ref t) => types.push(t.clone()),
+                        SymbolType::Procedure(_) => return Err(format!("<YASLC/ExpressionParser> The result of '{}' is a procedure!", l)),
+                    }
+                },
+                Expression::Operand(OType::Variable(ref l)) => {
+                    match self.table.get(l) {
+                        Some(s) => match s.symbol_type {
+                            SymbolType::Variable(ref t) | SymbolType::Constant(ref t) => types.push(t.clone()),
+                            SymbolType::Procedure(_) => return Err(format!("<YASLC/ExpressionParser> Attempted to use procedure '{}' in an expression!", l)),
+                        },
+                        None => return Err(format!("<YASLC/ExpressionParser> Attempted to use variable '{}' that has not been declared!", l)),
+                    }
+                },
+                Expression::Operator(TokenType::Keyword(KeywordType::Not)) => {
+                    match types.pop() {
+                        Some(SymbolValueType::Bool) => types.push(SymbolValueType::Bool),
+                        Some(SymbolValueType::Int) => return Err("Expected the operand of NOT to be a boolean but it was an integer!".to_string()),
+                        Some(SymbolValueType::Char) => return Err("Expected the operand of NOT to be a boolean but it was a char!".to_string()),
+                        Some(SymbolValueType::Str) => return Err("Expected the operand of NOT to be a boolean but it was a string!".to_string()),
+                        Some(SymbolValueType::Real) => return Err("Expected the operand of NOT to be a boolean but it was a real!".to_string()),
+                        Some(SymbolValueType::Array(..)) => return Err("Expected the operand of NOT to be a boolean but it was an array!".to_string()),
+                        None => return Err("<YASLC/ExpressionParser> Operator NOT is missing an operand!".to_string()),
+                    }
+                },
+                Expression::Operator(ref t_type) => {
+                    let (t1, t2) = match (types.pop(), types.pop()) {
+                        (Some(t2), Some(t1)) => (t1, t2),
+                        _ => return Err(format!("<YASLC/ExpressionParser> Operator {} is missing an operand!", t_type)),
+                    };
+
+                    match ExpressionParser::operation_type(t_type,
+                        &SymbolType::Variable(t1), &SymbolType::Variable(t2)) {
+                        Ok(t) => types.push(t),
+                        Err(e) => return Err(e),
+                    };
+                },
+                Expression::Combined(ref s) => {
+                    match s.symbol_type {
+                        SymbolType::Variable(ref t) | SymbolType::Constant(ref t) => types.push(t.clone()),
+                        SymbolType::Procedure(_) => return Err("<YASLC/ExpressionParser> Found a procedure in a combined expression!".to_string()),
+                    }
+                },
+                Expression::Negation => {
+                    match types.pop() {
+                        Some(SymbolValueType::Int) => types.push(SymbolValueType::Int),
+                        Some(SymbolValueType::Real) => types.push(SymbolValueType::Real),
+                        Some(t) => return Err(format!("Expected the operand of unary - to be an integer or real but it was {}!", t)),
+                        None => return Err("<YASLC/ExpressionParser> Unary - is missing an operand!".to_string()),
+                    }
+                },
+                Expression::Paren(_) => return Err("<YASLC/ExpressionParser> Found a parenthesis in a postfix expression!".to_string()),
+            };
+        }
+
+        match (types.pop(), types.is_empty()) {
+            (Some(t), true) => Ok(t),
+            (None, _) => Err("<YASLC/ExpressionParser> Attempted to find the type of an empty expression!".to_string()),
+            (Some(_), false) => Err("<YASLC/ExpressionParser> Expression has operands without an operator!".to_string()),
+        }
+    }
+
+    /// Returns the value type produced by applying the operator t_type to symbols of type
+    /// s1 and s2, or Err(e) if the operator can not be used with those types.
+    fn operation_type(t_type: &TokenType, s1: &SymbolType, s2: &SymbolType) -> Result<SymbolValueType, String> {
+        let (v1, v2) = match (s1, s2) {
+            (SymbolType::Variable(v1), SymbolType::Variable(v2))
+            | (SymbolType::Variable(v1), SymbolType::Constant(v2))
+            | (SymbolType::Constant(v1), SymbolType::Variable(v2))
+            | (SymbolType::Constant(v1), SymbolType::Constant(v2)) => (v1, v2),
+            _ => return Err("<YASLC/ExpressionParser> Found an error that should have been caught a long time ago...".to_string()),
+        };
+
+        if v1 != v2 {
+            return Err("<YASLC/ExpressionParser> Attempted to perform operation on two symbols which don't have the same type!".to_string());
+        }
+
+        // Strings can only be assigned and printed
+        if *v1 == SymbolValueType::Str {
+            return Err(format!("Can not use {} on strings, they can only be assigned and printed!", t_type));
+        }
+
+        // Only the elements of an array have values
+        if let SymbolValueType::Array(..) = *v1 {
+            return Err(format!("Can not use {} on arrays, only on their elements!", t_type));
+        }
+
+        match *t_type {
+            // If its a boolean, return an error
+            TokenType::EqualTo | TokenType::NotEqualTo if *v1 == SymbolValueType::Bool => {
+                Err(format!("Expected operands of {} to be integers but they were booleans!", t_type))
+            },
+
+            TokenType::GreaterThan | TokenType::LessThan | TokenType::GreaterThanOrEqual
+            | TokenType::LessThanOrEqual | TokenType::EqualTo | TokenType::NotEqualTo => {
+                Ok(SymbolValueType::Bool)
+            },
+
+            TokenType::Keyword(KeywordType::And) | TokenType::Keyword(KeywordType::Or) => {
+                // If its an integer, return an error
+                match *v1 {
+                    SymbolValueType::Int => Err(format!("Expected operands of {} to be booleans but they were integers!", t_type)),
+                    SymbolValueType::Char => Err(format!("Expected operands of {} to be booleans but they were chars!", t_type)),
+                    SymbolValueType::Real => Err(format!("Expected operands of {} to be booleans but they were reals!", t_type)),
+                    _ => Ok(SymbolValueType::Bool),
+                }
+            },
+
+            // Booleans can only be compared and used with and, or and not
+            _ if *v1 == SymbolValueType::Bool => {
+                Err(format!("Can not use {} on booleans, they can only be compared or combined with AND and OR!", t_type))
+            },
+
+            // A real has no remainder
+            TokenType::Keyword(KeywordType::Mod) if *v1 == SymbolValueType::Real => {
+                Err(format!("Can not use {} on reals, only on integers!", t_type))
+            },
+
+            // Bits can only be operated on in integers
+            ref t if is_bitwise(t) && *v1 != SymbolValueType::Int => {
+                Err(format!("Can not use {} on {}s, only on integers!", t, v1))
+            },
+
+            // Chars can only be compared
+            _ if *v1 == SymbolValueType::Char => {
+                Err(format!("Can not use {} on chars, they can only be compared!", t_type))
+            },
+
+            _ => Ok(v1.clone()),
+        }
+    }
+
     fn push_command(&mut self, command: String) {
-        log!("Pushing command: {}", command);
+        log!(self.verbose, "Pushing command: {}", command);
         self.commands.push_command(command);
     }
 
-    /// Reduces the stack of postfix expressions until there is only one remaining.
-    fn reduce_expression_stack(&mut self) -> Option<Symbol> {
-        // Move the register up by 1
-        self.table.up_register();
-
+    /// Reduces the stack of postfix expressions until there is only one remaining and returns
+    /// Ok(s) where s is its symbol, or Err(e) where e is why it could not be reduced.
+    fn reduce_expression_stack(&mut self) -> Result<Symbol, String> {
         // Reduce the list until there are no commands remaining
         while self.expressions.len() > 0 {
-            log!(NNL "Reducing in state:\n\tExpressions:[ ");
+            log!(NNL self.verbose, "Reducing in state:\n\tExpressions:[ ");
             for e in self.expressions.iter() {
-                log!(NNL "{}, ", e);
+                log!(NNL self.verbose, "{}, ", e);
             }
-            log!(NNL "],\n\tStack: [");
+            log!(NNL self.verbose, "],\n\tStack: [");
             for e in self.stack.iter() {
-                log!(NNL "{}, ", e);
+                log!(NNL self.verbose, "{}, ", e);
             }
-            log!("]");
+            log!(self.verbose, "]");
 
             // Pop the first expression
             let e = self.expressions.remove(0);
@@ -364,29 +739,26 @@ impl ExpressionParser {
                 Ok(_) => {},
                 Err(error) => {
                     println!("<YASLC/ExpressionParser> Error handling expression: {}", error);
-                    return None;
+                    return Err(error);
                 }
             };
 
         }
 
-        let f_symbol = match self.f_symbol() {
-            Some(s) => {
-                s
-            },
-            _ => {
+        match self.f_symbol() {
+            Some(s) => Ok(s),
+            None => {
                 println!("<YASLC/ExpressionParser> Error: Expected to find final symbol in expression parser but none was found!");
-                return None;
+                Err(format!("<YASLC/ExpressionParser> Attempted to get final symbol for expression but none was found!"))
             }
-        };
-
-        Some(f_symbol)
+        }
     }
 
     /// Returns the final symbol, given the stack is reduced
     fn f_symbol(&mut self) -> Option<Symbol> {
         if self.stack.len() == 0 {
-            panic!("<YASLC/ExpressionParser> Internal error attempted to get the final symbol of an expression but it was not found!");
+            println!("<YASLC/ExpressionParser> Internal error attempted to get the final symbol of an expression but it was not found!");
+            return None;
         }
 
         match self.stack.remove(0) {
@@ -400,37 +772,111 @@ impl ExpressionParser {
                         let s = self.table.temp(SymbolType::Variable(
                             match type_for_string(&l) {
                                 Some(so) => so,
-                                None => panic!("Error: Could not create a temporary variable for value because of indeterminable type!"),
+                                None => {
+                                    println!("Error: Could not create a temporary variable for value because of indeterminable type!");
+                                    return None;
+                                },
                             }
                         ));
-                        self.push_command(format!("movw #{} {}", l, s.location()));
+                        self.push_command(format!("movw {} {}", self.table.syntax().immediate(immediate_value(&l)), s.location()));
                         Some(s.clone())
                     },
                     OType::Variable(t) => {
-                        let symbol = match self.table.get(&*t) {
-                            Some(s) => s,
-                            None => {
-                                panic!("<YASLC/ExpressionParser> Attempted to use a symbol that was not found in the symbol table! This is very unexpected...");
-                            }
-                        };
-                        Some(symbol.clone())
+                        let symbol = self.table.get_cloned(&t);
+                        if symbol.is_none() {
+                            println!("<YASLC/ExpressionParser> Attempted to use a symbol that was not found in the symbol table! This is very unexpected...");
+                        }
+                        symbol
                     }
                 }
             }
             _ => {
-                panic!("Found expression consisting of only an operator!");
+                println!("Found expression consisting of only an operator!");
+                None
             }
         }
     }
 
-    fn last_two_expressions(mut stack: &mut Vec<Expression>) -> Result<(Expression, Expression), String> {
-        let e2 = match stack.pop() {
+    /// Calls the function of the operand where the operand is in the expression, so a call
+    /// on the right of and/or only runs when the left does not decide the result, and
+    /// returns the temp its result is copied to, so every call has a result of its own. The
+    /// stack pointer is moved past the temps in use first and R1 is set again after, since
+    /// the function uses both.
+    fn call(&mut self, name: &str) -> Result<Symbol, String> {
+        let call = match self.calls.get(name) {
+            Some(c) => c.clone(),
+            None => return Err(format!("<YASLC/ExpressionParser> Found no call named '{}'!", name)),
+        };
+
+        let mut arguments = Vec::<Symbol>::new();
+        for a in call.arguments.iter() {
+            arguments.push(self.argument(a.clone())?);
+        }
+
+        // The arguments are pushed above the temps which are still used, the frame of the
+        // function starts after them
+        let live = self.table.next_offset();
+        let syntax = self.table.syntax();
+        let sp = &syntax.stack_pointer;
+        if live > 0 {
+            self.push_command(format!("addw {} {}", syntax.immediate(live), sp));
+        }
+        for a in arguments.iter() {
+            self.push_command(format!("movw {} {}", a.location(), syntax.offset(0, sp)));
+            self.push_command(format!("addw {} {}", syntax.immediate(4), sp));
+        }
+        call.below.set(call.below.get().max(live + 4 * arguments.len() as u32));
+        self.push_command(format!("call {} {}", syntax.immediate(4 * arguments.len()), syntax.label(&call.function)));
+        if !arguments.is_empty() {
+            self.push_command(format!("subw {} {}", syntax.immediate(4 * arguments.len()), sp));
+        }
+        if live > 0 {
+            self.push_command(format!("subw {} {}", syntax.immediate(live), sp));
+        }
+        self.push_command(format!("movw {} {}", sp, syntax.register(1)));
+
+        let t = self.table.temp_with_name("call", call.result.symbol_type.clone());
+        self.push_command(format!("movw {} {}", call.result.location(), t.location()));
+        Ok(t)
+    }
+
+    /// Generates the code of an argument of a call with the temps of this expression still
+    /// in use and returns the symbol holding its value. The argument takes its temps and
+    /// labels from the same table, after those of this expression.
+    fn argument(&mut self, tokens: Vec<Token>) -> Result<Symbol, String> {
+        let mut e = match ExpressionParser::new(self.table.clone(), tokens) {
+            Some(e) => e,
+            None => return Err("<YASLC/ExpressionParser> Unable to parse an argument of a call!".to_string()),
+        };
+        e.fold = self.fold;
+        e.calls = self.calls.clone();
+
+        let a = e.reduce_expression_stack()?;
+        self.table = e.table;
+        self.commands.push_builder(e.commands);
+        Ok(a)
+    }
+
+    /// Pushes the expression onto the stack, whose code ends with the commands so far.
+    fn push_stack(&mut self, e: Expression) {
+        self.stack.push(e);
+        self.ends.push(self.commands.mark());
+    }
+
+    /// Pops the expression on top of the stack.
+    fn pop_stack(&mut self) -> Option<Expression> {
+        self.ends.pop();
+        self.stack.pop()
+    }
+
+    fn last_two_expressions(&mut self) -> Result<(Expression, Expression), String> {
+        let e2 = match self.pop_stack() {
             Some(s) => s,
             None => {
                 return Err(format!("Attempted to reduce expression but there is two missing operands!"));
             }
         };
-        let e1 = match stack.pop() {
+        let e1 = match self.pop_stack() {
             Some(s) => s,
             None => {
                 return Err(format!("Attempted to reduce expression but there is a missing operand!"));
@@ -439,15 +885,253 @@ impl ExpressionParser {
         Ok((e1, e2))
     }
 
+    /// Returns the name hint for a temp holding the result of the operation t_type.
+    fn temp_hint(t_type: &TokenType) -> &'static str {
+        match *t_type {
+            TokenType::Plus => "sum",
+            TokenType::Minus => "difference",
+            TokenType::Star => "product",
+            TokenType::Keyword(KeywordType::Div) => "quotient",
+            TokenType::Keyword(KeywordType::Mod) => "remainder",
+            TokenType::Keyword(KeywordType::And) | TokenType::Keyword(KeywordType::Or) => "logic",
+            ref t if is_bitwise(t) => "bits",
+            _ => "comparison",
+        }
+    }
+
+    /// Returns the value of the arithmetic operation t_type on two integer constants, or None
+    /// if it can not be computed at compile time.
+    fn fold_constants(t_type: &TokenType, l1: &str, l2: &str) -> Option<i32> {
+        let (a, b) = match (l1.parse::<i32>(), l2.parse::<i32>()) {
+            (Ok(a), Ok(b)) => (a, b),
+            _ => return None,
+        };
+
+        match *t_type {
+            TokenType::Plus => a.checked_add(b),
+            TokenType::Minus => a.checked_sub(b),
+            TokenType::Star => a.checked_mul(b),
+            TokenType::Keyword(KeywordType::Div) => a.checked_div(b),
+            TokenType::Keyword(KeywordType::Mod) => a.checked_rem(b),
+            TokenType::Keyword(KeywordType::BAnd) => Some(a & b),
+            TokenType::Keyword(KeywordType::BOr) => Some(a | b),
+            TokenType::Keyword(KeywordType::BXor) => Some(a ^ b),
+            TokenType::Keyword(KeywordType::Shl) if b >= 0 => a.checked_shl(b as u32),
+            TokenType::Keyword(KeywordType::Shr) if b >= 0 => a.checked_shr(b as u32),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the operation t_type on two constants, "true" or "false" for
+    /// comparisons and logic, or None if it can not be computed at compile time.
+    fn fold_value(t_type: &TokenType, l1: &str, l2: &str) -> Option<String> {
+        // Reals are computed by the program so the result is the same as at run time
+        if real_value(l1).is_some() || real_value(l2).is_some() {
+            return None;
+        }
+
+        if let Some(v) = ExpressionParser::fold_constants(t_type, l1, l2) {
+            return Some(v.to_string());
+        }
+
+        let truth = |l: &str| match l {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        let number = |l: &str| match truth(l) {
+            Some(b) => Some(b as i32),
+            None => immediate_value(l).parse::<i32>().ok(),
+        };
+
+        let result = match *t_type {
+            TokenType::Keyword(KeywordType::And) => truth(l1)? && truth(l2)?,
+            TokenType::Keyword(KeywordType::Or) => truth(l1)? || truth(l2)?,
+            TokenType::EqualTo | TokenType::NotEqualTo if truth(l1).is_some() || truth(l2).is_some() => return None,
+            TokenType::GreaterThan => number(l1)? > number(l2)?,
+            TokenType::LessThan => number(l1)? < number(l2)?,
+            TokenType::GreaterThanOrEqual => number(l1)? >= number(l2)?,
+            TokenType::LessThanOrEqual => number(l1)? <= number(l2)?,
+            TokenType::EqualTo => number(l1)? == number(l2)?,
+            TokenType::NotEqualTo => number(l1)? != number(l2)?,
+            _ => return None,
+        };
+        Some(result.to_string())
+    }
+
+    /// Returns the value of the expression if it only uses literals, or None if it depends
+    /// on a variable or can not be computed at compile time. No code is generated for it.
+    pub fn constant_value(&self) -> Option<String> {
+        let mut values = Vec::<String>::new();
+
+        for e in self.expressions.iter() {
+            match *e {
+                Expression::Operand(OType::Static(ref l)) => values.push(l.clone()),
+                Expression::Operator(TokenType::Keyword(KeywordType::Not)) => {
+                    let v = match &*values.pop()? {
+                        "true" => "false",
+                        "false" => "true",
+                        _ => return None,
+                    };
+                    values.push(v.to_string());
+                },
+                Expression::Negation => {
+                    let v = values.pop()?.parse::<i32>().ok()?.checked_neg()?;
+                    values.push(v.to_string());
+                },
+                Expression::Operator(ref t_type) => {
+                    let l2 = values.pop()?;
+                    let l1 = values.pop()?;
+                    values.push(ExpressionParser::fold_value(t_type, &l1, &l2)?);
+                },
+                _ => return None,
+            }
+        }
+
+        match values.len() {
+            1 => values.pop(),
+            _ => None,
+        }
+    }
+
+    /// Reduces the operand on top of the stack with not, leaving 1 - operand in a new temp.
+    fn reduce_not(&mut self) -> Result<(), String> {
+        let e = match self.pop_stack() {
+            Some(e) => e,
+            None => return Err("Operator NOT is missing an operand!".to_string()),
+        };
+
+        let s = match e {
+            Expression::Operand(OType::Static(l)) => {
+                if self.fold && (l == "true" || l == "false") {
+                    let v = if l == "true" { "false" } else { "true" };
+                    self.push_stack(Expression::Operand(OType::Static(v.to_string())));
+                    return Ok(());
+                }
+
+                let v_type = match type_for_string(&l) {
+                    Some(v) => v,
+                    None => return Err(format!("Unable to determine the type of value '{}'.", l)),
+                };
+                let temp = self.table.temp(SymbolType::Variable(v_type));
+                self.push_command(format!("movw {} {}", self.table.syntax().immediate(immediate_value(&l)), temp.location()));
+                temp
+            },
+            Expression::Operand(OType::Variable(l)) => {
+                match self.table.get_cloned(&l) {
+                    Some(x) => x,
+                    None => return Err(format!("Attempted to use variable '{}' that has not been declared!", l)),
+                }
+            },
+            Expression::Combined(s) => s,
+            _ => return Err("Found an operator where we were expecting an operand!".to_string()),
+        };
+
+        match s.symbol_type {
+            SymbolType::Variable(SymbolValueType::Bool) | SymbolType::Constant(SymbolValueType::Bool) => {},
+            _ => return Err(format!("Expected the operand of NOT to be a boolean but it was {}!", s)),
+        };
+
+        let dest = self.table.temp_with_name("logic", SymbolType::Variable(SymbolValueType::Bool));
+        self.push_command(format!("movw {} {}", self.table.syntax().immediate(1), dest.location()));
+        self.push_command(format!("subw {} {}", s.location(), dest.location()));
+
+        self.push_stack(Expression::Combined(dest));
+        Ok(())
+    }
+
+    /// Reduces the operand on top of the stack with unary -, leaving 0 - operand in a new
+    /// temp. A number is negated at compile time since it is just a negative literal.
+    fn reduce_negation(&mut self) -> Result<(), String> {
+        let e = match self.pop_stack() {
+            Some(e) => e,
+            None => return Err("Unary - is missing an operand!".to_string()),
+        };
+
+        let s = match e {
+            Expression::Operand(OType::Static(l)) => {
+                // A real literal is negated by its sign so it keeps its decimal point
+                let negated = match l.strip_prefix('-') {
+                    Some(r) if real_value(r).is_some() => Some(r.to_string()),
+                    _ if real_value(&l).is_some() => Some(format!("-{}", l)),
+                    _ => l.parse::<i32>().ok().and_then(|v| v.checked_neg()).map(|v| v.to_string()),
+                };
+                match negated {
+                    Some(v) => {
+                        self.push_stack(Expression::Operand(OType::Static(v)));
+                        return Ok(());
+                    },
+                    None => return Err(format!("Expected the operand of unary - to be an integer or real but it was '{}'!", l)),
+                }
+            },
+            Expression::Operand(OType::Variable(l)) => {
+                match self.table.get_cloned(&l) {
+                    Some(x) => x,
+                    None => return Err(format!("Attempted to use variable '{}' that has not been declared!", l)),
+                }
+            },
+            Expression::Combined(s) => s,
+            _ => return Err("Found an operator where we were expecting an operand!".to_string()),
+        };
+
+        // The bits of 0 are the float 0 as well, so only the subtraction differs for reals
+        let (v_type, op) = match s.symbol_type {
+            SymbolType::Variable(SymbolValueType::Int) | SymbolType::Constant(SymbolValueType::Int) => (SymbolValueType::Int, "subw"),
+            SymbolType::Variable(SymbolValueType::Real) | SymbolType::Constant(SymbolValueType::Real) => (SymbolValueType::Real, "subf"),
+            _ => return Err(format!("Expected the operand of unary - to be an integer or real but it was {}!", s)),
+        };
+
+        let dest = self.table.temp_with_name("negation", SymbolType::Variable(v_type));
+        self.push_command(format!("movw {} {}", self.table.syntax().immediate(0), dest.location()));
+        self.push_command(format!("{} {} {}", op, s.location(), dest.location()));
+
+        self.push_stack(Expression::Combined(dest));
+        Ok(())
+    }
+
     /// Reduces the previous two expressions on self.stack with the token type t_type
     fn reduce_expression(&mut self, t_type: TokenType) -> Result<(), String> {
+        // The code of the second operand starts where the first one ends
+        let e1_end = match self.ends.len().checked_sub(2) {
+            Some(n) => self.ends[n].clone(),
+            None => self.commands.mark(),
+        };
+
         // Pop the previous two expressions
-        let (e1, e2) = match ExpressionParser::last_two_expressions(&mut self.stack) {
+        let (e1, e2) = match self.last_two_expressions() {
             Ok((r1, r2)) => (r1, r2),
-            Err(e) => panic!("<YASLC/ExpresionParser> {}", e),
+            Err(e) => return Err(e),
         };
 
-        log!("<YASLC/ExpressionParser> Reducing expressions {} and {} using {}.", e1, e2, t_type);
+        log!(self.verbose, "<YASLC/ExpressionParser> Reducing expressions {} and {} using {}.", e1, e2, t_type);
+
+        // Dividing by a literal zero would trap when the program runs
+        let divides = matches!(t_type, TokenType::Keyword(KeywordType::Div) | TokenType::Keyword(KeywordType::Mod));
+        if let Expression::Operand(OType::Static(ref l2)) = e2 {
+            if divides && immediate_value(l2) == "0" {
+                let message = format!("Can not use {} with a divisor of 0, it divides by zero!", t_type);
+                return match self.position {
+                    Some((line, column)) => Err(format!("({}, {}) {}", line, column, message)),
+                    None => Err(message),
+                };
+            }
+        }
+
+        // Fold an operation on two literals into a single literal
+        if let (Expression::Operand(OType::Static(l1)), Expression::Operand(OType::Static(l2))) = (&e1, &e2) {
+            if let Some(v) = ExpressionParser::fold_value(&t_type, l1, l2) {
+                log!(self.verbose, "<YASLC/ExpressionParser> Folded constants {} and {} to {}.", l1, l2, v);
+                self.push_stack(Expression::Operand(OType::Static(v)));
+                return Ok(());
+            }
+        }
+
+        // A literal first operand is only moved to a temp after the code of the second
+        // operand, so the short circuit of and/or checks its value instead
+        let e1_immediate = match e1 {
+            Expression::Operand(OType::Static(ref l)) => Some(self.table.syntax().immediate(immediate_value(l))),
+            _ => None,
+        };
 
         // Match the first expression because if it is a temp variable we can operate on that
         // and not have to create another temp variable
@@ -456,22 +1140,26 @@ impl ExpressionParser {
                 match o_type {
                     // If its a variable
                     OType::Variable(l) => {
-                        match self.table.get(&*l) {
-                            Some(x) => x.clone(),
-                            None => panic!("Attempted to use variable '{}' that has not been declared!", l),
+                        match self.table.get_cloned(&l) {
+                            Some(x) => x,
+                            None => return Err(format!("Attempted to use variable '{}' that has not been declared!", l)),
                         }
                     },
 
                     // It is a constant, initialize to a temp
                     OType::Static(l) => {
-                        let temp = self.table.temp(SymbolType::Variable(type_for_string(&l).unwrap()));
-                        self.push_command(format!("movw #{} {}", l, temp.location()));
+                        let v_type = match type_for_string(&l) {
+                            Some(v) => v,
+                            None => return Err(format!("Unable to determine the type of value '{}'.", l)),
+                        };
+                        let temp = self.table.temp(SymbolType::Variable(v_type));
+                        self.push_command(format!("movw {} {}", self.table.syntax().immediate(immediate_value(&l)), temp.location()));
                         temp
                     }
                 }
             },
             Expression::Combined(s) => s,
-            _ => panic!("Found an operator where we were expecting an operand!"),
+            _ => return Err("Found an operator where we were expecting an operand!".to_string()),
         };
 
         let s2 = match e2 {
@@ -479,78 +1167,83 @@ impl ExpressionParser {
                 match o_type {
                     // If its a variable
                     OType::Variable(l) => {
-                        match self.table.get(&*l) {
-                            Some(x) => x.clone(),
-                            None => panic!("Attempted to use variable '{}' that has not been declared!", l),
+                        match self.table.get_cloned(&l) {
+                            Some(x) => x,
+                            None => return Err(format!("Attempted to use variable '{}' that has not been declared!", l)),
                         }
                     },
 
                     // It is a constant, initialize to a temp
                     OType::Static(l) => {
-                        let temp = self.table.temp(SymbolType::Variable(type_for_string(&l).unwrap()));
-                        self.push_command(format!("movw #{} {}", l, temp.location()));
+                        let v_type = match type_for_string(&l) {
+                            Some(v) => v,
+                            None => return Err(format!("Unable to determine the type of value '{}'.", l)),
+                        };
+                        let temp = self.table.temp(SymbolType::Variable(v_type));
+                        self.push_command(format!("movw {} {}", self.table.syntax().immediate(immediate_value(&l)), temp.location()));
                         temp
                     }
                 }
             },
             Expression::Combined(s) => s,
-            _ => panic!("Found an operator where we were expecting an operand!"),
+            _ => return Err("Found an operator where we were expecting an operand!".to_string()),
         };
 
-        match &s1.symbol_type {
-            &SymbolType::Variable(ref v1) | &SymbolType::Constant(ref v1) => {
-                match &s2.symbol_type {
-                    &SymbolType::Variable(ref v2) | &SymbolType::Constant(ref v2) => {
-                        if v1 != v2 {
-                            log!("s1: {:?}, s2: {:?}", s1.symbol_type, s2.symbol_type);
-                            return Err(format!("<YASLC/ExpressionParser> Attempted to perform operation on two symbols which don't have the same type!"));
-                        }
-                    },
-                    _ => {}
-                }
-            },
-            _ => {}
-        };
+        // Check that the operation is valid for the types of both symbols
+        if let Err(e) = ExpressionParser::operation_type(&t_type, &s1.symbol_type, &s2.symbol_type) {
+            log!(self.verbose, "s1: {}, s2: {}", s1, s2);
+            return Err(e);
+        }
 
         // Find the destination symbol
         let mut dest = if s1.is_temp() {
             // We can operate on s1
-            log!("We can operate on {:?} for expression in place of a temp because it is already a temp!", s1);
+            log!(self.verbose, "We can operate on {} for expression in place of a temp because it is already a temp!", s1);
             s1.clone()
         } else {
             // We have to operate on a temp
             //
             // Move the value from the first symbol to temp
-            let temp = self.table.temp(s1.symbol_type.clone());
-            log!("Generated temp symbol {:?} for expression.", temp);
+            let temp = self.table.temp_with_name(ExpressionParser::temp_hint(&t_type), s1.symbol_type.clone());
+            log!(self.verbose, "Generated temp symbol {} for expression.", temp);
             let mov = format!("movw {} {}", s1.location(), temp.location());
             self.push_command(mov);
             temp
         };
 
-        // Determine the operator string given the token type
+        // Determine the operator string given the token type, reals have operations of their own
+        let real = matches!(dest.symbol_type, SymbolType::Variable(SymbolValueType::Real) | SymbolType::Constant(SymbolValueType::Real));
         let op = match t_type {
+            TokenType::Plus if real => "addf",
+            TokenType::Minus if real => "subf",
+            TokenType::Star if real => "mulf",
+            TokenType::Keyword(KeywordType::Div) if real => "divf",
             TokenType::Plus => "addw",
             TokenType::Minus => "subw",
             TokenType::Star => "mulw",
             TokenType::Keyword(KeywordType::Div) => "divw",
+            TokenType::Keyword(KeywordType::BAnd) => "andw",
+            TokenType::Keyword(KeywordType::BOr) => "orw",
+            TokenType::Keyword(KeywordType::BXor) => "xorw",
+            TokenType::Keyword(KeywordType::Shl) => "shlw",
+            TokenType::Keyword(KeywordType::Shr) => "shrw",
             TokenType::Keyword(KeywordType::Mod) => {
                 // Special case, will return value for the function
-                log!("Reducing using Mod and special commands for that.");
+                log!(self.verbose, "Reducing using Mod and special commands for that.");
 
                 // Generate temp 1 and 2
                 let temp1 = dest;
                 let temp2 = self.table.temp(s2.symbol_type.clone());
 
+                // Move s1 to temp2 before temp1, which may be s1 itself, is divided
+                self.push_command(format!("movw {} {}", s1.location(), temp2.location()));
+
                 // Divide temp1 by s2
                 self.push_command(format!("divw {} {}", s2.location(), temp1.location()));
 
                 // Multiply temp1 by s2
                 self.push_command(format!("mulw {} {}", s2.location(), temp1.location()));
 
-                // Move s1 to temp2
-                self.push_command(format!("movw {} {}", s1.location(), temp2.location()));
-
                 // Subtract temp1 from temp2
                 self.push_command(format!("subw {} {}", temp1.location(), temp2.location()));
 
@@ -559,30 +1252,15 @@ impl ExpressionParser {
 
                 // Generate the combined expression
                 let c = Expression::Combined(temp1);
-                log!("<YASLC/ExpressionParser> Successfully generated 'mod' expression code, {}", c);
-                self.stack.push(c);
+                log!(self.verbose, "<YASLC/ExpressionParser> Successfully generated 'mod' expression code, {}", c);
+                self.push_stack(c);
 
                 return Ok(());
             },
 
             TokenType::GreaterThan | TokenType::LessThan | TokenType::GreaterThanOrEqual
             | TokenType::LessThanOrEqual | TokenType::EqualTo | TokenType::NotEqualTo  => {
-                log!("Reducing using a boolean expression.");
-
-                // if we have == or <> check that it is NOT boolean type
-                if t_type == TokenType::EqualTo || t_type == TokenType::NotEqualTo {
-                    let vt = match s1.symbol_type() {
-                        &SymbolType::Variable(ref vt) | &SymbolType::Constant(ref vt) => {
-                            vt
-                        },
-                        _ => return Err(format!("<YASLC/ExpressionParser> Found an error that should have been caught a long time ago...")),
-                    };
-                    // If its a boolean, return an error
-                    match vt {
-                        &SymbolValueType::Bool => return Err(format!("Expected symbol {:?} to be an integer but it was a boolean!", s1)),
-                        _ => {},
-                    };
-                }
+                log!(self.verbose, "Reducing using a boolean expression.");
 
                 // Get the comparator command
                 let comp  = match t_type {
@@ -592,43 +1270,34 @@ impl ExpressionParser {
                     TokenType::NotEqualTo => "bneq",
                     TokenType::LessThanOrEqual => "bleq",
                     TokenType::LessThan => "blss",
-                    _ => panic!(),
+                    _ => return Err(format!("Internal error: '{}' is not a comparison.", t_type)),
                 };
 
                 let bool_temp = self.table.bool_temp();
-
-                // We don't need to type check for comparison because both are stored as integers
-                self.push_command(format!("cmpw {} {}", s1.location(), s2.location()));
-                self.push_command(format!("{} $b_true{}", comp, bool_temp));
-                self.push_command(format!("movw #0 {}", dest.location()));
-                self.push_command(format!("jmp $b_end{}", bool_temp));
-                self.push_command(format!("$b_true{} movw #1 {}", bool_temp, dest.location()));
-                self.commands.set_prefix(format!("$b_end{}", bool_temp));
+                let syntax = self.table.syntax();
+
+                // Both operands have the same type, reals are compared as floats and
+                // everything else as integers
+                let cmp = if real { "cmpf" } else { "cmpw" };
+                self.push_command(format!("{} {} {}", cmp, s1.location(), s2.location()));
+                self.push_command(format!("{} {}", comp, syntax.label(format!("b_true{}", bool_temp))));
+                self.push_command(format!("movw {} {}", syntax.immediate(0), dest.location()));
+                self.push_command(format!("jmp {}", syntax.label(format!("b_end{}", bool_temp))));
+                self.push_command(format!("{} movw {} {}", syntax.label(format!("b_true{}", bool_temp)), syntax.immediate(1), dest.location()));
+                self.commands.set_prefix(syntax.label(format!("b_end{}", bool_temp)));
 
                 // Change the value type because all of these comparisons create a boolean
                 dest.set_value_type(SymbolValueType::Bool);
                 // Create the combination expression
                 // Push the combination expression to the stack
                 let c = Expression::Combined(dest);
-                self.stack.push(c);
+                self.push_stack(c);
 
                 return Ok(());
             },
 
             TokenType::Keyword(KeywordType::And) | TokenType::Keyword(KeywordType::Or) => {
-                log!("Reducing using 'and/or' special case.");
-                let vt = match s1.symbol_type() {
-                    &SymbolType::Variable(ref vt) | &SymbolType::Constant(ref vt) => {
-                        vt
-                    },
-                    _ => return Err(format!("<YASLC/ExpressionParser> Found an error that should have been caught a long time ago...")),
-                };
-                // If its an integer, return an error
-                match vt {
-                    &SymbolValueType::Int => return Err(format!("Expected symbol {:?} to be an boolean but it was a integer!", s1)),
-                    _ => {},
-                };
-
+                log!(self.verbose, "Reducing using 'and/or' special case.");
 
                 // For OR expressions we exit if either is TRUE and set to TRUE so we can exit
                 // early.
@@ -638,47 +1307,53 @@ impl ExpressionParser {
                 // o1 => symbol is compared to and set to if both are equal to it
                 // o2 => the alternate if either is not equal to
 
+                let syntax = self.table.syntax();
                 let (o1, o2) = match t_type {
-                    TokenType::Keyword(KeywordType::And) => ("1", "0"),
-                    TokenType::Keyword(KeywordType::Or) => ("0", "1"),
-                    _ => panic!(),
+                    TokenType::Keyword(KeywordType::And) => (syntax.immediate(1), syntax.immediate(0)),
+                    TokenType::Keyword(KeywordType::Or) => (syntax.immediate(0), syntax.immediate(1)),
+                    _ => return Err(format!("Internal error: '{}' is not a logical operator.", t_type)),
                 };
 
                 let bool_temp = self.table.bool_temp();
 
-                self.push_command(format!("cmpw {} {}", s1.location(), o1));
-                self.push_command(format!("bneq $b_else{}", bool_temp));
+                // The first operand is checked before the code of the second so the second is
+                // only evaluated when the first does not decide the result
+                let first = e1_immediate.unwrap_or_else(|| s1.location());
+                self.commands.insert_at(e1_end, vec![
+                    format!("cmpw {} {}", first, o1),
+                    format!("bneq {}", syntax.label(format!("b_else{}", bool_temp))),
+                ]);
                 self.push_command(format!("cmpw {} {}", s2.location(), o1));
-                self.push_command(format!("bneq $b_else{}", bool_temp));
+                self.push_command(format!("bneq {}", syntax.label(format!("b_else{}", bool_temp))));
                 self.push_command(format!("movw {} {}", o1, dest.location()));
-                self.push_command(format!("jmp $b_end{}", bool_temp));
-                self.push_command(format!("movw {} {}", o2, dest.location()));
-                self.commands.set_prefix(format!("$b_end{}", bool_temp));
+                self.push_command(format!("jmp {}", syntax.label(format!("b_end{}", bool_temp))));
+                self.push_command(format!("{} movw {} {}", syntax.label(format!("b_else{}", bool_temp)), o2, dest.location()));
+                self.commands.set_prefix(syntax.label(format!("b_end{}", bool_temp)));
 
                 // Change the value type because all of these comparisons create a boolean
                 dest.set_value_type(SymbolValueType::Bool);
                 // Create the combination expression
                 // Push the combination expression to the stack
                 let c = Expression::Combined(dest);
-                self.stack.push(c);
+                self.push_stack(c);
 
                 return Ok(());
             }
 
             n => {
-                panic!("Unrecognized operator '{}' in expression!", n)
+                return Err(format!("Unrecognized operator '{}' in expression!", n));
             },
         };
 
         // Push the combination expression to the stack
         let c = Expression::Combined(dest.clone());
-        log!("Got the combined expression {}", c);
-        self.stack.push(c);
+        log!(self.verbose, "Got the combined expression {}", c);
+        self.push_stack(c);
 
         // Perform the operation
         let full_op = format!("{} {} {}", op, s2.location(), dest.location());
 
-        log!("<YASLC/ExpressionParser> Generated operation for reduction: '{}'", full_op);
+        log!(self.verbose, "<YASLC/ExpressionParser> Generated operation for reduction: '{}'", full_op);
 
         self.push_command(full_op);
 
@@ -695,26 +1370,31 @@ impl ExpressionParser {
             Expression::Operand(o_type) => {
                 // Check if it an identifier or constant number
                 match o_type {
+                    OType::Variable(ref l) if self.calls.contains_key(l) => {
+                        let t = self.call(l)?;
+                        self.push_stack(Expression::Combined(t));
+                        Ok(())
+                    },
                     OType::Variable(l) => {
                         // Check that the variable has been declared
                         if let Some(s) = self.table.get(&*l) {
                             match s.symbol_type {
-                                SymbolType::Procedure => {
+                                SymbolType::Procedure(_) => {
                                     // Fail, we can't use procedures in expressions
-                                    panic!("Attempted to use a procedure as a variable in an expression!");
+                                    return Err(format!("Attempted to use procedure '{}' as a variable in an expression!", l));
                                 }
                                 _ => {}
                             }
                             // Success, push the operand to the stack
-                            self.stack.push(e);
+                            self.push_stack(e);
                             return Ok(());
                         } else {
-                            panic!("Attempted to use variable '{}' that has not been declared!", l);
+                            Err(format!("Attempted to use variable '{}' that has not been declared!", l))
                         }
                     },
                     OType::Static(_) => {
                         // It is a constant number, just push to the stack
-                        self.stack.push(e);
+                        self.push_stack(e);
                         return Ok(());
                     }
                 }
@@ -725,6 +1405,12 @@ impl ExpressionParser {
             //
             // NOTE: This does not check for ordering because it is assumed the list of
             // expressions is already in postfix order.
+            Expression::Operator(TokenType::Keyword(KeywordType::Not)) => {
+                match self.reduce_not() {
+                    Ok(_) => Ok(()),
+                    Err(e) => return Err(format!("Error while reducing expression stack: {}", e)),
+                }
+            },
             Expression::Operator(t_type) => {
                 // Pop the previous two expressions and combine them
                 let reduce_result = self.reduce_expression(t_type);
@@ -735,22 +1421,37 @@ impl ExpressionParser {
                 }
             },
             Expression::Combined(_) => {
-                self.stack.push(e);
+                self.push_stack(e);
                 Ok(())
-            }
+            },
+            Expression::Negation => {
+                match self.reduce_negation() {
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(format!("Error while reducing expression stack: {}", e)),
+                }
+            },
+            Expression::Paren(_) => Err("Found a parenthesis in a postfix expression!".to_string()),
         }
     }
 
     /// Converts the vector of tokens to a vector of expressions and returns None if there was an
     /// invalid token.
-    fn tokens_to_expressions(mut tokens: Vec<Token>) -> Option<Vec<Expression>> {
+    fn tokens_to_expressions(mut tokens: Vec<Token>, verbose: bool) -> Option<Vec<Expression>> {
         let mut expressions = Vec::<Expression>::new();
         // while there's still tokens, push them onto the stack
         while tokens.len() > 0 {
             // Get the front token
             let t = tokens.remove(0);
 
-            log!("<YASLC/ExpressionParser> Popped token for conversion to expression: {}", t);
+            log!(verbose, "<YASLC/ExpressionParser> Popped token for conversion to expression: {}", t);
+
+            // A minus is unary when there is no operand before it
+            let prefix = matches!(expressions.last(), None | Some(&Expression::Operator(_))
+                                  | Some(&Expression::Negation) | Some(&Expression::Paren(TokenType::LeftParen)));
+            if prefix && t.is_type(TokenType::Minus) {
+                expressions.push(Expression::Negation);
+                continue;
+            }
 
             // Attempt to convert it to an expression
             if let Some(e) = Expression::from_token(t.clone()) {
@@ -763,13 +1464,13 @@ impl ExpressionParser {
             }
         }
 
-        log!("<YASLC/ExpressionParser> Successfully converted tokens to expressions!");
+        log!(verbose, "<YASLC/ExpressionParser> Successfully converted tokens to expressions!");
 
         Some(expressions)
     }
 
     /// Converts the vector of expressions to postfix from infix.
-    fn expressions_to_postfix(expressions: Vec<Expression>) -> Option<Vec<Expression>> {
+    fn expressions_to_postfix(expressions: Vec<Expression>, verbose: bool) -> Option<Vec<Expression>> {
         // Initialize the stack and the operator stack
         let mut stack: Vec<Expression> = Vec::<Expression>::new();
         let mut op_stack: Vec<Expression> = Vec::<Expression>::new();
@@ -781,13 +1482,35 @@ impl ExpressionParser {
                     // We have a number, push to the stack
                     stack.push(e);
                 },
+                // not is a prefix operator with no left operand, so nothing before it can
+                // be finished yet
+                Expression::Operator(TokenType::Keyword(KeywordType::Not)) => {
+                    op_stack.push(e);
+                },
+                Expression::Paren(TokenType::LeftParen) | Expression::Negation => {
+                    op_stack.push(e);
+                },
+                Expression::Paren(_) => {
+                    // Everything since the matching left parenthesis is finished
+                    loop {
+                        match op_stack.pop() {
+                            Some(Expression::Paren(_)) => break,
+                            Some(o) => stack.push(o),
+                            None => {
+                                println!("<YASLC/ExpressionParser> Error: Found a `)` without a matching `(`.");
+                                return None;
+                            },
+                        }
+                    }
+                },
                 Expression::Operator(_) => {
                     // We have an operator, check it's precedence vs the top of the stack
                     if op_stack.len() != 0 {
                         while let Some(o) = op_stack.pop() {
-                            // If its greater than current expression, pop and add to stack
-                            if o > e {
-                                log!("<YASLC/ExpressionParser> Pushing operator '{}' to the operand stack.", o);
+                            // If its greater than current expression, or of the same precedence
+                            // and so before it from the left, pop and add to stack
+                            if o > e || o.same_precedence(&e) {
+                                log!(verbose, "<YASLC/ExpressionParser> Pushing operator '{}' to the operand stack.", o);
                                 stack.push(o);
                             } else {
                                 op_stack.push(o);
@@ -806,21 +1529,27 @@ impl ExpressionParser {
         }
 
         while let Some(o) = op_stack.pop() {
+            if let Expression::Paren(_) = o {
+                println!("<YASLC/ExpressionParser> Error: Found a `(` without a matching `)`.");
+                return None;
+            }
             stack.push(o);
         }
 
-        log!("<YASLC/ExpressionParser> Successfully converted infix expressions to postfix.");
-        log!(NNL "[");
-        for e in stack.iter() {
-            match e {
-                &Expression::Operand(ref t) => match t{
-                    &OType::Static(ref l) | &OType::Variable(ref l) => log!(NNL "{}, ", l),
-                },
-                &Expression::Operator(ref t) => log!(NNL "{}, ", t),
-                _ => {},
-            };
+        log!(verbose, "<YASLC/ExpressionParser> Successfully converted infix expressions to postfix.");
+        if verbose {
+            print!("[");
+            for e in stack.iter() {
+                match e {
+                    &Expression::Operand(ref t) => match t{
+                        &OType::Static(ref l) | &OType::Variable(ref l) => print!("{}, ", l),
+                    },
+                    &Expression::Operator(ref t) => print!("{}, ", t),
+                    _ => {},
+                };
+            }
+            println!("]");
         }
-        log!("]");
 
         Some(stack)
     }
@@ -18,7 +18,7 @@ macro_rules! eparser_helper {
         let mut table = SymbolTable::empty();
         for t in tokens.iter() {
             if t.is_type(TokenType::Identifier) {
-                table.add(t.lexeme(), SymbolType::Variable(SymbolValueType::Int));
+                let _ = table.add(t.lexeme(), SymbolType::Variable(SymbolValueType::Int));
             }
         }
 
@@ -156,6 +156,7 @@ fn e_parser_bool_one() {
 }
 
 #[test]
+#[should_panic(expected = "they were booleans")]
 // Tests "true == false"
 fn e_parser_bool_eq() {
     eparser_helper!(TS "true", TokenType::Keyword(KeywordType::True),
@@ -165,6 +166,7 @@ fn e_parser_bool_eq() {
 }
 
 #[test]
+#[should_panic(expected = "they were booleans")]
 // Tests "true <> false"
 fn e_parser_bool_neq() {
     eparser_helper!(TS "true", TokenType::Keyword(KeywordType::True),
@@ -192,6 +194,7 @@ fn e_parser_int_comp() {
 }
 
 #[test]
+#[should_panic(expected = "they were booleans")]
 // Tests "5 < 4 == true" (it is false)
 fn e_parser_combined() {
     eparser_helper!(TS
@@ -204,6 +207,7 @@ fn e_parser_combined() {
 }
 
 #[test]
+#[should_panic(expected = "they were booleans")]
 // Tests "5 < 4 == 10 >= 9" (it is false)
 fn e_parser_int_comp_double() {
     eparser_helper!(TS
@@ -218,6 +222,7 @@ fn e_parser_int_comp_double() {
 }
 
 #[test]
+#[should_panic(expected = "they were booleans")]
 // Tests "5 <= 4 + 1 == 10 >= 9" (it is true)
 fn e_parser_int_comp_double_arith() {
     eparser_helper!(TS
@@ -232,6 +237,7 @@ fn e_parser_int_comp_double_arith() {
 }
 
 #[test]
+#[should_panic(expected = "they were booleans")]
 // Tests "5 <= 4 + 1 == 2 + 10 >= 9 + 10" (it is false)
 fn e_parser_int_comp_double_arith2() {
     eparser_helper!(TS
@@ -328,16 +334,16 @@ fn code_mod_two() {
         "y", TokenType::Identifier);
 
     // Move x to temp variable
+    // Move x to second temp variable
     // Divide temp by y
     // Multiply temp by y
-    // Move x to second temp variable
     // Subtract first temp from second temp
     // Move second temp to R1
     is_commands!(parser,
         "movw +0@R0 +0@R1",
+        "movw +0@R0 +4@R1",
         "divw +4@R0 +0@R1",
         "mulw +4@R0 +0@R1",
-        "movw +0@R0 +4@R1",
         "subw +0@R1 +4@R1",
         "movw +4@R1 +0@R1"
     );
@@ -399,13 +405,8 @@ fn code_long_expression() {
         "1", TokenType::Number
     );
 
-    // move x to temp 1
-    // mult temp 1 by y
-    // add temp 1 by 4
-    // move 30 to temp 2
-    // div temp 2 by z
-    // sub temp 1 by temp 2
-    // add 1 to temp 1
+    // ((4 + x * y) - 30 div z) + 1, since operators of the same precedence are
+    // worked from the left
     is_commands!(parser,
         // move x to temp1
         "movw +0@R0 +0@R1",
@@ -413,28 +414,424 @@ fn code_long_expression() {
         // mult temp1 by y
         "mulw +4@R0 +0@R1",
 
-        // move 30 to t2
-        "movw #30 +4@R1",
+        // move 4 to t2
+        "movw #4 +4@R1",
+
+        // add t1 to t2
+        "addw +0@R1 +4@R1",
+
+        // move 30 to t3
+        "movw #30 +8@R1",
+
+        // div t3 by z
+        "divw +8@R0 +8@R1",
+
+        // sub t3 from t2
+        "subw +8@R1 +4@R1",
 
-        // div temp2 by z
-        "divw +8@R0 +4@R1",
+        // move 1 to t4
+        "movw #1 +12@R1",
 
-        // move 1 to t3
-        "movw #1 +8@R1",
+        // add t4 to t2
+        "addw +12@R1 +4@R1",
 
-        // add t3 to t2
-        "addw +8@R1 +4@R1",
+        // move t2 to +0@R1
+        "movw +4@R1 +0@R1"
+    );
+}
 
-        // sub t2 from t1
-        "subw +4@R1 +0@R1",
+/// **************************************
+/// ****** Expression Type Inference ******
+/// **************************************
 
-        // move 4 to t4
-        "movw #4 +12@R1",
+#[test]
+// Tests that "x + 5 * y" is inferred to be an integer
+fn type_of_arithmetic() {
+    let mut table = SymbolTable::empty();
+    table.add(format!("x"), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+    table.add(format!("y"), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+
+    let tokens = vec![
+        Token::new_with(0, 0, format!("x"), TokenType::Identifier),
+        Token::new_with(0, 0, format!("+"), TokenType::Plus),
+        Token::new_with(0, 0, format!("5"), TokenType::Number),
+        Token::new_with(0, 0, format!("*"), TokenType::Star),
+        Token::new_with(0, 0, format!("y"), TokenType::Identifier),
+    ];
+
+    match type_of(table, tokens) {
+        Ok(SymbolValueType::Int) => {},
+        Ok(t) => panic!("Expected an integer expression but found {:?}", t),
+        Err(e) => panic!("Error: {}", e),
+    };
+}
 
-        // add t1 to t4 (because of the order of operations)
-        "addw +0@R1 +12@R1",
+#[test]
+// Tests that "x < 5" is inferred to be a boolean and generates no code
+fn type_of_comparison() {
+    let mut table = SymbolTable::empty();
+    table.add(format!("x"), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+
+    let tokens = vec![
+        Token::new_with(0, 0, format!("x"), TokenType::Identifier),
+        Token::new_with(0, 0, format!("<"), TokenType::LessThan),
+        Token::new_with(0, 0, format!("5"), TokenType::Number),
+    ];
+
+    let parser = ExpressionParser::new(table, tokens).unwrap();
+    match parser.value_type() {
+        Ok(SymbolValueType::Bool) => {},
+        Ok(t) => panic!("Expected a boolean expression but found {:?}", t),
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert!(parser.commands.len() == 0);
+}
 
-        // move t4 to +0@R1
-        "movw +12@R1 +0@R1"
+#[test]
+// Tests that "true + 5" can not be typed
+fn type_of_mismatch() {
+    let tokens = vec![
+        Token::new_with(0, 0, format!("true"), TokenType::Keyword(KeywordType::True)),
+        Token::new_with(0, 0, format!("+"), TokenType::Plus),
+        Token::new_with(0, 0, format!("5"), TokenType::Number),
+    ];
+
+    assert!(type_of(SymbolTable::empty(), tokens).is_err());
+}
+
+#[test]
+// Tests that booleans can not be compared with == or <>, even when they are folded
+fn type_of_boolean_equality() {
+    for expression in &["true == false", "5 < 4 <> true"] {
+        let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of(expression)).unwrap();
+        assert!(parser.value_type().is_err(), "{}", expression);
+        assert_eq!(parser.constant_value(), None);
+
+        let mut parser = ExpressionParser::new(SymbolTable::empty(), tokens_of(expression)).unwrap();
+        parser.set_folding(true);
+        assert!(parser.parse().is_err(), "{}", expression);
+    }
+
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("5 == 4")).unwrap();
+    assert!(matches!(parser.value_type(), Ok(SymbolValueType::Bool)));
+}
+
+/// Returns the postfix order of the expression parser as the lexemes of its operands and
+/// the names of its operators.
+fn postfix(parser: &ExpressionParser) -> Vec<String> {
+    parser.expressions.iter().map(|e| {
+        match *e {
+            Expression::Operand(OType::Static(ref l)) | Expression::Operand(OType::Variable(ref l)) => l.clone(),
+            Expression::Operator(ref t) => format!("{}", t),
+            Expression::Combined(ref s) => s.identifier().clone(),
+            Expression::Paren(ref t) => format!("{}", t),
+            Expression::Negation => "NEGATE".to_string(),
+        }
+    }).collect()
+}
+
+#[test]
+// Tests that "not a and b" is "(not a) and b" and "not x > 3" is "not (x > 3)"
+fn not_precedence() {
+    let mut table = SymbolTable::empty();
+    table.add(format!("a"), SymbolType::Variable(SymbolValueType::Bool)).unwrap();
+    table.add(format!("b"), SymbolType::Variable(SymbolValueType::Bool)).unwrap();
+    table.add(format!("x"), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+
+    let tokens = vec![
+        Token::new_with(0, 0, format!("not"), TokenType::Keyword(KeywordType::Not)),
+        Token::new_with(0, 0, format!("a"), TokenType::Identifier),
+        Token::new_with(0, 0, format!("and"), TokenType::Keyword(KeywordType::And)),
+        Token::new_with(0, 0, format!("b"), TokenType::Identifier),
+    ];
+    let parser = ExpressionParser::new(table.clone(), tokens).unwrap();
+    assert_eq!(postfix(&parser), vec!["a", "NOT", "b", "AND"]);
+    assert!(matches!(parser.value_type(), Ok(SymbolValueType::Bool)));
+
+    let tokens = vec![
+        Token::new_with(0, 0, format!("not"), TokenType::Keyword(KeywordType::Not)),
+        Token::new_with(0, 0, format!("x"), TokenType::Identifier),
+        Token::new_with(0, 0, format!(">"), TokenType::GreaterThan),
+        Token::new_with(0, 0, format!("3"), TokenType::Number),
+    ];
+    let parser = ExpressionParser::new(table.clone(), tokens).unwrap();
+    assert_eq!(postfix(&parser), vec!["x", "3", "GREATERTHAN", "NOT"]);
+
+    // not of an integer can not be typed
+    let tokens = vec![
+        Token::new_with(0, 0, format!("not"), TokenType::Keyword(KeywordType::Not)),
+        Token::new_with(0, 0, format!("x"), TokenType::Identifier),
+    ];
+    assert!(type_of(table, tokens).is_err());
+}
+
+/// Returns the tokens of the expression, which is written with a space between each token.
+fn tokens_of(expression: &str) -> Vec<Token> {
+    expression.split(' ').map(|l| {
+        let t_type = match l {
+            "(" => TokenType::LeftParen,
+            ")" => TokenType::RightParen,
+            "+" => TokenType::Plus,
+            "-" => TokenType::Minus,
+            "*" => TokenType::Star,
+            "<" => TokenType::LessThan,
+            ">" => TokenType::GreaterThan,
+            "==" => TokenType::EqualTo,
+            "<>" => TokenType::NotEqualTo,
+            "div" => TokenType::Keyword(KeywordType::Div),
+            "mod" => TokenType::Keyword(KeywordType::Mod),
+            "%" => TokenType::Percent,
+            "not" => TokenType::Keyword(KeywordType::Not),
+            "and" => TokenType::Keyword(KeywordType::And),
+            "or" => TokenType::Keyword(KeywordType::Or),
+            "band" => TokenType::Keyword(KeywordType::BAnd),
+            "shl" => TokenType::Keyword(KeywordType::Shl),
+            "true" => TokenType::Keyword(KeywordType::True),
+            "false" => TokenType::Keyword(KeywordType::False),
+            _ if l.parse::<f64>().is_ok() => TokenType::Number,
+            _ => TokenType::Identifier,
+        };
+        Token::new_with(0, 0, l.to_string(), t_type)
+    }).collect()
+}
+
+#[test]
+// Tests that parentheses override the precedence of the operators inside them
+fn paren_precedence() {
+    let grouped = ExpressionParser::new(SymbolTable::empty(), tokens_of("( 5 + 4 ) * 2")).unwrap();
+    assert_eq!(postfix(&grouped), vec!["5", "4", "PLUS", "2", "STAR"]);
+    assert_eq!(grouped.constant_value(), Some("18".to_string()));
+
+    let plain = ExpressionParser::new(SymbolTable::empty(), tokens_of("5 + 4 * 2")).unwrap();
+    assert_eq!(postfix(&plain), vec!["5", "4", "2", "STAR", "PLUS"]);
+    assert_eq!(plain.constant_value(), Some("13".to_string()));
+
+    let nested = ExpressionParser::new(SymbolTable::empty(), tokens_of("2 * ( ( 1 + 2 ) * 3 )")).unwrap();
+    assert_eq!(nested.constant_value(), Some("18".to_string()));
+
+    // The grouped sum is computed before the product
+    let (_, c) = eparser_helper!(TS
+        "(", TokenType::LeftParen,
+        "x", TokenType::Identifier,
+        "+", TokenType::Plus,
+        "y", TokenType::Identifier,
+        ")", TokenType::RightParen,
+        "*", TokenType::Star,
+        "z", TokenType::Identifier
     );
+    assert_eq!(c.commands[0], "movw +0@R0 +0@R1");
+    assert_eq!(c.commands[1], "addw +4@R0 +0@R1");
+    assert!(c.commands[2].starts_with("mulw"));
+}
+
+#[test]
+// Tests that operators of the same precedence are worked from the left, and negation and not
+// from the right
+fn left_associativity() {
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("10 - 2 - 1")).unwrap();
+    assert_eq!(postfix(&parser), vec!["10", "2", "MINUS", "1", "MINUS"]);
+    assert_eq!(parser.constant_value(), Some("7".to_string()));
+
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("64 div 4 div 2")).unwrap();
+    assert_eq!(postfix(&parser), vec!["64", "4", "DIV", "2", "DIV"]);
+    assert_eq!(parser.constant_value(), Some("8".to_string()));
+
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("10 - 2 * 3 - 1")).unwrap();
+    assert_eq!(postfix(&parser), vec!["10", "2", "3", "STAR", "MINUS", "1", "MINUS"]);
+    assert_eq!(parser.constant_value(), Some("3".to_string()));
+
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("not not true")).unwrap();
+    assert_eq!(postfix(&parser), vec!["true", "NOT", "NOT"]);
+
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("true or false and false")).unwrap();
+    assert_eq!(postfix(&parser), vec!["true", "false", "false", "AND", "OR"]);
+}
+
+#[test]
+// Tests that an expression with unbalanced parentheses is not parsed
+fn paren_unbalanced() {
+    assert!(ExpressionParser::new(SymbolTable::empty(), tokens_of("( 5 + 4 * 2")).is_none());
+    assert!(ExpressionParser::new(SymbolTable::empty(), tokens_of("5 + 4 ) * 2")).is_none());
+    assert!(ExpressionParser::new(SymbolTable::empty(), tokens_of(") 5 (")).is_none());
+}
+
+#[test]
+// Tests not of a literal, of a grouped expression and that not of an integer is rejected
+fn not_operand() {
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("not true")).unwrap();
+    assert_eq!(postfix(&parser), vec!["true", "NOT"]);
+    assert_eq!(parser.constant_value(), Some("false".to_string()));
+    let (s, c) = parser.parse().unwrap();
+    assert_eq!(s.symbol_type, SymbolType::Variable(SymbolValueType::Bool));
+    assert_eq!(c.commands[1], "movw #1 +4@R1");
+    assert_eq!(c.commands[2], "subw +0@R1 +4@R1");
+
+    let mut table = SymbolTable::empty();
+    table.add(format!("a"), SymbolType::Variable(SymbolValueType::Bool)).unwrap();
+    table.add(format!("b"), SymbolType::Variable(SymbolValueType::Bool)).unwrap();
+    let parser = ExpressionParser::new(table.clone(), tokens_of("not ( a and b )")).unwrap();
+    assert_eq!(postfix(&parser), vec!["a", "b", "AND", "NOT"]);
+    assert!(matches!(parser.value_type(), Ok(SymbolValueType::Bool)));
+    assert!(parser.parse().is_ok());
+
+    let parser = ExpressionParser::new(table, tokens_of("not 5")).unwrap();
+    assert!(parser.value_type().is_err());
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+// Tests that a minus with no operand before it negates the operand after it
+fn unary_minus() {
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("- 5 + 3")).unwrap();
+    assert_eq!(postfix(&parser), vec!["5", "NEGATE", "3", "PLUS"]);
+    assert_eq!(parser.constant_value(), Some("-2".to_string()));
+    let (_, c) = parser.parse().unwrap();
+    assert_eq!(c.commands[0], "movw #-2 +0@R1");
+
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("3 * - 2")).unwrap();
+    assert_eq!(postfix(&parser), vec!["3", "2", "NEGATE", "STAR"]);
+    assert_eq!(parser.constant_value(), Some("-6".to_string()));
+
+    let mut table = SymbolTable::empty();
+    table.add(format!("a"), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+    table.add(format!("b"), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+    let parser = ExpressionParser::new(table.clone(), tokens_of("- ( a + b )")).unwrap();
+    assert_eq!(postfix(&parser), vec!["a", "b", "PLUS", "NEGATE"]);
+    assert!(matches!(parser.value_type(), Ok(SymbolValueType::Int)));
+    let (s, c) = parser.parse().unwrap();
+    assert_eq!(s.symbol_type, SymbolType::Variable(SymbolValueType::Int));
+    assert_eq!(c.commands[2], "movw #0 +4@R1");
+    assert_eq!(c.commands[3], "subw +0@R1 +4@R1");
+
+    // Only integers can be negated
+    let parser = ExpressionParser::new(table, tokens_of("- true")).unwrap();
+    assert!(parser.value_type().is_err());
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+// Tests that an operation on two literals is computed when it is compiled
+fn fold_literals() {
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("2 + 3")).unwrap();
+    let (s, c) = parser.parse().unwrap();
+    assert_eq!(s.symbol_type, SymbolType::Variable(SymbolValueType::Int));
+    assert_eq!(c.commands, vec!["movw #5 +0@R1"]);
+
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("2 + 3 * 4 - 10 div 3")).unwrap();
+    let (_, c) = parser.parse().unwrap();
+    assert_eq!(c.commands, vec!["movw #11 +0@R1"]);
+
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("2 * 3 < 5")).unwrap();
+    let (s, c) = parser.parse().unwrap();
+    assert_eq!(s.symbol_type, SymbolType::Variable(SymbolValueType::Bool));
+    assert_eq!(c.commands, vec!["movw #false +0@R1"]);
+
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("4 div ( 2 - 2 )")).unwrap();
+    assert!(parser.parse().is_err());
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("4 mod 0")).unwrap();
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+// Tests that dividing a variable by a literal zero is an error at the position of the expression
+fn divide_by_zero() {
+    let mut table = SymbolTable::empty();
+    table.add(format!("x"), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+
+    for expression in &["x div 0", "x mod 0", "( x + 1 ) div ( 3 - 3 )"] {
+        let mut tokens = tokens_of(expression);
+        tokens[0] = Token::new_with(4, 9, tokens[0].lexeme(), tokens[0].token_type());
+        let parser = ExpressionParser::new(table.clone(), tokens).unwrap();
+        let e = match parser.parse() {
+            Err(e) => e,
+            Ok(_) => panic!("{} should not compile", expression),
+        };
+        assert!(e.contains("(4, 9)"), "{}", e);
+        assert!(e.contains("divides by zero"), "{}", e);
+    }
+
+    let parser = ExpressionParser::new(table, tokens_of("x div 2")).unwrap();
+    assert!(parser.parse().is_ok());
+}
+
+#[test]
+// Tests that the second operand of and/or is only evaluated after the first is checked
+fn short_circuit() {
+    let mut table = SymbolTable::empty();
+    table.add(format!("a"), SymbolType::Variable(SymbolValueType::Bool)).unwrap();
+    table.add(format!("x"), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+
+    for (expression, checked) in &[("a and ( x > 3 )", "cmpw +0@R0 #1"), ("a or ( x > 3 )", "cmpw +0@R0 #0")] {
+        let parser = ExpressionParser::new(table.clone(), tokens_of(expression)).unwrap();
+        let (_, c) = parser.parse().unwrap();
+
+        assert_eq!(c.commands[0], *checked);
+        assert!(c.commands[1].starts_with("bneq $b_else"));
+        let load = c.commands.iter().position(|l| l.contains("+4@R0")).unwrap();
+        assert!(load > 1, "{:?}", c.commands);
+    }
+
+    // A comparison on the left keeps its end label on the check of its result
+    let parser = ExpressionParser::new(table, tokens_of("x > 3 and ( x < 9 )")).unwrap();
+    let (_, c) = parser.parse().unwrap();
+    let check = c.commands.iter().position(|l| l.starts_with("$b_end0 cmpw")).unwrap();
+    let load = c.commands.iter().skip(check).position(|l| l.starts_with("movw #9")).unwrap();
+    assert!(load > 0, "{:?}", c.commands);
+}
+
+#[test]
+// Checks that an operation on reals uses the float operation on the bits of the literals
+// instead of being folded
+fn real_addition() {
+    let parser = ExpressionParser::new(SymbolTable::empty(), tokens_of("1.5 + 2.5")).unwrap();
+    assert_eq!(parser.value_type(), Ok(SymbolValueType::Real));
+
+    let (s, c) = parser.parse().unwrap();
+    assert_eq!(s.symbol_type, SymbolType::Variable(SymbolValueType::Real));
+    assert_eq!(c.commands, vec![
+        format!("movw #{} +0@R1", 1.5f32.to_bits() as i32),
+        format!("movw #{} +4@R1", 2.5f32.to_bits() as i32),
+        "addf +4@R1 +0@R1".to_string(),
+    ]);
+}
+
+#[test]
+// Checks that % generates the same commands as mod
+fn percent_is_mod() {
+    let mut table = SymbolTable::empty();
+    table.add(format!("x"), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+    table.add(format!("y"), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+
+    let percent = ExpressionParser::new(table.clone(), tokens_of("x + 7 % y")).unwrap();
+    let word = ExpressionParser::new(table, tokens_of("x + 7 mod y")).unwrap();
+    assert_eq!(postfix(&percent), postfix(&word));
+    assert_eq!(percent.parse().unwrap().1.commands, word.parse().unwrap().1.commands);
+}
+
+#[test]
+// Checks that bitwise operators fold on literals, generate their own instructions on
+// variables, bind looser than arithmetic and are only used on integers
+fn bitwise_operators() {
+    assert_eq!(ExpressionParser::new(SymbolTable::empty(), tokens_of("6 band 3")).unwrap().constant_value(), Some("2".to_string()));
+    assert_eq!(ExpressionParser::new(SymbolTable::empty(), tokens_of("1 shl 4")).unwrap().constant_value(), Some("16".to_string()));
+
+    let mut table = SymbolTable::empty();
+    table.add(format!("x"), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+    table.add(format!("b"), SymbolType::Variable(SymbolValueType::Bool)).unwrap();
+
+    let parser = ExpressionParser::new(table.clone(), tokens_of("x band 3")).unwrap();
+    assert_eq!(parser.parse().unwrap().1.commands, vec![
+        "movw #3 +0@R1".to_string(),
+        "movw +0@R0 +4@R1".to_string(),
+        "andw +0@R1 +4@R1".to_string(),
+        "movw +4@R1 +0@R1".to_string(),
+    ]);
+
+    let parser = ExpressionParser::new(table.clone(), tokens_of("1 shl x")).unwrap();
+    assert_eq!(parser.parse().unwrap().1.commands.last(), Some(&"shlw +0@R0 +0@R1".to_string()));
+
+    let parser = ExpressionParser::new(table.clone(), tokens_of("x band 1 + 2 > 0")).unwrap();
+    assert_eq!(postfix(&parser), vec!["x", "1", "2", "PLUS", "BAND", "0", "GREATERTHAN"]);
+
+    assert!(ExpressionParser::new(table, tokens_of("b band true")).unwrap().value_type().is_err());
 }
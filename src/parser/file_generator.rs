@@ -7,21 +7,53 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::io;
 
-/// Generates a file given the list of commands and list of declarations and returns the
+/// Generates the file with the given name containing each line in lines and returns the
 /// io::Result, containing Ok(file) if it was successful and Err(e) if it was not.
-pub fn file_from(commands: Vec<String>) -> io::Result<File> {
-    let mut f = try!(File::create("out.pal"));
+pub fn file_named(name: &str, commands: Vec<String>) -> io::Result<File> {
+    let mut f = File::create(name)?;
 
+    match write_commands(&mut f, commands) {
+        Ok(_) => {},
+        Err(e) => {
+            println!("Error writing to file: {:?}!", e);
+        },
+    };
+
+    Ok(f)
+}
+
+/// Writes each command to the sink, such as a file or a buffer, followed by a newline and
+/// returns Err(e) if one could not be written.
+pub fn write_commands<W: Write>(w: &mut W, commands: Vec<String>) -> io::Result<()> {
     for c in commands {
-        match f.write_fmt(format_args!("{}\n", c)) {
-            Ok(_) => {
-                //println!("Successfully wrote {:?} bytes to file.", n);
-            },
-            Err(e) => {
-                println!("Error writing to file: {:?}!", e);
-            },
-        };
+        w.write_all(c.as_bytes())?;
+        w.write_all(b"\n")?;
     }
 
+    Ok(())
+}
+
+/// Returns the text of a file containing each line in lines, each followed by a newline.
+pub fn text_of(lines: &[String]) -> String {
+    let mut text = String::new();
+    for l in lines {
+        text.push_str(l);
+        text.push('\n');
+    }
+    text
+}
+
+/// Generates the file with the given name containing the text and returns the io::Result,
+/// containing Ok(file) if it was successful and Err(e) if it was not.
+pub fn file_with_text(name: &str, text: &str) -> io::Result<File> {
+    let mut f = try!(File::create(name));
+
+    match f.write_all(text.as_bytes()) {
+        Ok(_) => {},
+        Err(e) => {
+            println!("Error writing to file: {:?}!", e);
+        },
+    };
+
     Ok(f)
 }
@@ -0,0 +1,159 @@
+/// parser/instruction.rs
+///
+/// The instruction module splits the generated commands into structured instructions
+/// so passes over the final program can reason about labels, operations and branches.
+
+use super::syntax::Syntax;
+
+use std::fmt;
+
+/// Instruction is a single line of the generated program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// An empty line.
+    Blank,
+
+    /// A comment line, the string is the text after the ':'.
+    Comment(String),
+
+    /// A labeled data declaration, such as "$junk #1".
+    Data {
+        label: String,
+        value: String,
+    },
+
+    /// An operation with an optional label, such as "$main movw SP R0".
+    Op {
+        label: Option<String>,
+        mnemonic: String,
+        operands: Vec<String>,
+    },
+}
+
+impl Instruction {
+    /// Returns the instructions for a list of commands. A single command may contain
+    /// several lines, each of which becomes its own instruction.
+    pub fn from_commands(commands: &[String]) -> Vec<Instruction> {
+        Instruction::from_commands_in(commands, &Syntax::default())
+    }
+
+    /// Returns the instructions for a list of commands written in the syntax.
+    pub fn from_commands_in(commands: &[String], syntax: &Syntax) -> Vec<Instruction> {
+        let mut instructions = Vec::<Instruction>::new();
+        for c in commands.iter() {
+            for line in c.split('\n') {
+                instructions.push(Instruction::from_line(line, syntax));
+            }
+        }
+        instructions
+    }
+
+    /// Returns the instruction for a single line of the generated program written in the
+    /// syntax, whose label and immediate sigils tell labels and data apart.
+    pub fn from_line(line: &str, syntax: &Syntax) -> Instruction {
+        let line = line.trim();
+
+        if line.is_empty() {
+            return Instruction::Blank;
+        }
+
+        if let Some(comment) = line.strip_prefix(':') {
+            return Instruction::Comment(comment.trim().to_string());
+        }
+
+        let mut words: Vec<String> = line.split_whitespace()
+            .map(|w| w.trim_end_matches(',').to_string())
+            .collect();
+
+        let label = if words[0].starts_with(&syntax.label) && words.len() > 1 {
+            Some(words.remove(0)[syntax.label.len()..].to_string())
+        } else {
+            None
+        };
+
+        // A label followed by an immediate declares data instead of an operation
+        if let Some(ref l) = label {
+            if words.len() == 1 && words[0].starts_with(&syntax.immediate) {
+                return Instruction::Data {
+                    label: l.clone(),
+                    value: words.remove(0),
+                };
+            }
+        }
+
+        let mnemonic = words.remove(0);
+
+        Instruction::Op {
+            label: label,
+            mnemonic: mnemonic,
+            operands: words,
+        }
+    }
+
+    /// Returns the label for this instruction, if there is one.
+    pub fn label(&self) -> Option<&str> {
+        match *self {
+            Instruction::Data { ref label, .. } => Some(label),
+            Instruction::Op { label: Some(ref label), .. } => Some(label),
+            _ => None,
+        }
+    }
+
+    /// Returns the mnemonic of the operation, or None if it is not an operation.
+    pub fn mnemonic(&self) -> Option<&str> {
+        match *self {
+            Instruction::Op { ref mnemonic, .. } => Some(mnemonic),
+            _ => None,
+        }
+    }
+
+    /// Returns the operands of the operation, or an empty list if it is not an operation.
+    pub fn operands(&self) -> &[String] {
+        match *self {
+            Instruction::Op { ref operands, .. } => operands,
+            _ => &[],
+        }
+    }
+
+    /// Returns true if the instruction is an operation, false for comments, blank lines
+    /// and data.
+    pub fn is_op(&self) -> bool {
+        self.mnemonic().is_some()
+    }
+
+    /// Returns the label this instruction may transfer control to, if it is a jump or branch.
+    pub fn branch_target(&self) -> Option<&str> {
+        match self.mnemonic() {
+            Some("jmp") | Some("beq") | Some("bneq") | Some("bgtr") | Some("blss")
+            | Some("bgeq") | Some("bleq") => {
+                self.operands().last().map(|o| o.trim_start_matches('$'))
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns true if control never continues to the next instruction after this one.
+    pub fn is_terminator(&self) -> bool {
+        matches!(self.mnemonic(), Some("jmp") | Some("ret") | Some("end"))
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Blank => Ok(()),
+            Instruction::Comment(ref c) => write!(f, ": {}", c),
+            Instruction::Data { ref label, ref value } => write!(f, "${} {}", label, value),
+            Instruction::Op { ref label, ref mnemonic, ref operands } => {
+                if let Some(ref l) = *label {
+                    write!(f, "${} ", l)?;
+                }
+                write!(f, "{}", mnemonic)?;
+                for o in operands.iter() {
+                    write!(f, " {}", o)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
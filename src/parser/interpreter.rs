@@ -0,0 +1,310 @@
+/// parser/interpreter.rs
+///
+/// The interpreter module runs a compiled program directly, supporting the instructions
+/// the code generator emits, and captures everything the program outputs.
+
+use super::instruction::Instruction;
+
+use std::collections::HashMap;
+
+/// The most instructions a program may execute before it is stopped, so a program that
+/// never ends can not hang the caller.
+const STEP_LIMIT: usize = 1_000_000;
+
+/// The address of the first data declaration, far above the stack.
+const DATA_START: i32 = 1 << 24;
+
+/// Machine is the state of a running program.
+struct Machine {
+    /// The values of the named registers, R0 through R7, SP and FP.
+    registers: HashMap<String, i32>,
+
+    /// The words of memory by byte address. Memory that was never written reads as 0.
+    memory: HashMap<i32, i32>,
+
+    /// The addresses of the data declarations by label.
+    data: HashMap<String, i32>,
+
+    /// The result of the last cmpw as (first, second).
+    compared: (i32, i32),
+
+    /// The input which has not been read yet.
+    input: Vec<char>,
+
+    /// Everything the program has output.
+    output: String,
+}
+
+impl Machine {
+    /// Returns the value of the operand.
+    fn read(&self, operand: &str) -> Result<i32, String> {
+        if let Some(n) = operand.strip_prefix('#') {
+            return match n {
+                "true" => Ok(1),
+                "false" => Ok(0),
+                _ => n.parse::<i32>().map_err(|_| format!("Invalid immediate '{}'", operand)),
+            };
+        }
+
+        // A bare number is used as an immediate
+        if let Ok(n) = operand.parse::<i32>() {
+            return Ok(n);
+        }
+
+        if let Some(r) = self.registers.get(operand) {
+            return Ok(*r);
+        }
+
+        let address = self.address(operand)?;
+        Ok(*self.memory.get(&address).unwrap_or(&0))
+    }
+
+    /// Stores the value at the operand.
+    fn write(&mut self, operand: &str, value: i32) -> Result<(), String> {
+        if self.registers.contains_key(operand) {
+            self.registers.insert(operand.to_string(), value);
+            return Ok(());
+        }
+
+        let address = self.address(operand)?;
+        self.memory.insert(address, value);
+        Ok(())
+    }
+
+    /// Returns the memory address of an operand such as "+4@R0", "-8@FP" or "$junk".
+    fn address(&self, operand: &str) -> Result<i32, String> {
+        if let Some(label) = operand.strip_prefix('$') {
+            return match self.data.get(label) {
+                Some(a) => Ok(*a),
+                None => Err(format!("Unknown data label '{}'", operand)),
+            };
+        }
+
+        let n = match operand.find('@') {
+            Some(n) => n,
+            None => return Err(format!("Invalid operand '{}'", operand)),
+        };
+
+        let offset = match &operand[..n] {
+            "" => 0,
+            o => o.parse::<i32>().map_err(|_| format!("Invalid offset in '{}'", operand))?,
+        };
+
+        match self.registers.get(&operand[n + 1..]) {
+            Some(r) => Ok(r.wrapping_add(offset)),
+            None => Err(format!("Unknown register in '{}'", operand)),
+        }
+    }
+
+    /// Returns the next character of the input, or None if it has all been read.
+    fn next_char(&mut self) -> Option<char> {
+        if self.input.is_empty() {
+            None
+        } else {
+            Some(self.input.remove(0))
+        }
+    }
+
+    /// Returns the next whitespace separated number of the input, or 0 if there is none.
+    fn next_number(&mut self) -> i32 {
+        while self.input.first().is_some_and(|c| c.is_whitespace()) {
+            self.input.remove(0);
+        }
+
+        let mut number = String::new();
+        while let Some(c) = self.input.first().cloned() {
+            if c.is_ascii_digit() || (c == '-' && number.is_empty()) {
+                number.push(c);
+                self.input.remove(0);
+            } else {
+                break;
+            }
+        }
+
+        number.parse::<i32>().unwrap_or(0)
+    }
+}
+
+/// Returns the float whose bits are the word, which is how reals are stored.
+fn float(word: i32) -> f32 {
+    f32::from_bits(word as u32)
+}
+
+/// Runs the lines of a compiled program with the given input and returns Ok(output) with
+/// everything it output, or Err(e) where e describes why the program could not continue.
+pub fn run(program: &[String], input: &str) -> Result<String, String> {
+    let instructions = Instruction::from_commands(program);
+
+    // Lay out the operations and find the label of each one. A label on a line of its own
+    // belongs to the operation after it.
+    let mut ops = Vec::<(String, Vec<String>)>::new();
+    let mut labels = HashMap::<String, usize>::new();
+    let mut data = HashMap::<String, i32>::new();
+    let mut memory = HashMap::<i32, i32>::new();
+    for i in instructions.iter() {
+        match *i {
+            Instruction::Data { ref label, ref value } => {
+                let address = DATA_START + 4 * data.len() as i32;
+                memory.insert(address, value.trim_start_matches('#').parse::<i32>().unwrap_or(0));
+                data.insert(label.clone(), address);
+            },
+            Instruction::Op { ref label, ref mnemonic, ref operands } => {
+                if mnemonic.starts_with('$') && operands.is_empty() {
+                    labels.insert(mnemonic[1..].to_string(), ops.len());
+                    continue;
+                }
+
+                if let Some(ref l) = *label {
+                    labels.insert(l.clone(), ops.len());
+                }
+                ops.push((mnemonic.clone(), operands.clone()));
+            },
+            _ => {},
+        }
+    }
+
+    let mut registers = HashMap::<String, i32>::new();
+    for r in ["R0", "R1", "R2", "R3", "R4", "R5", "R6", "R7", "SP", "FP"].iter() {
+        registers.insert(r.to_string(), 0);
+    }
+
+    let mut m = Machine {
+        registers: registers,
+        memory: memory,
+        data: data,
+        compared: (0, 0),
+        input: input.chars().collect(),
+        output: String::new(),
+    };
+
+    let target = |operand: Option<&String>| -> Result<usize, String> {
+        let label = operand.map(|o| o.trim_start_matches('$')).unwrap_or("");
+        match labels.get(label) {
+            Some(n) => Ok(*n),
+            None => Err(format!("Unknown label '{}'", label)),
+        }
+    };
+
+    let mut returns = Vec::<usize>::new();
+    let mut pc = 0;
+    for _ in 0..STEP_LIMIT {
+        let (mnemonic, operands) = match ops.get(pc) {
+            Some(op) => op,
+            None => return Err("The program ran past its last instruction".to_string()),
+        };
+        let operand = |n: usize| -> Result<&str, String> {
+            match operands.get(n) {
+                Some(o) => Ok(o),
+                None => Err(format!("`{}` is missing an operand", mnemonic)),
+            }
+        };
+        pc += 1;
+
+        match &**mnemonic {
+            "movw" => {
+                let v = m.read(operand(0)?)?;
+                m.write(operand(1)?, v)?;
+            },
+            "addw" | "subw" | "mulw" | "divw" => {
+                let src = m.read(operand(0)?)?;
+                let dst = m.read(operand(1)?)?;
+                let v = match &**mnemonic {
+                    "addw" => dst.wrapping_add(src),
+                    "subw" => dst.wrapping_sub(src),
+                    "mulw" => dst.wrapping_mul(src),
+                    _ => match dst.checked_div(src) {
+                        Some(v) => v,
+                        None => return Err("Division by zero".to_string()),
+                    },
+                };
+                m.write(operand(1)?, v)?;
+            },
+            "andw" | "orw" | "xorw" | "shlw" | "shrw" => {
+                // Shifts only use the low five bits of their count
+                let src = m.read(operand(0)?)?;
+                let dst = m.read(operand(1)?)?;
+                let v = match &**mnemonic {
+                    "andw" => dst & src,
+                    "orw" => dst | src,
+                    "xorw" => dst ^ src,
+                    "shlw" => dst.wrapping_shl(src as u32),
+                    _ => dst.wrapping_shr(src as u32),
+                };
+                m.write(operand(1)?, v)?;
+            },
+            "addf" | "subf" | "mulf" | "divf" => {
+                let src = float(m.read(operand(0)?)?);
+                let dst = float(m.read(operand(1)?)?);
+                let v = match &**mnemonic {
+                    "addf" => dst + src,
+                    "subf" => dst - src,
+                    "mulf" => dst * src,
+                    _ if src == 0.0 => return Err("Division by zero".to_string()),
+                    _ => dst / src,
+                };
+                m.write(operand(1)?, v.to_bits() as i32)?;
+            },
+            "cmpw" => {
+                m.compared = (m.read(operand(0)?)?, m.read(operand(1)?)?);
+            },
+            "cmpf" => {
+                // The branches compare the order of the floats as integers
+                let (a, b) = (float(m.read(operand(0)?)?), float(m.read(operand(1)?)?));
+                m.compared = match a.partial_cmp(&b) {
+                    Some(o) => (o as i32, 0),
+                    None => return Err("Compared a real which is not a number".to_string()),
+                };
+            },
+            "jmp" | "beq" | "bneq" | "blss" | "bleq" | "bgtr" | "bgeq" => {
+                let (a, b) = m.compared;
+                let taken = match &**mnemonic {
+                    "jmp" => true,
+                    "beq" => a == b,
+                    "bneq" => a != b,
+                    "blss" => a < b,
+                    "bleq" => a <= b,
+                    "bgtr" => a > b,
+                    _ => a >= b,
+                };
+                if taken {
+                    pc = target(operands.last())?;
+                }
+            },
+            "call" => {
+                returns.push(pc);
+                pc = target(operands.last())?;
+            },
+            "ret" => {
+                pc = match returns.pop() {
+                    Some(r) => r,
+                    None => return Err("`ret` without a matching call".to_string()),
+                };
+            },
+            "outw" => {
+                let v = m.read(operand(0)?)?;
+                m.output.push_str(&v.to_string());
+            },
+            "outf" => {
+                // A real is always written with its decimal point, as in 4.0
+                let v = float(m.read(operand(0)?)?);
+                m.output.push_str(&format!("{:?}", v));
+            },
+            "outb" => {
+                let v = m.read(operand(0)?)?;
+                m.output.push(std::char::from_u32(v as u32).unwrap_or('?'));
+            },
+            "inw" => {
+                let v = m.next_number();
+                m.write(operand(0)?, v)?;
+            },
+            "inb" => {
+                let v = m.next_char().map_or(0, |c| c as i32);
+                m.write(operand(0)?, v)?;
+            },
+            "end" => return Ok(m.output),
+            other => return Err(format!("Unsupported instruction `{}`", other)),
+        };
+    }
+
+    Err(format!("The program did not end within {} instructions", STEP_LIMIT))
+}
@@ -0,0 +1,285 @@
+/// parser/ir.rs
+///
+/// The ir module writes the generated instructions as a JSON intermediate representation,
+/// so tools outside the crate can optimize a program, and reads it back for final emission.
+///
+/// The representation is an array with an object for each instruction:
+///
+/// ```text
+/// {"kind": "op", "label": null, "mnemonic": "movw", "operands": ["SP", "R0"]}
+/// {"kind": "data", "label": "junk", "value": "#1"}
+/// {"kind": "comment", "text": "a comment"}
+/// {"kind": "blank"}
+/// ```
+
+use super::instruction::Instruction;
+
+/// Json is a parsed JSON value, limited to the values the representation uses.
+enum Json {
+    Null,
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Returns the value of the field with the name if this is an object.
+    fn field(&self, name: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref fields) => fields.iter().find(|f| f.0 == name).map(|f| &f.1),
+            _ => None,
+        }
+    }
+
+    /// Returns the string of the field with the name, or Err(e) if it is missing or not a
+    /// string.
+    fn string(&self, name: &str) -> Result<String, String> {
+        match self.field(name) {
+            Some(Json::Str(s)) => Ok(s.clone()),
+            _ => Err(format!("Expected the string field \"{}\"", name)),
+        }
+    }
+}
+
+/// Returns the instructions as a pretty printed JSON array.
+pub fn to_ir_json(instructions: &[Instruction]) -> String {
+    let mut json = String::from("[\n");
+
+    for (n, i) in instructions.iter().enumerate() {
+        let object = match *i {
+            Instruction::Blank => "{\"kind\": \"blank\"}".to_string(),
+            Instruction::Comment(ref c) => {
+                format!("{{\"kind\": \"comment\", \"text\": {}}}", quoted(c))
+            },
+            Instruction::Data { ref label, ref value } => {
+                format!("{{\"kind\": \"data\", \"label\": {}, \"value\": {}}}", quoted(label), quoted(value))
+            },
+            Instruction::Op { ref label, ref mnemonic, ref operands } => {
+                let label = match *label {
+                    Some(ref l) => quoted(l),
+                    None => "null".to_string(),
+                };
+                let operands: Vec<String> = operands.iter().map(|o| quoted(o)).collect();
+                format!("{{\"kind\": \"op\", \"label\": {}, \"mnemonic\": {}, \"operands\": [{}]}}",
+                        label, quoted(mnemonic), operands.join(", "))
+            },
+        };
+
+        json.push_str("  ");
+        json.push_str(&object);
+        if n + 1 != instructions.len() {
+            json.push(',');
+        }
+        json.push('\n');
+    }
+
+    json.push(']');
+    json
+}
+
+/// Reads the JSON representation and returns Ok(c) where c are the lines of the program
+/// ready to be written, or Err(e) where e describes why it could not be read.
+pub fn from_ir_json(json: &str) -> Result<Vec<String>, String> {
+    let mut reader = Reader {
+        chars: json.chars().collect(),
+        position: 0,
+    };
+
+    let value = reader.value()?;
+    reader.skip_whitespace();
+    if reader.position != reader.chars.len() {
+        return Err(format!("Unexpected text after the instructions at {}", reader.position));
+    }
+
+    let items = match value {
+        Json::Array(items) => items,
+        _ => return Err("Expected an array of instructions".to_string()),
+    };
+
+    let mut commands = Vec::<String>::new();
+    for item in items.iter() {
+        commands.push(format!("{}", instruction_from(item)?));
+    }
+    Ok(commands)
+}
+
+/// Returns the instruction for an object of the representation.
+fn instruction_from(item: &Json) -> Result<Instruction, String> {
+    match &*item.string("kind")? {
+        "blank" => Ok(Instruction::Blank),
+        "comment" => Ok(Instruction::Comment(item.string("text")?)),
+        "data" => Ok(Instruction::Data {
+            label: item.string("label")?,
+            value: item.string("value")?,
+        }),
+        "op" => {
+            let label = match item.field("label") {
+                None | Some(Json::Null) => None,
+                Some(Json::Str(l)) => Some(l.clone()),
+                _ => return Err("Expected the label to be a string or null".to_string()),
+            };
+
+            let operands = match item.field("operands") {
+                None => Vec::new(),
+                Some(Json::Array(items)) => {
+                    let mut operands = Vec::<String>::new();
+                    for o in items.iter() {
+                        match *o {
+                            Json::Str(ref s) => operands.push(s.clone()),
+                            _ => return Err("Expected the operands to be strings".to_string()),
+                        }
+                    }
+                    operands
+                },
+                _ => return Err("Expected the operands to be an array".to_string()),
+            };
+
+            Ok(Instruction::Op {
+                label: label,
+                mnemonic: item.string("mnemonic")?,
+                operands: operands,
+            })
+        },
+        k => Err(format!("Unknown instruction kind \"{}\"", k)),
+    }
+}
+
+/// Returns the string as a JSON string literal.
+pub fn quoted(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Reader parses JSON text one character at a time.
+struct Reader {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Reader {
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.position).is_some_and(|c| c.is_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    /// Consumes the character after any whitespace if it is the expected one.
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.get(self.position) {
+            Some(c) if *c == expected => {
+                self.position += 1;
+                Ok(())
+            },
+            _ => Err(format!("Expected '{}' at {}", expected, self.position)),
+        }
+    }
+
+    /// Returns the character after any whitespace without consuming it.
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.position).cloned()
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        match self.peek() {
+            Some('"') => Ok(Json::Str(self.string()?)),
+            Some('[') => {
+                self.position += 1;
+                let mut items = Vec::<Json>::new();
+                if self.peek() == Some(']') {
+                    self.position += 1;
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    match self.peek() {
+                        Some(',') => self.position += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(']')?;
+                Ok(Json::Array(items))
+            },
+            Some('{') => {
+                self.position += 1;
+                let mut fields = Vec::<(String, Json)>::new();
+                if self.peek() == Some('}') {
+                    self.position += 1;
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let name = self.string()?;
+                    self.expect(':')?;
+                    fields.push((name, self.value()?));
+                    match self.peek() {
+                        Some(',') => self.position += 1,
+                        _ => break,
+                    }
+                }
+                self.expect('}')?;
+                Ok(Json::Object(fields))
+            },
+            Some('n') if self.chars[self.position..].starts_with(&['n', 'u', 'l', 'l']) => {
+                self.position += 4;
+                Ok(Json::Null)
+            },
+            _ => Err(format!("Unexpected value at {}", self.position)),
+        }
+    }
+
+    /// Reads a string literal, starting at its opening quote.
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+
+        let mut s = String::new();
+        loop {
+            let c = match self.chars.get(self.position) {
+                Some(c) => *c,
+                None => return Err("Unterminated string".to_string()),
+            };
+            self.position += 1;
+
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let e = self.chars.get(self.position).cloned();
+                    self.position += 1;
+                    match e {
+                        Some('"') => s.push('"'),
+                        Some('\\') => s.push('\\'),
+                        Some('/') => s.push('/'),
+                        Some('n') => s.push('\n'),
+                        Some('t') => s.push('\t'),
+                        Some('r') => s.push('\r'),
+                        Some('b') => s.push('\u{8}'),
+                        Some('f') => s.push('\u{c}'),
+                        Some('u') => {
+                            let hex: String = self.chars.iter().skip(self.position).take(4).collect();
+                            self.position += 4;
+                            match u32::from_str_radix(&hex, 16).ok().and_then(std::char::from_u32) {
+                                Some(c) => s.push(c),
+                                None => return Err(format!("Invalid escape \\u{}", hex)),
+                            }
+                        },
+                        _ => return Err(format!("Invalid escape at {}", self.position - 1)),
+                    }
+                },
+                c => s.push(c),
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+/// parser/listing.rs
+///
+/// The listing module renders the final list of instructions in a form meant for people
+/// to read rather than for the assembler.
+
+use super::instruction::Instruction;
+
+/// Returns each label of the program with the index of the instruction it marks, counting
+/// only the operations and data declarations, in the order they appear. The index is the
+/// position of the instruction once comments and blank lines are removed.
+pub fn label_manifest(instructions: &[Instruction]) -> Vec<(String, usize)> {
+    let mut manifest = Vec::<(String, usize)>::new();
+
+    let mut index = 0;
+    for i in instructions.iter() {
+        match *i {
+            Instruction::Blank | Instruction::Comment(_) => {},
+
+            // A label on a line of its own marks the next instruction
+            Instruction::Op { ref mnemonic, ref operands, .. } if mnemonic.starts_with('$') && operands.is_empty() => {
+                manifest.push((mnemonic[1..].to_string(), index));
+            },
+            _ => {
+                if let Some(l) = i.label() {
+                    manifest.push((l.to_string(), index));
+                }
+                index += 1;
+            },
+        }
+    }
+
+    manifest
+}
+
+/// Returns the lines of the program with every label on its own line, the operations
+/// indented beneath it and a blank line between basic blocks.
+pub fn indented(instructions: &[Instruction]) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+
+    // A block starts at every label and after any jump, branch or terminator
+    let mut end_of_block = false;
+    for i in instructions.iter() {
+        match *i {
+            // The blank lines are replaced by the ones between blocks
+            Instruction::Blank => {},
+            Instruction::Data { .. } => lines.push(format!("{}", i)),
+            Instruction::Comment(_) => lines.push(format!("    {}", i)),
+
+            // A label on a line of its own, which the following operation shares
+            Instruction::Op { ref mnemonic, ref operands, .. } if mnemonic.starts_with('$') && operands.is_empty() => {
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                lines.push(mnemonic.clone());
+                end_of_block = false;
+            },
+            Instruction::Op { ref label, ref mnemonic, ref operands } => {
+                if (end_of_block || label.is_some()) && !lines.is_empty() {
+                    lines.push(String::new());
+                }
+
+                if let Some(ref l) = *label {
+                    lines.push(format!("${}", l));
+                }
+
+                let mut line = format!("    {}", mnemonic);
+                for o in operands.iter() {
+                    line.push_str(&format!(" {}", o));
+                }
+                lines.push(line);
+
+                end_of_block = i.branch_target().is_some() || i.is_terminator();
+            },
+        }
+    }
+
+    lines
+}
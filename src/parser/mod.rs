@@ -6,24 +6,54 @@
 /// The parser generates code for the final output file.
 
 pub mod symbol;
+mod cfg;
+mod diagnostic;
 mod expression;
 mod file_generator;
+mod instruction;
+mod interpreter;
+mod ir;
+mod listing;
+mod optimizer;
+mod syntax;
+
+#[cfg(test)]
+mod tests;
 
 pub use super::lexer::{Token, TokenType, KeywordType};
+use super::lexer::{number_value, real_value, LexerOptions};
 
 pub use self::symbol::{Symbol, SymbolTable, SymbolType, SymbolValueType};
-use self::file_generator::file_from;
-use self::expression::ExpressionParser;
-
-/// Set true if you want the parser to log all its progress, false otherwise.
-static mut VERBOSE: bool = true;
-
+use self::symbol::STRING_CAPACITY;
+use self::cfg::ControlFlowGraph;
+pub use self::diagnostic::{Diagnostic, Severity};
+use self::file_generator::{file_named, file_with_text, text_of};
+use self::expression::{Call, ExpressionParser};
+use self::instruction::Instruction;
+use self::listing::{indented, label_manifest};
+use self::optimizer::propagate_constants;
+pub use self::syntax::Syntax;
+pub use self::expression::type_of;
+pub use self::interpreter::run;
+pub use self::ir::from_ir_json;
+
+#[cfg(test)]
+use std::ops::Index;
+
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io;
+use std::rc::Rc;
+
+/// The most constants a constant may be defined through when the options do not set it.
+const DEFAULT_CONSTANT_DEPTH: u32 = 64;
+
+/// Prints the message when verbose is true.
 macro_rules! log {
-    ($message:expr $(,$arg:expr)*) => {
-        unsafe {
-            if VERBOSE == true {
-                println!($message, $($arg,)*);
-            }
+    ($verbose:expr, $message:expr $(,$arg:expr)*) => {
+        if $verbose {
+            println!($message, $($arg,)*);
         }
     };
 }
@@ -83,12 +113,169 @@ macro_rules! c_exp {
     };
 }
 
+/// ParserOptions holds the settings which change what the parser outputs.
+#[derive(Clone, Default)]
+pub struct ParserOptions {
+    /// Write the control flow graph of the program to "out.dot" in DOT format.
+    pub emit_cfg: bool,
+
+    /// Write each label of the program with the index of the instruction it marks to
+    /// "out.labels".
+    pub emit_labels: bool,
+
+    /// Run the optimization passes over the compiled program.
+    pub optimize: bool,
+
+    /// Treat the tokens as a single expression instead of a program, compiling a program
+    /// which prints the value of the expression.
+    pub expression_only: bool,
+
+    /// Print each distinct string literal with a single shared routine instead of
+    /// repeating its output everywhere it is printed.
+    pub share_strings: bool,
+
+    /// Predeclare the program arguments, which the runtime places in the first words of
+    /// the stack before the program starts.
+    pub arguments: bool,
+
+    /// Print the comment lines of the compiled program after compiling it.
+    pub comments_only: bool,
+
+    /// Require the bodies of if, else and while to be begin/end blocks instead of allowing
+    /// a single bare statement.
+    pub strict: bool,
+
+    /// Lex a number immediately followed by letters as one invalid token instead of a number
+    /// and an identifier.
+    pub strict_numbers: bool,
+
+    /// Only accept `bool`, not the synonym `boolean`, as the boolean type.
+    pub strict_keywords: bool,
+
+    /// Warn when a declaration shadows a symbol of an enclosing scope.
+    pub warn_shadowing: bool,
+
+    /// Treat variables which are only ever assigned a number once as constants after the
+    /// assignment, and compute operations on constants at compile time.
+    pub aggressive: bool,
+
+    /// The symbols defined for conditional compilation with #if.
+    pub defines: Vec<String>,
+
+    /// Write the stack location and type of every variable and constant to "out.dbg".
+    pub debug_info: bool,
+
+    /// Write the symbol table with its offsets and locations to "out.sym" as each
+    /// procedure and the main block end, including the scopes enclosing it.
+    pub emit_symbols: bool,
+
+    /// The number of registers of the target. R0 and R1 are always used, the registers
+    /// after them hold expression temporaries before any are stored on the stack.
+    pub registers: u32,
+
+    /// The mnemonics the target supports. When set, any other operation in the generated
+    /// program is an error.
+    pub instruction_set: Option<Vec<String>>,
+
+    /// Write the program with the operations indented under their labels and blank lines
+    /// between basic blocks instead of the flat form.
+    pub indent: bool,
+
+    /// Warn when the main block reads a variable before any assignment to it on every path
+    /// that reaches the read. This is approximate, an assignment inside an if only counts
+    /// when both branches make it, one inside a while never counts and procedures are not
+    /// checked at all since what they read depends on where they are called.
+    pub warn_unassigned: bool,
+
+    /// Warn at the end of each block about the variables and constants it declares which
+    /// are never used. A parameter only has to be passed, so it is never reported.
+    pub warn_unused: bool,
+
+    /// Warn about the conditions of if and while statements which only use literals and
+    /// constants, so they always go the same way.
+    pub warn_constant_conditions: bool,
+
+    /// Write each source line as a comment before the code generated for the statement
+    /// starting on it. The parser needs the source text from set_source.
+    pub source_listing: bool,
+
+    /// Write a comment with the line and column of each statement before the code generated
+    /// for it, which unlike the source listing does not need the source text.
+    pub line_comments: bool,
+
+    /// Print boolean expressions as the text "true" or "false" instead of 1 or 0.
+    pub print_bools: bool,
+
+    /// The most constants a constant may be defined through, as in "const b = a;", before
+    /// it is an error. 0 uses the default of 64.
+    pub max_constant_depth: u32,
+
+    /// Fail the compile if there are any warnings, each of which is also reported as an
+    /// error.
+    pub warnings_as_errors: bool,
+
+    /// Report a missing `then`, `do` or `end` and carry on as if it was there, so the errors
+    /// after it are found in the same compile. The compile still fails.
+    pub recover: bool,
+
+    /// Write the most bytes of stack the program can use as a comment at the top of it.
+    pub emit_stack_depth: bool,
+
+    /// Print each error and warning as a line of JSON instead of for people to read.
+    pub json_diagnostics: bool,
+
+    /// The syntax the program is written in. The passes which read the program back, the
+    /// optimizer, indentation, the control flow graph, the label manifest, the intermediate
+    /// representation and the interpreter, only understand the default syntax.
+    pub syntax: Syntax,
+
+    /// Record the symbols visible at the start of each statement, for tools such as editors
+    /// to look up with symbols_at.
+    pub record_scopes: bool,
+
+    /// Log the progress of the lexer, the parser, the expression parser and the symbol
+    /// table.
+    pub verbose: bool,
+}
+
+impl ParserOptions {
+    /// Returns the optimization level the options amount to, 0 for none, 1 for the passes
+    /// over the compiled program and dead branch elimination and 2 for those with constant
+    /// folding and propagation of variables which are only assigned once.
+    pub fn optimization_level(&self) -> u32 {
+        match (self.optimize, self.aggressive) {
+            (true, true) => 2,
+            (true, false) => 1,
+            _ => 0,
+        }
+    }
+
+    /// Turns on the optimizations of the level and turns off the rest. Levels above 2 are
+    /// the same as 2.
+    pub fn set_optimization_level(&mut self, level: u32) {
+        self.optimize = level >= 1;
+        self.aggressive = level >= 2;
+    }
+
+    /// Returns the options for the lexical analysis of the source the parser compiles.
+    pub fn lexer_options(&self) -> LexerOptions {
+        LexerOptions {
+            strict_numbers: self.strict_numbers,
+            strict_keywords: self.strict_keywords,
+            verbose: self.verbose,
+        }
+    }
+}
+
 /// The Parser struct can check syntax for a set of tokens for validity as well as generate
 /// the final code for them.
 pub struct Parser {
     /// The set of tokens for this Parser.
     tokens: Vec<Token>,
 
+    /// The options for this parser.
+    options: ParserOptions,
+
     /// The last expression symbol from the evaluated expression
     last_expression: Option<Symbol>,
 
@@ -106,6 +293,94 @@ pub struct Parser {
 
     /// A vector of declarations for output to the file.
     declarations: Vec<String>,
+
+    /// The string literals printed by shared routines, the index is the routine's number.
+    strings: Vec<String>,
+
+    /// The warnings found while compiling, which do not stop compilation.
+    warnings: Vec<String>,
+
+    /// The errors found while compiling with a specific diagnosis.
+    errors: Vec<String>,
+
+    /// The errors and warnings in the order they were found, with where they were found.
+    diagnostics: Vec<Diagnostic>,
+
+    /// The line and column of the start of each statement with the symbols visible there,
+    /// when scopes are recorded.
+    scopes: Vec<(u32, u32, Vec<Symbol>)>,
+
+    /// The variables which are assigned once and never changed otherwise.
+    single_assignments: HashSet<String>,
+
+    /// The values of the single assignment variables after they have been assigned.
+    known_constants: HashMap<String, String>,
+
+    /// The number of array elements read at indexes computed when the program runs, which
+    /// names the symbols holding them.
+    elements: u32,
+
+    /// The number of control structure bodies the parser is inside of.
+    nesting: u32,
+
+    /// The number of procedure declarations the parser is inside of.
+    proc_depth: u32,
+
+    /// The variables of the main block which are assigned on every path to the current
+    /// statement, or have already been warned about.
+    assigned: HashSet<String>,
+
+    /// The first name which was used without being declared.
+    undeclared: Option<String>,
+
+    /// The debug table, one line per variable or constant of every scope.
+    debug_info: Vec<String>,
+
+    /// The symbol table as each scope ended, for the symbol file.
+    symbol_dumps: Vec<String>,
+
+    /// The location, value and depth of each constant by identifier. The depth is how many
+    /// constants it is defined through, 0 for a literal.
+    constant_values: HashMap<String, (String, i32, u32)>,
+
+
+    /// The return type of each function, a procedure declared with a return type. The
+    /// result of a call is stored in the global named by result_of.
+    functions: HashMap<String, SymbolValueType>,
+
+    /// The lines of the source text, for the source listing.
+    source: Vec<String>,
+
+    /// The last source line written to the source listing.
+    listed_line: u32,
+
+    /// The names of the source files by the identifier set on their tokens.
+    file_names: Vec<String>,
+
+    /// The bytes of stack each procedure uses as (frame, temps), where the frame is the saved
+    /// frame pointer and the locals and temps is the most its expressions use above it. The
+    /// main program is "mainblock" with no frame.
+    frames: HashMap<String, (u32, u32)>,
+
+    /// The procedures and functions called by each procedure, "mainblock" for the main program,
+    /// with the most bytes of temps and arguments it has pushed below the frame of a call.
+    calls: HashMap<String, HashMap<String, u32>>,
+
+    /// The bytes the stack pointer is moved past at the start of the program.
+    global_size: u32,
+
+    /// The names of the procedures being parsed, the innermost last.
+    parsing_procs: Vec<String>,
+
+    /// The number of begin/end statements the parser is inside of, not counting the
+    /// blocks of the program and procedures.
+    open_blocks: u32,
+
+    /// The number of while bodies the parser is inside of.
+    loops: u32,
+
+    /// The parameters of the procedure being declared with their types, in order.
+    parameters: Vec<(Token, SymbolValueType)>,
 }
 
 /// The parser is implemented with some convenience functions for many rules. However,
@@ -114,64 +389,284 @@ pub struct Parser {
 /// correct rule.
 impl Parser {
 
-    /// Returns a new parser given the input tokens.
+    /// Returns a new parser given the input tokens. The tokens are parsed as they are, so
+    /// any directives must already have been evaluated with preprocess.
     pub fn new_with_tokens(tokens: Vec<Token>) -> Parser {
+        Parser::new_with_options(tokens, ParserOptions::default())
+    }
+
+    /// Returns a new parser given the input tokens and the options to use. The defines of the
+    /// options are not applied here, the tokens must already have been preprocessed with them.
+    pub fn new_with_options(tokens: Vec<Token>, options: ParserOptions) -> Parser {
+        let mut symbol_table = SymbolTable::empty();
+        symbol_table.set_registers(options.registers);
+        symbol_table.set_verbose(options.verbose);
+        symbol_table.set_syntax(options.syntax.clone());
+        let commands = CommandBuilder::new(options.verbose, symbol_table.syntax());
+
         Parser {
             tokens: tokens,
 
+            options: options,
+
             last_token: None,
 
             last_expression: None,
 
-            symbol_table: SymbolTable::empty(),
+            symbol_table: symbol_table,
 
-            commands: CommandBuilder::new(),
+            commands: commands,
 
             declarations: Vec::<String>::new(),
+
+            strings: Vec::<String>::new(),
+
+            warnings: Vec::<String>::new(),
+
+            errors: Vec::<String>::new(),
+
+            diagnostics: Vec::<Diagnostic>::new(),
+
+            scopes: Vec::new(),
+
+            single_assignments: HashSet::<String>::new(),
+
+            known_constants: HashMap::<String, String>::new(),
+
+            elements: 0,
+
+            nesting: 0,
+
+            proc_depth: 0,
+
+            assigned: HashSet::<String>::new(),
+
+            undeclared: None,
+
+            debug_info: Vec::<String>::new(),
+
+            symbol_dumps: Vec::<String>::new(),
+
+            constant_values: HashMap::<String, (String, i32, u32)>::new(),
+            functions: HashMap::<String, SymbolValueType>::new(),
+
+            source: Vec::<String>::new(),
+
+            listed_line: 0,
+
+            file_names: Vec::<String>::new(),
+
+            frames: HashMap::<String, (u32, u32)>::new(),
+            calls: HashMap::<String, HashMap<String, u32>>::new(),
+            global_size: 0,
+            parsing_procs: Vec::<String>::new(),
+            open_blocks: 0,
+            loops: 0,
+            parameters: Vec::<(Token, SymbolValueType)>::new(),
         }
     }
 
-    /// Starts to parse on the set of input tokens.
+    /// Sets the source text the tokens were read from, which the source listing quotes.
+    pub fn set_source(&mut self, source: &str) {
+        self.source = source.lines().map(|l| l.trim().to_string()).collect();
+    }
+
+    /// Sets the names of the source files, where the name at index n is the file of the
+    /// tokens with the identifier n, so warnings and errors say which file they are in.
+    pub fn set_file_names(&mut self, names: Vec<String>) {
+        self.file_names = names;
+    }
+
+    /// Starts to parse on the set of input tokens and writes the compiled program to a file.
     pub fn parse(&mut self) -> ParserResult {
-        match self.program() {
+        match self.compile() {
+            ParserResult::Success => {
+                let _ = self.write_files("out.pal");
+                ParserResult::Success
+            },
+            r => r,
+        }
+    }
+
+    /// Writes the compiled program to the file with the given name along with the files
+    /// the options ask for, returning Err(e) if the program could not be written.
+    pub fn write_files(&self, output: &str) -> io::Result<()> {
+        match file_with_text(output, &self.compiled_output()) {
+            Ok(f) => {
+                log!(self.options.verbose, "<YASLC/Parser> Successfully wrote file {:?}!", f);
+            },
+            Err(e) => {
+                log!(self.options.verbose, "<YASLC/Parser> Error writing file: {:?}", e);
+                return Err(e);
+            },
+        };
+
+        if self.options.emit_cfg {
+            match file_named("out.dot", self.cfg().to_dot()) {
+                Ok(f) => {
+                    log!(self.options.verbose, "<YASLC/Parser> Successfully wrote control flow graph {:?}!", f);
+                },
+                Err(e) => {
+                    println!("<YASLC/Parser> Error writing control flow graph: {:?}", e);
+                },
+            };
+        }
+
+        if self.options.emit_labels {
+            let manifest = self.labels().iter().map(|&(ref l, n)| format!("{} {}", l, n)).collect();
+            match file_named("out.labels", manifest) {
+                Ok(f) => {
+                    log!(self.options.verbose, "<YASLC/Parser> Successfully wrote label manifest {:?}!", f);
+                },
+                Err(e) => {
+                    println!("<YASLC/Parser> Error writing label manifest: {:?}", e);
+                },
+            };
+        }
+
+        if self.options.debug_info {
+            match file_named("out.dbg", self.debug_info.clone()) {
+                Ok(f) => {
+                    log!(self.options.verbose, "<YASLC/Parser> Successfully wrote debug info {:?}!", f);
+                },
+                Err(e) => {
+                    println!("<YASLC/Parser> Error writing debug info: {:?}", e);
+                },
+            };
+        }
+
+        if self.options.emit_symbols {
+            match file_with_text("out.sym", &self.symbol_dumps.concat()) {
+                Ok(f) => {
+                    log!(self.options.verbose, "<YASLC/Parser> Successfully wrote symbol table {:?}!", f);
+                },
+                Err(e) => {
+                    println!("<YASLC/Parser> Error writing symbol table: {:?}", e);
+                },
+            };
+        }
+
+        if self.options.comments_only {
+            for c in self.comment_trace() {
+                println!("{}", c);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses the set of input tokens and assembles the compiled program in
+    /// self.declarations without writing it anywhere.
+    pub fn compile(&mut self) -> ParserResult {
+        // The passes which read the program back only know the default syntax
+        let rereads = self.options.optimize || self.options.indent || self.options.emit_cfg || self.options.emit_labels;
+        if rereads && self.options.syntax != Syntax::default() {
+            let error = self.diagnostic(Severity::Error, None, "The optimizer, indentation, the control flow graph and the label manifest only read the default syntax".to_string());
+            self.report(error);
+            return ParserResult::Unexpected;
+        }
+
+        if self.options.aggressive {
+            self.single_assignments = Parser::single_assignments(&self.tokens);
+        }
+
+        let state = if self.options.expression_only {
+            self.expression_program()
+        } else {
+            self.program()
+        };
+
+        match state {
             ParserState::Done(r) => {
                 match r {
+                    // Parsing recovered from the errors but the program is still wrong
+                    ParserResult::Success if !self.errors.is_empty() => {
+                        println!("<YASLC/Parser> Found {} error(s).", self.errors.len());
+                        return ParserResult::Unexpected;
+                    },
                     ParserResult::Success => {
-                        log!("<YASLC/Parser> Correctly parsed YASL program file.");
+                        log!(self.options.verbose, "<YASLC/Parser> Correctly parsed YASL program file.");
+
+                        // Routines for shared strings go after the end of the program
+                        self.push_string_routines();
 
-                        // Get the number of declarations
-                        let n_decl = self.declarations.len();
+                        self.record_debug_info("mainblock".to_string());
+                        self.record_symbols();
 
-                        // Move the SP based on the number of declarations
-                        self.declarations.push(format!("addw #{}, SP", n_decl * 4));
+                        // Move the SP past the globals and the temps of the main block
+                        self.global_size = match self.stack_adjustment() {
+                            Some(size) => size,
+                            None => {
+                                let error = self.diagnostic(Severity::Error, None, "The globals and temps of the program need more stack than fits in a word".to_string());
+                                self.report(error);
+                                return ParserResult::Unexpected;
+                            },
+                        };
+                        let syntax = self.syntax();
+                        self.declarations.push(format!("addw {}, {}", syntax.immediate(self.global_size), syntax.stack_pointer));
+                        self.frames.insert("mainblock".to_string(), (0, self.symbol_table.temp_high_water()));
                         self.declarations.push(format!(""));
 
                         // Create one list of commands
                         self.declarations.append(&mut self.commands.commands);
 
                         // "Fix" commands with prepends and appends
-                        self.declarations.insert(0, format!("$main movw SP R0"));
-                        self.declarations.insert(0, format!("$junk #1"));
+                        self.declarations.insert(0, format!("{} movw {} {}", syntax.label("main"), syntax.stack_pointer, syntax.register(0)));
+                        self.declarations.insert(0, format!("{} {}", syntax.label("junk"), syntax.immediate(1)));
                         self.declarations.insert(0, format!(": Initialize junk variable and setup the stack"));
 
-                        match file_from(self.declarations.clone()) {
-                            Ok(f) => {
-                                log!("<YASLC/Parser> Successfully wrote file {:?}!", f);
-                            },
-                            Err(e) => {
-                                log!("<YASLC/Parser> Error writing file: {:?}", e);
-                            },
-                        };
+                        if self.options.optimize {
+                            self.optimize();
+                        }
+
+                        if !self.check_instruction_set() {
+                            return ParserResult::Unexpected;
+                        }
+
+                        if self.options.emit_stack_depth {
+                            let comment = match self.stack_depth() {
+                                Some(d) => format!(": Maximum stack depth of {} bytes", d),
+                                None => ": Unbounded stack depth, the program is recursive".to_string(),
+                            };
+                            println!("<YASLC/Parser> {}", &comment[2..]);
+                            self.declarations.insert(0, comment);
+                        }
+
+                        if self.options.warnings_as_errors && !self.warnings.is_empty() {
+                            let warnings: Vec<Diagnostic> = self.diagnostics.iter()
+                                .filter(|d| d.severity == Severity::Warning)
+                                .cloned()
+                                .collect();
+                            for w in warnings {
+                                self.report(Diagnostic {
+                                    severity: Severity::Error,
+                                    message: format!("{} (warnings are errors)", w.message),
+                                    ..w
+                                });
+                            }
+                            return ParserResult::Unexpected;
+                        }
 
                         return ParserResult::Success;
                     },
                     // It was not a success, figure out what went wrong.
                     _ => {
-                        // Get the error token
-                        if let Some(t) = self.last_token() {
-                            println!("<YASLC/Parser> Error: Unexpected token at ({}, {}) of type: {}", t.line(), t.column(), t.token_type());
-                        } else {
-                            println!("<YASC/Parser> Internal error: Could not find the error token, we don't know what went wrong.");
+                        // Get the error token, which is empty if the file ended too soon. A rule
+                        // may have put it back, then it is the next token
+                        let token = self.last_token().or_else(|| self.tokens.first().cloned());
+                        let end_of_file = |t: &Token| t.is_type(TokenType::Invalid) && t.lexeme().is_empty();
+                        let ended = self.tokens.iter().all(end_of_file) || token.as_ref().is_some_and(end_of_file);
+                        match token {
+                            // Running out of tokens is an error of its own unless a rule reported why
+                            _ if ended && self.errors().is_empty() => {
+                                let error = self.diagnostic(Severity::Error, token.as_ref(), "Unexpected end of file".to_string());
+                                self.report(error);
+                            },
+                            Some(ref t) if ended => {
+                                println!("<YASLC/Parser> Error: Unexpected end of file after ({}, {})", t.line(), t.column());
+                            },
+                            Some(t) => println!("<YASLC/Parser> Error: Unexpected token at ({}, {}) of type: {}", t.line(), t.column(), t.token_type()),
+                            None => println!("<YASC/Parser> Internal error: Could not find the error token, we don't know what went wrong."),
                         }
                         return ParserResult::Unexpected;
                     }
@@ -189,149 +684,768 @@ impl Parser {
         }
     }
 
-    /// Pops the front token off the stack of tokens and returns it.
-    fn next_token(&mut self) -> Token {
-        if self.tokens.len() == 0 {
-            panic!("Unexpected end of file!");
+    /// Returns the bytes the stack pointer is moved past at the start of the program, which
+    /// holds every declaration, the globals of the root table and the most the temps of the
+    /// main block use, or None if it does not fit in a word.
+    fn stack_adjustment(&self) -> Option<u32> {
+        let declared = u32::try_from(self.declarations.len()).ok()?.checked_mul(4)?;
+        let globals = self.symbol_table.scope_size();
+        declared.max(globals).checked_add(self.symbol_table.temp_high_water())
+    }
+
+    /// Returns the most bytes of stack the compiled program can use, the globals and the
+    /// deepest chain of calls with the temps and arguments pushed for each, or None if a
+    /// procedure can call itself so there is no limit.
+    pub fn stack_depth(&self) -> Option<u32> {
+        let mut calling = Vec::<String>::new();
+        self.depth_from("mainblock", &mut calling).map(|d| d + self.global_size)
+    }
+
+    /// Returns the most bytes of stack a call of the procedure can use, or None if it can
+    /// call one of the procedures which are calling it.
+    fn depth_from(&self, proc_name: &str, calling: &mut Vec<String>) -> Option<u32> {
+        if calling.iter().any(|p| p == proc_name) {
+            return None;
         }
-        let t = self.tokens.remove(0);
 
-        self.last_token = Some(t.clone());
+        let (frame, temps) = self.frames.get(proc_name).cloned().unwrap_or((0, 0));
 
-        t
+        calling.push(proc_name.to_string());
+        let mut deepest = temps;
+        if let Some(callees) = self.calls.get(proc_name) {
+            for (c, below) in callees.iter() {
+                deepest = deepest.max(below + self.depth_from(c, calling)?);
+            }
+        }
+        calling.pop();
+
+        Some(frame + deepest)
     }
 
-    /// Returns a copy of the last token popped.
-    fn last_token(&mut self) -> Option<Token> {
-        self.last_token.clone()
+    /// Records a call of the procedure from the current one with the bytes of temps and
+    /// arguments on the stack below its frame.
+    fn record_call(&mut self, callee: &str, below: u32) {
+        let caller = match self.parsing_procs.last() {
+            Some(p) => p.clone(),
+            None => "mainblock".to_string(),
+        };
+        let most = self.calls.entry(caller).or_default().entry(callee.to_string()).or_insert(0);
+        *most = (*most).max(below);
     }
 
-    /// Inserts the last token popped into the token set.
-    fn insert_last_token(&mut self) {
-        if let Some(a) = self.last_token() {
-            self.tokens.insert(0, a);
-            self.last_token = None;
+    /// Runs the optimization passes over the compiled program. Only the lines a pass changes
+    /// are written again, every other line keeps the text it was generated with.
+    fn optimize(&mut self) {
+        let instructions = Instruction::from_commands(&self.declarations);
+
+        let optimized = propagate_constants(instructions.clone());
+
+        let mut lines = instructions.into_iter().zip(optimized)
+            .map(|(before, after)| if before == after { None } else { Some(format!("{}", after)) });
+        self.declarations = self.declarations.iter().map(|c| {
+            c.split('\n').map(|line| match lines.next() {
+                Some(Some(l)) => l,
+                _ => line.to_string(),
+            }).collect::<Vec<String>>().join("\n")
+        }).collect();
+    }
+
+    /// Reports every operation of the generated program whose mnemonic is not in the
+    /// instruction set of the target and returns true if there were none.
+    fn check_instruction_set(&mut self) -> bool {
+        let unsupported: Vec<String> = match self.options.instruction_set {
+            Some(ref set) => {
+                Instruction::from_commands_in(&self.declarations, &self.options.syntax).iter()
+                    .filter_map(|i| i.mnemonic().map(|m| m.to_string()))
+                    .filter(|m| !set.contains(m))
+                    .collect()
+            },
+            None => return true,
+        };
+
+        for m in unsupported.iter() {
+            self.report(Diagnostic {
+                severity: Severity::Error,
+                message: format!("The target does not support the instruction `{}`", m),
+                file: None,
+                position: None,
+            });
+        }
+
+        unsupported.is_empty()
+    }
+
+    /// Returns the lines of the compiled program to write, indented if the options ask for it.
+    pub fn listing(&self) -> Vec<String> {
+        if self.options.indent {
+            indented(&Instruction::from_commands(&self.declarations))
         } else {
-            log!("<YASLC/Parser> Internal warning: Attempted to insert the last token into the parser but there is no last token!");
+            self.declarations.clone()
         }
     }
 
-    /// Checks the next token for the token type t and returns the parser state (continue or done)
-    /// based on the input.
-    fn check(&mut self, t: TokenType) -> ParserState {
-        let token = self.next_token();
+    /// Returns the text of the compiled program exactly as write_files writes it, without
+    /// touching the filesystem.
+    pub fn compiled_output(&self) -> String {
+        text_of(&self.listing())
+    }
 
-        log!("<YASLC/Parser> Checking if token {} is of type {}.", token, t);
-        log!("\t\t\t {} tokens left in vector.", self.tokens.len());
+    /// Returns the compiled program as the JSON intermediate representation, which
+    /// from_ir_json turns back into the lines of the program.
+    pub fn to_ir_json(&self) -> String {
+        ir::to_ir_json(&Instruction::from_commands(&self.declarations))
+    }
 
-        self.check_token(t, token)
+    /// Returns each label of the compiled program with the index of the instruction it
+    /// marks, not counting comments and blank lines.
+    pub fn labels(&self) -> Vec<(String, usize)> {
+        label_manifest(&Instruction::from_commands(&self.declarations))
     }
 
-    /// Checks if the token is the correct type and returns Continue if it is, Unexpected token
-    /// otherwise.
-    fn check_token(&mut self, t: TokenType, token: Token) -> ParserState {
-        match token.is_type(t) {
-            true => ParserState::Continue,
-            false => ParserState::Done(ParserResult::Unexpected),
-        }
+    /// Returns the control flow graph of the compiled program.
+    fn cfg(&self) -> ControlFlowGraph {
+        ControlFlowGraph::from_instructions(&Instruction::from_commands(&self.declarations))
     }
 
-    //. Checks the token for the first token type t1. If it fails it checks the token for type t2.
-    //. Returns success if either is the type of token.
-    fn check_and_then_check(&mut self, t1: TokenType, t2: TokenType)
-        -> (ParserState, Option<TokenType>) {
-        match self.check(t1.clone()) {
-            ParserState::Continue => (ParserState::Continue, Some(t1)),
-            ParserState::Done(_) => {
-                self.insert_last_token();
-                (self.check(t2.clone()), Some(t2))
-            },
-        }
+    /// Returns the warnings found while compiling.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
     }
 
-    /// Adds the string command to the list of commands.
-    fn push_command(&mut self, command: String) {
-        log!("<YASLC/Parser> Adding command to list of output: \'{}\'", command);
-        self.commands.push_command(command);
+    /// Prints the warning for the last token and records it.
+    fn warn(&mut self, message: String) {
+        let token = self.last_token();
+        let warning = self.diagnostic(Severity::Warning, token.as_ref(), message);
+        self.report(warning);
     }
 
-    fn push_prefix(&mut self, prefix: String) -> String {
-        self.commands.set_prefix(prefix)
+    /// Prints the warning for the token and records it.
+    fn warn_at(&mut self, token: &Token, message: String) {
+        let warning = self.diagnostic(Severity::Warning, Some(token), message);
+        self.report(warning);
     }
 
-    /// Adds the print command, which is a series of single character outputs.
-    fn add_print_command(&mut self, print_message: &str) {
-        let mut i = 0;
-        for c in print_message.chars() {
-            if i != 0 && i != print_message.len()-1 {
-                self.push_command(format!("outb #{}", c as u8));
-            }
-            i += 1;
-        }
-        self.push_command(format!("outb #10"));
+    /// Returns the errors diagnosed while compiling.
+    pub fn errors(&self) -> &[String] {
+        &self.errors
     }
 
-    /**
-     * YASL Context free grammar rules
-     */
+    /// Returns the first name which was used without being declared, if there was one.
+    pub fn undeclared(&self) -> Option<&str> {
+        self.undeclared.as_deref()
+    }
 
-    /*
-     *  PROGRAM rule
-     */
-    fn program(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting PROGRAM rule.");
+    /// Returns the token the parser stopped at when the compile failed, if there was one.
+    pub fn unexpected_token(&self) -> Option<&Token> {
+        self.last_token.as_ref()
+    }
 
-        c_token!(self, TokenType::Keyword(KeywordType::Program));
-        c_token!(self, TokenType::Identifier);
-        c_token!(self, TokenType::Semicolon);
+    /// Records the name as used without being declared unless another name already was.
+    fn record_undeclared(&mut self, name: &str) {
+        if self.undeclared.is_none() {
+            self.undeclared = Some(name.to_string());
+        }
+    }
 
-        c_exp!(self.block());
+    /// Returns the errors and warnings in the order they were found.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
 
-        c_token!(self, TokenType::Period, ParserState::Continue, {
-            log!("<YASLC/Parser> Exiting Parser because we found the final period.");
-            self.push_command(format!("inb $junk"));
-            self.push_command(format!("end"));
-            ParserState::Done(ParserResult::Success)
-        })
+    /// Returns the symbols visible at the line and column, innermost scope first, as they
+    /// were at the start of the last statement before it. Scopes must be recorded with the
+    /// record_scopes option, otherwise there are none.
+    pub fn symbols_at(&self, line: u32, column: u32) -> &[Symbol] {
+        self.scopes.iter()
+            .filter(|&&(l, c, _)| (l, c) <= (line, column))
+            .max_by_key(|&&(l, c, _)| (l, c))
+            .map_or(&[], |(_, _, symbols)| symbols)
     }
 
-    // BLOCK rule
-    fn block (&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting BLOCK rule.");
+    /// Prints the error for the token and records it.
+    fn error(&mut self, token: &Token, message: String) {
+        let error = self.diagnostic(Severity::Error, Some(token), message);
+        self.report(error);
+    }
 
-        let proc_t = self.symbol_table.current_proc();
+    /// Returns the diagnostic about the token, which is in the file with the name of the
+    /// identifier set on it.
+    fn diagnostic(&self, severity: Severity, token: Option<&Token>, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: severity,
+            message: message,
+            file: token.and_then(|t| t.file()).and_then(|f| self.file_names.get(f as usize)).cloned(),
+            position: token.map(|t| (t.line(), t.column())),
+        }
+    }
 
-        if self.commands.prefix.is_some() {
-            self.commands.push_useless();
+    /// Prints the diagnostic in the format of the options and records it.
+    fn report(&mut self, diagnostic: Diagnostic) {
+        if self.options.json_diagnostics {
+            println!("{}", diagnostic.to_json());
+        } else {
+            match diagnostic.severity {
+                Severity::Error => println!("<YASLC/Parser> Error: {}", diagnostic),
+                Severity::Warning => println!("<YASLC/Parser> Warning: {}", diagnostic),
+            }
         }
 
-        if proc_t != "mainblock" {
-            self.push_command(format!(": Block {}", proc_t));
-            self.commands.set_prefix(format!("${}", proc_t));
+        match diagnostic.severity {
+            Severity::Error => self.errors.push(diagnostic.to_string()),
+            Severity::Warning => self.warnings.push(diagnostic.to_string()),
         }
+        self.diagnostics.push(diagnostic);
+    }
 
-        c_exp!(self.consts());
-        c_exp!(self.vars());
+    /// Reports the token if it is a control structure keyword outside of its structure and
+    /// returns true if it was.
+    fn stray_keyword(&mut self, token: &Token) -> bool {
+        let message = match token.token_type() {
+            TokenType::Keyword(KeywordType::Do) => "`do` without matching `while`",
+            TokenType::Keyword(KeywordType::To) => "`to` without matching `for`",
+            TokenType::Keyword(KeywordType::Then) => "`then` without matching `if`",
+            TokenType::Keyword(KeywordType::Else) => "`else` without matching `if`",
+            TokenType::Keyword(KeywordType::Elsif) => "`elsif` without matching `if`",
+            _ => return false,
+        };
 
-        if proc_t == "mainblock" {
-            self.push_command(format!(": Jump to block {} of execution", proc_t));
-            self.push_command(format!("jmp ${}", proc_t));
-            self.push_command(format!(""));
+        self.error(token, message.to_string());
+        true
+    }
+
+    /// Returns the warning for declaring the name in the current scope if it shadows a
+    /// symbol of an enclosing scope and shadowing is warned about.
+    fn shadowing(&self, id: &str) -> Option<String> {
+        if !self.options.warn_shadowing {
+            return None;
+        }
+
+        self.symbol_table.shadows(id).map(|scope| {
+            format!("{} in {} shadows a declaration in {}.", id, self.symbol_table.current_proc(), scope)
+        })
+    }
+
+    /// Adds the symbol to the symbol table, warning if it shadows an enclosing symbol.
+    fn declare(&mut self, id: String, t: SymbolType) -> ParserState {
+        let token = self.last_token();
+        self.declare_at(token.as_ref(), id, t)
+    }
+
+    /// Adds the symbol to the symbol table like declare, reporting a warning or error about
+    /// the declaration at the token which names it.
+    fn declare_at(&mut self, token: Option<&Token>, id: String, t: SymbolType) -> ParserState {
+        if let Some(warning) = self.shadowing(&id) {
+            let warning = self.diagnostic(Severity::Warning, token, warning);
+            self.report(warning);
+        }
+
+        match self.symbol_table.add(id, t) {
+            Ok(_) => ParserState::Continue,
+            Err(e) => {
+                let error = self.diagnostic(Severity::Error, token, e);
+                self.report(error);
+                ParserState::Done(ParserResult::Unexpected)
+            },
+        }
+    }
+
+    /// Writes out the compound assignment such as "x += e" or "a[i] *= e" which starts at
+    /// the next token as "x = x + (e)", with the target being assigned, so it is parsed as an
+    /// ordinary assignment. Does nothing if the next token is not a compound assignment.
+    fn expand_compound_assignment(&mut self, target: &[Token]) {
+        let t = match self.tokens.first() {
+            Some(t) => t.clone(),
+            None => return,
+        };
+        let operator = match t.token_type() {
+            TokenType::PlusAssign => (TokenType::Plus, "+"),
+            TokenType::MinusAssign => (TokenType::Minus, "-"),
+            TokenType::StarAssign => (TokenType::Star, "*"),
+            _ => return,
+        };
+        self.tokens.remove(0);
+
+        let at = |lexeme: &str, t_type: TokenType| {
+            let mut new = Token::new_with(t.line(), t.column(), lexeme.to_string(), t_type);
+            if let Some(file) = t.file() {
+                new.set_file(file);
+            }
+            new
+        };
+        let mut expanded = vec![at("=", TokenType::Assign)];
+        expanded.extend(target.iter().cloned());
+        expanded.push(at(operator.1, operator.0));
+        expanded.push(at("(", TokenType::LeftParen));
+
+        // The value is everything up to where the expression parser would stop
+        let mut depth = 0;
+        while let Some(next) = self.tokens.first() {
+            match next.token_type() {
+                TokenType::LeftParen => depth += 1,
+                TokenType::RightParen if depth > 0 => depth -= 1,
+                _ if Parser::ends_expression(next, expanded.last()) => break,
+                _ => {},
+            };
+            expanded.push(self.tokens.remove(0));
+        }
+        expanded.push(at(")", TokenType::RightParen));
+
+        self.tokens.splice(0..0, expanded);
+    }
+
+    /// Returns the identifiers which are declared once, assigned once and never prompted
+    /// into or passed to a procedure, so they only ever hold one value after the assignment.
+    fn single_assignments(tokens: &[Token]) -> HashSet<String> {
+        let mut declared = HashMap::<String, u32>::new();
+        let mut assigned = HashMap::<String, u32>::new();
+        let mut excluded = HashSet::<String>::new();
+
+        for (n, t) in tokens.iter().enumerate() {
+            if !t.is_type(TokenType::Identifier) {
+                continue;
+            }
+
+            let previous = if n > 0 { Some(tokens[n - 1].token_type()) } else { None };
+            match previous {
+                Some(TokenType::Keyword(KeywordType::Var)) | Some(TokenType::Keyword(KeywordType::Const))
+                | Some(TokenType::Keyword(KeywordType::Proc)) => {
+                    *declared.entry(t.lexeme()).or_insert(0) += 1;
+                },
+
+                // Prompted into, a parameter, an argument, declared after another in a list,
+                // counted by a for loop or swapped
+                Some(TokenType::Comma) | Some(TokenType::LeftParen) | Some(TokenType::Keyword(KeywordType::For))
+                | Some(TokenType::Keyword(KeywordType::Swap)) => {
+                    excluded.insert(t.lexeme());
+                },
+                _ => {},
+            };
+
+            match tokens.get(n + 1).map(|t| t.token_type()) {
+                Some(TokenType::Assign) | Some(TokenType::PlusAssign) | Some(TokenType::MinusAssign)
+                | Some(TokenType::StarAssign) => {
+                    *assigned.entry(t.lexeme()).or_insert(0) += 1;
+                },
+                _ => {},
+            }
+        }
+
+        assigned.into_iter()
+            .filter(|&(ref id, n)| n == 1 && declared.get(id) == Some(&1) && !excluded.contains(id))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Warns about each variable of the main block that the tokens read before it has been
+    /// assigned. Only the first read of a variable is reported.
+    fn check_assigned(&mut self, tokens: &[Token]) {
+        if self.proc_depth > 0 {
+            return;
+        }
+
+        for t in tokens.iter().filter(|t| t.is_type(TokenType::Identifier)) {
+            let id = t.lexeme();
+            let variable = match self.symbol_table.get(&id) {
+                Some(s) => matches!(s.symbol_type, SymbolType::Variable(_)),
+                None => false,
+            };
+
+            if variable && !self.assigned.contains(&id) {
+                self.assigned.insert(id.clone());
+                self.warn_at(t, format!("`{}` is read before it is assigned", id));
+            }
+        }
+    }
+
+    /// Returns the number assigned to the identifier if the next tokens assign a single
+    /// number to a single assignment variable, outside of any control structure.
+    fn constant_assignment(&self, id: &str) -> Option<String> {
+        if !self.options.aggressive || self.nesting > 0
+            || self.symbol_table.current_proc() != "mainblock"
+            || !self.single_assignments.contains(id) {
+            return None;
+        }
+
+        match (self.tokens.first(), self.tokens.get(1)) {
+            (Some(n), Some(e)) if n.is_type(TokenType::Number)
+                && (e.is_type(TokenType::Semicolon) || e.is_type(TokenType::Keyword(KeywordType::End))) => {
+                Some(n.lexeme())
+            },
+            _ => None,
+        }
+    }
+
+    /// Adds the variables and constants of the current scope to the debug table as
+    /// "scope identifier kind type location", for example "mainblock x var int +0@R0".
+    fn record_debug_info(&mut self, scope: String) {
+        if !self.options.debug_info {
+            return;
+        }
+
+        for s in self.symbol_table.symbols().iter().rev().filter(|s| !s.is_temp() && !s.is_element()) {
+            let (kind, v_type) = match s.symbol_type {
+                SymbolType::Variable(ref v) => ("var", v),
+                SymbolType::Constant(ref v) => ("const", v),
+                SymbolType::Procedure(_) => continue,
+            };
+
+            self.debug_info.push(format!("{} {} {} {} {}", scope, s.identifier(), kind, v_type, s.location()));
+        }
+    }
+
+    /// Adds the dump of the symbol table to the symbol file as the current scope ends.
+    fn record_symbols(&mut self) {
+        if self.options.emit_symbols {
+            self.symbol_dumps.push(self.symbol_table.dump());
+        }
+    }
+
+    /// Adds the source line as a comment before the code that follows, unless it has
+    /// already been added.
+    fn list_source_line(&mut self, line: u32) {
+        if line <= self.listed_line {
+            return;
+        }
+        self.listed_line = line;
+
+        if let Some(text) = self.source.get(line as usize - 1).cloned() {
+            self.push_command(format!("\n: {}: {}", line, text));
+        }
+    }
+
+    /// Returns only the comment lines of the compiled program in order, which traces the
+    /// blocks and expressions the compiler generated code for.
+    fn comment_trace(&self) -> Vec<String> {
+        Instruction::from_commands_in(&self.declarations, &self.options.syntax).iter()
+            .filter(|i| matches!(**i, Instruction::Comment(_)))
+            .map(|i| format!("{}", i))
+            .collect()
+    }
+
+    /// Pops the front token off the stack of tokens and returns it.
+    fn next_token(&mut self) -> Token {
+        if self.tokens.len() == 0 {
+            // Return an invalid token at the end of the file which no rule accepts
+            println!("<YASLC/Parser> Unexpected end of file!");
+            let (line, column) = match self.last_token {
+                Some(ref t) => (t.line(), t.column()),
+                None => (0, 0),
+            };
+            let t = Token::new_with(line, column, String::new(), TokenType::Invalid);
+            self.last_token = Some(t.clone());
+            return t;
+        }
+        let t = self.tokens.remove(0);
+
+        self.last_token = Some(t.clone());
+
+        t
+    }
+
+    /// Returns a copy of the last token popped.
+    fn last_token(&mut self) -> Option<Token> {
+        self.last_token.clone()
+    }
+
+    /// Inserts the last token popped into the token set.
+    fn insert_last_token(&mut self) {
+        if let Some(a) = self.last_token() {
+            self.tokens.insert(0, a);
+            self.last_token = None;
+        } else {
+            log!(self.options.verbose, "<YASLC/Parser> Internal warning: Attempted to insert the last token into the parser but there is no last token!");
+        }
+    }
+
+    /// Checks the next token for the token type t and returns the parser state (continue or done)
+    /// based on the input.
+    fn check(&mut self, t: TokenType) -> ParserState {
+        let token = self.next_token();
+
+        log!(self.options.verbose, "<YASLC/Parser> Checking if token {} is of type {}.", token, t);
+        log!(self.options.verbose, "\t\t\t {} tokens left in vector.", self.tokens.len());
+
+        self.check_token(t, token)
+    }
+
+    /// Checks if the token is the correct type and returns Continue if it is, Unexpected token
+    /// otherwise.
+    fn check_token(&mut self, t: TokenType, token: Token) -> ParserState {
+        match token.is_type(t) {
+            true => ParserState::Continue,
+            false => ParserState::Done(ParserResult::Unexpected),
+        }
+    }
+
+    //. Checks the token for the first token type t1. If it fails it checks the token for type t2.
+    //. Returns success if either is the type of token.
+    fn check_and_then_check(&mut self, t1: TokenType, t2: TokenType)
+        -> (ParserState, Option<TokenType>) {
+        match self.check(t1.clone()) {
+            ParserState::Continue => (ParserState::Continue, Some(t1)),
+            ParserState::Done(_) => {
+                self.insert_last_token();
+                (self.check(t2.clone()), Some(t2))
+            },
+        }
+    }
+
+    /// Adds the string command to the list of commands.
+    fn push_command(&mut self, command: String) {
+        log!(self.options.verbose, "<YASLC/Parser> Adding command to list of output: \'{}\'", command);
+        self.commands.push_command(command);
+    }
+
+    fn push_prefix(&mut self, prefix: String) -> String {
+        self.commands.set_prefix(prefix)
+    }
+
+    /// Returns the syntax the program is written in.
+    fn syntax(&self) -> Rc<Syntax> {
+        self.symbol_table.syntax()
+    }
+
+    /// Adds the program arguments to the root table. The runtime stores the argument count
+    /// in the first word of the stack, so "argc" is a constant at +0@R0 that the program
+    /// reads but never initializes.
+    fn add_arguments(&mut self) -> ParserState {
+        c_exp!(self.declare("argc".to_string(), SymbolType::Constant(SymbolValueType::Int)));
+
+        // The declaration reserves the word for argc when the stack pointer is moved, looking
+        // it up also means it is never reported as unused
+        if let Some(s) = self.symbol_table.get("argc") {
+            let location = s.location();
+            self.declarations.push(format!(": argc is stored at {} by the runtime", location));
+        }
+
+        ParserState::Continue
+    }
+
+    /// Adds the print command, which is a series of single character outputs, followed by
+    /// a newline if newline is true.
+    ///
+    /// If strings are shared the characters are output by a routine for the string which
+    /// is called instead, so each string is only output once in the program.
+    fn add_print_command(&mut self, print_message: &str, newline: bool) {
+        if self.options.share_strings {
+            let n = match self.strings.iter().position(|s| s == print_message) {
+                Some(n) => n,
+                None => {
+                    self.strings.push(print_message.to_string());
+                    self.strings.len() - 1
+                }
+            };
+            let syntax = self.syntax();
+            self.push_command(format!("call {} {}", syntax.immediate(0), syntax.label(format!("string_{}", n))));
+        } else {
+            for c in Parser::print_commands(&self.syntax(), print_message) {
+                self.push_command(c);
+            }
+        }
+
+        if newline {
+            self.push_command(format!("outb {}", self.options.syntax.immediate(10)));
+        }
+    }
+
+    /// Adds the commands printing "true" or "false" for the boolean value at the location.
+    fn add_bool_print_command(&mut self, location: &str) {
+        let n = self.symbol_table.if_temp();
+        let syntax = self.syntax();
+        self.push_command(format!("cmpw {} {}", syntax.immediate(0), location));
+        self.push_command(format!("beq {}", syntax.label(format!("print_false{}", n))));
+        for c in Parser::print_commands(&syntax, "\"true\"") {
+            self.push_command(c);
+        }
+        self.push_command(format!("jmp {}", syntax.label(format!("print_end{}", n))));
+
+        self.push_prefix(syntax.label(format!("print_false{}", n)));
+        for c in Parser::print_commands(&syntax, "\"false\"") {
+            self.push_command(c);
+        }
+        self.push_prefix(syntax.label(format!("print_end{}", n)));
+    }
+
+    /// Adds the commands printing the characters of the string variable, as many as its
+    /// length.
+    fn add_string_print_command(&mut self, s: &Symbol) {
+        let n = self.symbol_table.if_temp();
+        let syntax = self.syntax();
+        for i in 0..STRING_CAPACITY {
+            self.push_command(format!("cmpw {} {}", syntax.immediate(i), s.location()));
+            self.push_command(format!("bgeq {}", syntax.label(format!("print_string_end{}", n))));
+            self.push_command(format!("outb {}", s.location_past(4 + 4 * i)));
+        }
+        self.push_prefix(syntax.label(format!("print_string_end{}", n)));
+    }
+
+    /// Returns the commands printing the message, which still includes its quotes, without
+    /// a newline after it.
+    fn print_commands(syntax: &Syntax, print_message: &str) -> Vec<String> {
+        let mut commands = Vec::<String>::new();
+
+        // Skip the quotes at the start and end of the message
+        let n = print_message.chars().count();
+        for (i, c) in print_message.chars().enumerate() {
+            if i != 0 && i + 1 != n {
+                commands.push(format!("outb {}", syntax.immediate(c as u8)));
+            }
+        }
+        commands
+    }
+
+    /// Adds a routine for each shared string which prints it and returns.
+    fn push_string_routines(&mut self) {
+        let strings = self.strings.clone();
+        let syntax = self.syntax();
+        for (n, message) in strings.iter().enumerate() {
+            self.push_command(format!("\n: string {}", n));
+            self.push_prefix(syntax.label(format!("string_{}", n)));
+            for c in Parser::print_commands(&syntax, message) {
+                self.push_command(c);
+            }
+            self.push_command("ret".to_string());
+        }
+    }
+
+    /**
+     * YASL Context free grammar rules
+     */
+
+    /*
+     *  PROGRAM rule
+     */
+    fn program(&mut self) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting PROGRAM rule.");
+
+        c_token!(self, TokenType::Keyword(KeywordType::Program));
+        c_token!(self, TokenType::Identifier);
+        c_token!(self, TokenType::Semicolon);
+
+        if self.options.arguments {
+            c_exp!(self.add_arguments());
+        }
+
+        c_exp!(self.block());
+
+        // The `end` of the block, which the period must follow
+        let end = self.last_token();
+        let period = self.next_token();
+        if !period.is_type(TokenType::Period) {
+            if period.is_type(TokenType::Invalid) && period.lexeme().is_empty() {
+                let token = end.unwrap_or(period);
+                self.error(&token, "The program must end with a period after its last `end`".to_string());
+            } else {
+                self.error(&period, format!("Expected a period to end the program but found `{}`", period.lexeme()));
+            }
+            return ParserState::Done(ParserResult::Unexpected);
+        }
+
+        // Nothing may follow the final period
+        if !self.tokens.is_empty() {
+            let junk = self.next_token();
+            self.error(&junk, format!("Found `{}` after the period which ends the program", junk.lexeme()));
+            return ParserState::Done(ParserResult::Unexpected);
+        }
+
+        log!(self.options.verbose, "<YASLC/Parser> Exiting Parser because we found the final period.");
+        self.push_command(format!("inb {}", self.options.syntax.label("junk")));
+        self.push_command(format!("end"));
+        ParserState::Done(ParserResult::Success)
+    }
+
+    /*
+     *  EXPRESSION-PROGRAM rule, used in place of PROGRAM when only compiling an expression
+     */
+    fn expression_program(&mut self) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting EXPRESSION-PROGRAM rule.");
+
+        let syntax = self.syntax();
+        self.push_command(": Block mainblock".to_string());
+        self.push_command(format!("movw {} {}", syntax.stack_pointer, syntax.register(1)));
+
+        // The whole input is the expression
+        let tokens = self.tokens.drain(..).collect();
+        c_exp!(self.parse_expression_tokens(tokens, HashMap::new()));
+
+        let f = match self.last_expression.take() {
+            Some(e) => e,
+            None => {
+                println!("<YASLC/Parser> Expected to find an expression parser but it went missing!");
+                return ParserState::Done(ParserResult::Unexpected);
+            }
+        };
+
+        // Print the value of the expression
+        self.push_command(format!("outw {}", f.location()));
+        self.push_command(format!("outb {}", syntax.immediate(10)));
+
+        self.push_command(format!("inb {}", syntax.label("junk")));
+        self.push_command("end".to_string());
+        ParserState::Done(ParserResult::Success)
+    }
+
+    // BLOCK rule
+    fn block (&mut self) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting BLOCK rule.");
+
+        let proc_t = self.symbol_table.current_proc();
+        let syntax = self.syntax();
+
+        if self.commands.prefix.is_some() {
+            self.commands.push_useless();
+        }
+
+        if proc_t != "mainblock" {
+            self.push_command(format!(": Block {}", proc_t));
+            self.commands.set_prefix(syntax.label(&proc_t));
+
+            // Save the frame pointer of the caller, the frame of this call starts after it
+            self.push_command(format!("movw {} {}", syntax.frame_pointer, syntax.offset(0, &syntax.stack_pointer)));
+            self.push_command(format!("addw {} {}", syntax.immediate(4), syntax.stack_pointer));
+            self.push_command(format!("movw {} {}", syntax.stack_pointer, syntax.frame_pointer));
+        }
+
+        c_exp!(self.consts());
+        c_exp!(self.vars());
+
+        // Move the stack pointer past the locals so each call has its own
+        let frame_size = self.symbol_table.frame_size();
+        if proc_t != "mainblock" && frame_size > 0 {
+            self.push_command(format!("addw {} {}", syntax.immediate(frame_size), syntax.stack_pointer));
+        }
+
+        if proc_t == "mainblock" {
+            self.push_command(format!(": Jump to block {} of execution", proc_t));
+            self.push_command(format!("jmp {}", syntax.label(&proc_t)));
+            self.push_command(format!(""));
         }
 
         c_exp!(self.procs());
 
         c_token!(self, TokenType::Keyword(KeywordType::Begin));
+        let begin = self.last_token();
         if proc_t == "mainblock" {
             self.push_command(format!(": Block {}", proc_t));
-            self.commands.set_prefix(format!("${}", proc_t));
+            self.commands.set_prefix(syntax.label(&proc_t));
         }
 
         c_exp!(self.statements());
 
-        match self.check(TokenType::Keyword(KeywordType::End)) {
+        match self.check_end(begin) {
             ParserState::Continue => {
+                if self.options.warn_unused {
+                    for name in self.symbol_table.unused() {
+                        self.warn(format!("`{}` is declared in {} but never used", name, proc_t));
+                    }
+                }
+
                 if proc_t != "mainblock" {
+                    // Every return of a function jumps to the epilogue
+                    if self.functions.contains_key(&proc_t) {
+                        self.commands.set_prefix(syntax.label(format!("return_{}", proc_t)));
+                    }
+                    self.push_frame_teardown(frame_size);
                     self.push_command(format!("ret\n: end {}\n", proc_t));
                 }
 
@@ -345,7 +1459,7 @@ impl Parser {
      *  CONSTS rule
      */
     fn consts(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting CONSTS rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting CONSTS rule.");
 
         match self.token_const() {
             ParserState::Continue => self.consts(),
@@ -359,61 +1473,97 @@ impl Parser {
 
     // CONST rule
     fn token_const(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting CONST rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting CONST rule.");
 
         c_token!(self, TokenType::Keyword(KeywordType::Const),
             return ParserState::Done(ParserResult::Incorrect));
 
-        let id = match self.check(TokenType::Identifier) {
+        let name = match self.check(TokenType::Identifier) {
             ParserState::Continue => {
-                self.last_token().unwrap().lexeme()
+                self.last_token().unwrap()
             },
             _ => return ParserState::Done(ParserResult::Unexpected),
         };
+        let id = name.lexeme();
 
         c_token!(self, TokenType::Assign);
 
-        let (t, v) = match self.check(TokenType::Number) {
-            ParserState::Continue => {
-                let l = self.last_token().unwrap();
-
-                // If the lexeme is numeric it's a number, otherwise if its "true"/"false its a boolean"
-                // if its neither then crash
-                match l.lexeme().parse::<i32>() {
-                    Ok(n) => {
-                        // Its a number
-                        (SymbolValueType::Int, n)
-                    },
-                    Err(_) => {
-                        // It is not a number, check if it is a boolean
-                        if l.lexeme() == "true" {
-                            (SymbolValueType::Bool, 1)
-                        } else if l.lexeme() == "false" {
-                            (SymbolValueType::Bool, 0)
-                        } else {
-                            // We don't know what it is, crash.
-                            panic!("<YASLC/Parser> Invalid constant value: {}", l.lexeme());
+        // A value of more than one token is an expression, which must fold to a literal
+        let expression = self.tokens.get(1).is_some_and(|t| !t.is_type(TokenType::Semicolon))
+            && !self.tokens.first().is_some_and(|t| t.is_type(TokenType::String));
+
+        let (t, v, depth) = if expression {
+            match self.constant_expression(&id) {
+                Some(c) => c,
+                None => return ParserState::Done(ParserResult::Unexpected),
+            }
+        } else {
+            match self.check_constant_value() {
+                ParserState::Continue => {
+                    let l = self.last_token().unwrap();
+
+                    // A string constant only exists at compile time, it is not stored anywhere
+                    // in the program, only printed or measured with `len`
+                    if l.is_type(TokenType::String) {
+                        c_exp!(self.declare_at(Some(&name), id.clone(), SymbolType::Constant(SymbolValueType::Str)));
+                        self.symbol_table.set_string(&id, l.lexeme());
+
+                        return self.check(TokenType::Semicolon);
+                    }
+
+                    // A constant defined as another constant takes its type and value
+                    if l.is_type(TokenType::Identifier) {
+                        match self.constant_through(&l) {
+                            Some(c) => c,
+                            None => return ParserState::Done(ParserResult::Unexpected),
+                        }
+                    } else {
+                        // If the lexeme is numeric it's a number, otherwise if its "true"/"false its a boolean"
+                        // if its neither then crash
+                        match l.number_value() {
+                            Some(n) => {
+                                // Its a number
+                                (SymbolValueType::Int, n, 0)
+                            },
+                            None => {
+                                // It is not a number, check if it is a real number or a boolean
+                                if let Some(r) = real_value(&l.lexeme()) {
+                                    (SymbolValueType::Real, (r as f32).to_bits() as i32, 0)
+                                } else if l.is_type(TokenType::Keyword(KeywordType::True)) {
+                                    (SymbolValueType::Bool, 1, 0)
+                                } else if l.is_type(TokenType::Keyword(KeywordType::False)) {
+                                    (SymbolValueType::Bool, 0, 0)
+                                } else {
+                                    // We don't know what it is, stop.
+                                    println!("<YASLC/Parser> Invalid constant value: {}", l.lexeme());
+                                    return ParserState::Done(ParserResult::Unexpected);
+                                }
+                            }
                         }
                     }
-                }
-            },
+                },
 
-            _ => return ParserState::Done(ParserResult::Unexpected),
+                _ => return ParserState::Done(ParserResult::Unexpected),
+            }
         };
 
-        self.symbol_table.add(id.clone(), SymbolType::Constant(t));
-        match self.symbol_table.get(&*id) {
+        c_exp!(self.declare_at(Some(&name), id.clone(), SymbolType::Constant(t)));
+        match self.symbol_table.declared(&*id) {
             Some(s) => {
+                // Remember the value for conditions which can be decided at compile time
+                self.constant_values.insert(id.clone(), (s.location(), v, depth));
+
                 // If it is a constant then set the value
-                let c = format!("movw #{} {}", v, s.location());
+                let c = format!("movw {} {}", self.options.syntax.immediate(v), s.location());
                 if self.symbol_table.current_proc() == "mainblock" {
                     self.declarations.push(c);
                 } else {
-                    self.commands.push_command(format!("movw #{} {}", v, s.location()));
+                    self.commands.push_command(format!("movw {} {}", self.options.syntax.immediate(v), s.location()));
                 }
             },
             None => {
-                panic!("Internal error with the symbol table.");
+                println!("<YASLC/Parser> Internal error with the symbol table.");
+                return ParserState::Done(ParserResult::Unexpected);
             }
         }
 
@@ -423,15 +1573,113 @@ impl Parser {
             ParserState::Continue)
     }
 
-    // VARS rule
-    fn vars(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting VARS rule.");
+    // CONSTANT-VALUE rule, a number, true, false, a string or another constant
+    fn check_constant_value(&mut self) -> ParserState {
+        let token = self.next_token();
 
-        match self.var() {
-            ParserState::Continue => self.vars(),
-            ParserState::Done(ParserResult::Incorrect) => {
-                self.insert_last_token();
-                ParserState::Continue
+        match token.token_type() {
+            TokenType::Number | TokenType::Keyword(KeywordType::True)
+            | TokenType::Keyword(KeywordType::False) | TokenType::Identifier
+            | TokenType::String => ParserState::Continue,
+            _ => ParserState::Done(ParserResult::Unexpected),
+        }
+    }
+
+    /// Reads the expression which defines the constant named id and returns Some((t, v, d))
+    /// with the type and value it folds to and the depth of the deepest constant it uses, or
+    /// None after reporting why it can not be computed at compile time.
+    fn constant_expression(&mut self, id: &str) -> Option<(SymbolValueType, i32, u32)> {
+        let start = self.tokens.first().cloned()?;
+        let mut tokens = Vec::<Token>::new();
+        let mut parens = 0;
+        let mut depth = 0;
+        while let Some(t) = self.tokens.first().cloned() {
+            match t.token_type() {
+                TokenType::LeftParen => parens += 1,
+                TokenType::RightParen if parens > 0 => parens -= 1,
+                _ if Parser::ends_expression(&t, tokens.last()) => break,
+                _ => {},
+            };
+            self.next_token();
+
+            // Constants are replaced by their values
+            if !t.is_type(TokenType::Identifier) {
+                tokens.push(t);
+                continue;
+            }
+            let (v_type, v, d) = self.constant_through(&t)?;
+            depth = depth.max(d);
+            let literal = match v_type {
+                SymbolValueType::Bool if v != 0 => Token::new_with(t.line(), t.column(), "true".to_string(), TokenType::Keyword(KeywordType::True)),
+                SymbolValueType::Bool => Token::new_with(t.line(), t.column(), "false".to_string(), TokenType::Keyword(KeywordType::False)),
+                SymbolValueType::Real => Token::new_with(t.line(), t.column(), format!("{:?}", f32::from_bits(v as u32)), TokenType::Number),
+                _ => Token::new_with(t.line(), t.column(), v.to_string(), TokenType::Number),
+            };
+            tokens.push(literal);
+        }
+
+        // Reals are never folded
+        let value = if tokens.iter().any(|t| real_value(&t.lexeme()).is_some()) {
+            None
+        } else {
+            ExpressionParser::new(self.symbol_table.clone(), tokens).and_then(|e| e.constant_value())
+        };
+        match value.as_deref() {
+            Some("true") => Some((SymbolValueType::Bool, 1, depth)),
+            Some("false") => Some((SymbolValueType::Bool, 0, depth)),
+            v => match v.and_then(number_value) {
+                Some(n) => Some((SymbolValueType::Int, n, depth)),
+                None => {
+                    self.error(&start, format!("The value of constant `{}` can not be computed at compile time", id));
+                    None
+                },
+            },
+        }
+    }
+
+    /// Returns Some((t, v, d)) with the type, value and depth of a constant defined as the
+    /// constant named by the token, or None after reporting why it can not be.
+    fn constant_through(&mut self, token: &Token) -> Option<(SymbolValueType, i32, u32)> {
+        let name = token.lexeme();
+
+        // The name must still refer to the constant that was recorded
+        let found = match (self.symbol_table.get(&name), self.constant_values.get(&name)) {
+            (Some(s), Some(&(ref location, v, depth))) if *location == s.location() => {
+                match s.symbol_type {
+                    SymbolType::Constant(ref t) => Some((t.clone(), v, depth + 1)),
+                    _ => None,
+                }
+            },
+            _ => None,
+        };
+
+        let limit = match self.options.max_constant_depth {
+            0 => DEFAULT_CONSTANT_DEPTH,
+            n => n,
+        };
+
+        match found {
+            Some((_, _, depth)) if depth > limit => {
+                self.error(token, format!("Constant `{}` is defined through more than {} constants", name, limit));
+                None
+            },
+            None => {
+                self.error(token, format!("`{}` is not a constant", name));
+                None
+            },
+            c => c,
+        }
+    }
+
+    // VARS rule
+    fn vars(&mut self) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting VARS rule.");
+
+        match self.var() {
+            ParserState::Continue => self.vars(),
+            ParserState::Done(ParserResult::Incorrect) => {
+                self.insert_last_token();
+                self.check_section_order("variable declarations")
             },
             _ => {
                 ParserState::Done(ParserResult::Unexpected)
@@ -441,67 +1689,185 @@ impl Parser {
 
     // VAR rule
     fn var(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting VAR rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting VAR rule.");
 
         c_token!(self, TokenType::Keyword(KeywordType::Var), return ParserState::Done(ParserResult::Incorrect));
 
-        let id = match self.check(TokenType::Identifier) {
-            ParserState::Continue => {self.last_token().unwrap().lexeme()},
-            _ => return ParserState::Done(ParserResult::Unexpected),
-        };
+        // Several variables of the same type can be declared together, as in "var a, b : int;"
+        let mut ids = Vec::<Token>::new();
+        loop {
+            match self.check(TokenType::Identifier) {
+                ParserState::Continue => ids.push(self.last_token().unwrap()),
+                _ => return ParserState::Done(ParserResult::Unexpected),
+            };
+
+            match self.check(TokenType::Comma) {
+                ParserState::Continue => {},
+                _ => {
+                    self.insert_last_token();
+                    break;
+                },
+            };
+        }
 
         c_token!(self, TokenType::Colon);
 
-        let t = match self.token_type() {
-            ParserState::Continue => {
-                match self.last_token().unwrap().token_type() {
-                    TokenType::Keyword(KeywordType::Bool) => {
-                        SymbolValueType::Bool
-                    },
-                    TokenType::Keyword(KeywordType::Int) => {
-                        SymbolValueType::Int
-                    },
-                    _ => {
-                        println!("<YASLC/Parser> Error: Unrecognized type for var found {}.", self.last_token().unwrap());
-                        return ParserState::Done(ParserResult::Unexpected);
+        let array = match self.tokens.first() {
+            Some(t) if t.is_type(TokenType::Keyword(KeywordType::Array)) => Some(self.next_token()),
+            _ => None,
+        };
+
+        let t = match array {
+            Some(array) => match self.array_type(&array) {
+                Some(t) => t,
+                None => return ParserState::Done(ParserResult::Unexpected),
+            },
+            None => match self.token_type() {
+                ParserState::Continue => {
+                    match self.last_token().unwrap().token_type() {
+                        TokenType::Keyword(KeywordType::Bool) => {
+                            SymbolValueType::Bool
+                        },
+                        TokenType::Keyword(KeywordType::Int) => {
+                            SymbolValueType::Int
+                        },
+                        TokenType::Keyword(KeywordType::Char) => {
+                            SymbolValueType::Char
+                        },
+                        TokenType::Keyword(KeywordType::Str) => {
+                            SymbolValueType::Str
+                        },
+                        TokenType::Keyword(KeywordType::Real) => {
+                            SymbolValueType::Real
+                        },
+                        _ => {
+                            println!("<YASLC/Parser> Error: Unrecognized type for var found {}.", self.last_token().unwrap());
+                            return ParserState::Done(ParserResult::Unexpected);
+                        }
                     }
-                }
 
+                },
+                _ => return ParserState::Done(ParserResult::Unexpected),
             },
-            _ => return ParserState::Done(ParserResult::Unexpected),
         };
 
-        self.symbol_table.add(id.clone(), SymbolType::Variable(t));
-        match self.symbol_table.get(&*id) {
-            Some(s) => {
-                // Initialize the value as 0
-                self.declarations.push(format!("movw #0 {}", s.location()));
-            },
-            None => {
-                panic!("Internal error with the symbol table.");
+        // Every element of an array is initialized
+        let words = match t {
+            SymbolValueType::Array(_, n) => n,
+            _ => 1,
+        };
+
+        for name in ids {
+            let id = name.lexeme();
+            c_exp!(self.declare_at(Some(&name), id.clone(), SymbolType::Variable(t.clone())));
+            match self.symbol_table.declared(&*id).cloned() {
+                Some(s) => {
+                    // Initialize the value as 0, in a procedure every call initializes its own
+                    for w in 0..words {
+                        let c = format!("movw {} {}", self.options.syntax.immediate(0), s.location_past(4 * w));
+                        if self.symbol_table.current_proc() == "mainblock" {
+                            self.declarations.push(c);
+                        } else {
+                            self.commands.push_command(c);
+                        }
+                    }
+                },
+                None => {
+                    println!("<YASLC/Parser> Internal error with the symbol table.");
+                    return ParserState::Done(ParserResult::Unexpected);
+                }
             }
         }
 
         self.check(TokenType::Semicolon)
     }
 
+    /// Reads the rest of `array[n] of type` after the array keyword and returns Some(t) with
+    /// the type of the array, or None after reporting why it is not one.
+    fn array_type(&mut self, array: &Token) -> Option<SymbolValueType> {
+        let size = match (self.tokens.first(), self.tokens.get(1), self.tokens.get(2)) {
+            (Some(l), Some(n), Some(r)) if l.is_type(TokenType::LeftBracket)
+                && r.is_type(TokenType::RightBracket) => n.number_value(),
+            _ => None,
+        };
+
+        let size = match size {
+            Some(n) if n > 0 => n as u32,
+            _ => {
+                self.error(array, "The size of an array must be a positive number in brackets, as in `array[10] of int`".to_string());
+                return None;
+            },
+        };
+        self.tokens.drain(..3);
+
+        match self.expect_keyword(KeywordType::Of, "after the size of an array") {
+            ParserState::Continue => {},
+            _ => return None,
+        }
+
+        let element = self.next_token();
+        let t = match element.token_type() {
+            TokenType::Keyword(KeywordType::Int) => SymbolValueType::Int,
+            TokenType::Keyword(KeywordType::Bool) => SymbolValueType::Bool,
+            TokenType::Keyword(KeywordType::Char) => SymbolValueType::Char,
+            TokenType::Keyword(KeywordType::Real) => SymbolValueType::Real,
+            _ => {
+                self.error(&element, "An array can only hold ints, bools, chars or reals".to_string());
+                return None;
+            },
+        };
+
+        Some(SymbolValueType::Array(Box::new(t), size))
+    }
+
+    /// Reports the next token if it starts a declaration section which has to come before
+    /// the section that just ended, the sections are always consts, vars then procs.
+    fn check_section_order(&mut self, section: &str) -> ParserState {
+        let token = match self.tokens.first() {
+            Some(t) => t.clone(),
+            None => return ParserState::Continue,
+        };
+
+        let earlier = match token.token_type() {
+            TokenType::Keyword(KeywordType::Const) => "Constant declarations",
+            TokenType::Keyword(KeywordType::Var) if section == "procedures" => "Variable declarations",
+            _ => return ParserState::Continue,
+        };
+
+        self.error(&token, format!("{} must come before {}", earlier, section));
+        ParserState::Done(ParserResult::Unexpected)
+    }
+
     // TYPE rule
     fn token_type(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting TYPE rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting TYPE rule.");
 
-        self.check_and_then_check(TokenType::Keyword(KeywordType::Int),
-            TokenType::Keyword(KeywordType::Bool)).0
+        match self.check_and_then_check(TokenType::Keyword(KeywordType::Int),
+            TokenType::Keyword(KeywordType::Bool)).0 {
+            ParserState::Continue => ParserState::Continue,
+            _ => {
+                self.insert_last_token();
+                match self.check_and_then_check(TokenType::Keyword(KeywordType::Char),
+                    TokenType::Keyword(KeywordType::Str)).0 {
+                    ParserState::Continue => ParserState::Continue,
+                    _ => {
+                        self.insert_last_token();
+                        self.check(TokenType::Keyword(KeywordType::Real))
+                    },
+                }
+            },
+        }
     }
 
     // PROCS rule
     fn procs(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting PROCS rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting PROCS rule.");
 
         match self.token_proc() {
             ParserState::Continue => self.procs(),
             ParserState::Done(ParserResult::Incorrect) => {
                 self.insert_last_token();
-                ParserState::Continue
+                self.check_section_order("procedures")
             },
             _ => {
                 ParserState::Done(ParserResult::Unexpected)
@@ -511,7 +1877,7 @@ impl Parser {
 
     // PROC rule
     fn token_proc(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting PROC rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting PROC rule.");
 
         //let t = self.symbol_table.temp(SymbolType::Variable(SymbolValueType::Int));
 
@@ -520,43 +1886,127 @@ impl Parser {
             _ => return ParserState::Done(ParserResult::Incorrect),
         };
 
-        self.symbol_table = self.symbol_table.clone().enter_proc();
+        let name = match self.check(TokenType::Identifier) {
+            ParserState::Continue => self.last_token().unwrap(),
+            _ => return ParserState::Done(ParserResult::Unexpected),
+        };
+        let id = name.lexeme();
 
-        let id = match self.check(TokenType::Identifier) {
+        c_exp!(self.param_list());
+        let parameters: Vec<(Token, SymbolValueType)> = self.parameters.drain(..).collect();
+
+        // A return type makes the procedure a function
+        let returns = match self.check(TokenType::Colon) {
             ParserState::Continue => {
-                self.last_token().unwrap().lexeme()
+                c_exp!(self.token_type());
+                let t = self.last_token().unwrap();
+                match t.token_type() {
+                    TokenType::Keyword(KeywordType::Bool) => Some(SymbolValueType::Bool),
+                    TokenType::Keyword(KeywordType::Char) => Some(SymbolValueType::Char),
+                    TokenType::Keyword(KeywordType::Real) => Some(SymbolValueType::Real),
+                    TokenType::Keyword(KeywordType::Str) => {
+                        self.error(&t, format!("Function `{}` can not return a string", id));
+                        return ParserState::Done(ParserResult::Unexpected);
+                    },
+                    _ => Some(SymbolValueType::Int),
+                }
+            },
+            _ => {
+                self.insert_last_token();
+                None
             },
-            _ => return ParserState::Done(ParserResult::Unexpected),
         };
 
-        self.symbol_table.add(id, SymbolType::Procedure);
+        c_token!(self, TokenType::Semicolon);
 
-        c_exp!(self.param_list());
+        if let Some(t) = returns {
+            c_exp!(self.declare_function(&name, t));
+        }
 
-        c_token!(self, TokenType::Semicolon);
+        // The procedure is declared in the enclosing scope, so calls after it can check their
+        // arguments against the types of its parameters
+        let types: Vec<SymbolValueType> = parameters.iter().map(|p| p.1.clone()).collect();
+        c_exp!(self.declare_at(Some(&name), id, SymbolType::Procedure(types)));
 
-        c_exp!(self.block());
+        self.symbol_table = self.symbol_table.clone().enter_proc();
+
+        // The caller pushes the arguments in order before the frame pointer it saves, so
+        // the last is just below it
+        let n = parameters.len() as i32;
+        for (i, (t, v_type)) in parameters.into_iter().enumerate() {
+            let offset = -4 * (n - i as i32 + 1);
+            if let Some(warning) = self.shadowing(&t.lexeme()) {
+                self.warn_at(&t, warning);
+            }
+            if let Err(e) = self.symbol_table.add_parameter(t.lexeme(), v_type, offset) {
+                self.error(&t, e);
+                return ParserState::Done(ParserResult::Unexpected);
+            }
+        }
+
+        self.proc_depth += 1;
+        self.parsing_procs.push(name.lexeme());
+        let r = self.block();
+        self.parsing_procs.pop();
+        self.proc_depth -= 1;
+        c_exp!(r);
 
         let r = match self.check(TokenType::Semicolon) {
             ParserState::Continue => ParserState::Continue,
             _ => ParserState::Done(ParserResult::Unexpected),
         };
 
+        let scope = self.symbol_table.current_proc();
+        let frame = (4 + self.symbol_table.frame_size(), self.symbol_table.temp_high_water());
+        self.frames.insert(name.lexeme(), frame);
+        self.record_debug_info(scope);
+        self.record_symbols();
+
         self.symbol_table = match self.symbol_table.clone().exit(){
             Some(s) => {
                 s
             }
             None => {
-                panic!("<YASLC/Parser> A symbol table has been popped where it shouldn't have been and we're in big trouble.");
+                println!("<YASLC/Parser> A symbol table has been popped where it shouldn't have been and we're in big trouble.");
+                return ParserState::Done(ParserResult::Unexpected);
             }
         };
 
         r
     }
 
+    /// Returns the name of the global holding the result of the function. It can not be
+    /// declared in a program and does not start with $, since the expression parser writes
+    /// into symbols which do.
+    fn result_of(function: &str) -> String {
+        format!("{}.result", function)
+    }
+
+    /// Declares the global which holds the result of the function, which has to be in the
+    /// main program so the result is at the same place for every call.
+    fn declare_function(&mut self, name: &Token, returns: SymbolValueType) -> ParserState {
+        if self.proc_depth > 0 {
+            self.error(name, format!("Function `{}` must be declared in the main program", name.lexeme()));
+            return ParserState::Done(ParserResult::Unexpected);
+        }
+
+        let result = Parser::result_of(&name.lexeme());
+        c_exp!(self.declare(result.clone(), SymbolType::Variable(returns.clone())));
+        if let Some(s) = self.symbol_table.declared(&result) {
+            let c = format!("movw {} {}", self.options.syntax.immediate(0), s.location());
+            self.declarations.push(c);
+        }
+
+        // The result is only ever used through calls, which do not look it up
+        let _ = self.symbol_table.get(&result);
+
+        self.functions.insert(name.lexeme(), returns);
+        ParserState::Continue
+    }
+
     // PARAM-LIST rule
     fn param_list(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting PARAM-LIST rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting PARAM-LIST rule.");
 
         match self.check(TokenType::LeftParen) {
             ParserState::Continue => ParserState::Continue,
@@ -575,7 +2025,7 @@ impl Parser {
 
     // PARAMS rule
     fn params(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting PARAMS rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting PARAMS rule.");
 
         c_exp!(self.param());
 
@@ -591,7 +2041,7 @@ impl Parser {
 
     // FOLLOW_PARAM rule
     fn follow_param(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting FOLLOW-PARAM rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting FOLLOW-PARAM rule.");
 
         match self.check(TokenType::Comma) {
             ParserState::Continue => {},
@@ -606,19 +2056,33 @@ impl Parser {
 
     // PARAM rule
     fn param(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting PARAM rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting PARAM rule.");
 
         c_token!(self, TokenType::Identifier);
+        let name = self.last_token().unwrap();
 
         c_token!(self, TokenType::Colon);
 
-        c_exp!(self.token_type(), ParserState::Done(ParserResult::Unexpected),
-            ParserState::Continue)
+        c_exp!(self.token_type());
+        let t = self.last_token().unwrap();
+        let v_type = match t.token_type() {
+            TokenType::Keyword(KeywordType::Bool) => SymbolValueType::Bool,
+            TokenType::Keyword(KeywordType::Char) => SymbolValueType::Char,
+            TokenType::Keyword(KeywordType::Real) => SymbolValueType::Real,
+            TokenType::Keyword(KeywordType::Str) => {
+                self.error(&t, format!("Parameter `{}` can not be a string", name.lexeme()));
+                return ParserState::Done(ParserResult::Unexpected);
+            },
+            _ => SymbolValueType::Int,
+        };
+        self.parameters.push((name, v_type));
+
+        ParserState::Continue
     }
 
     // STATEMENTS rule
     fn statements(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting STATEMENTS rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting STATEMENTS rule.");
 
         match self.statement() {
             ParserState::Continue => {},
@@ -633,7 +2097,7 @@ impl Parser {
 
     // STATEMENT-TAIL rule
     fn statement_tail(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting STATEMENT-TAIL rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting STATEMENT-TAIL rule.");
 
         match self.check(TokenType::Semicolon) {
             ParserState::Continue => {},
@@ -643,6 +2107,11 @@ impl Parser {
             },
         };
 
+        // A trailing semicolon before the end is an empty statement
+        if self.tokens.first().is_some_and(|t| t.is_type(TokenType::Keyword(KeywordType::End))) {
+            return ParserState::Continue;
+        }
+
         c_exp!(self.statement());
 
         self.statement_tail()
@@ -652,13 +2121,35 @@ impl Parser {
     // Statement rule is special because there are so many types of statements that we must
     // be more explicit with definitions.
     fn statement(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting STATEMENT rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting STATEMENT rule.");
 
         let token = self.next_token();
 
+        if self.options.record_scopes {
+            self.scopes.push((token.line(), token.column(), self.symbol_table.visible()));
+        }
+
+        if self.options.source_listing {
+            self.list_source_line(token.line());
+        }
+
+        if self.options.line_comments {
+            self.commands.push_comment(format!(": line {}, column {}", token.line(), token.column()));
+        }
+
         match self.check_token(TokenType::Keyword(KeywordType::If), token.clone()) {
             ParserState::Continue => {
+                if let Some(taken) = self.constant_condition(KeywordType::Then) {
+                    if let Some(t) = self.tokens.first().cloned().filter(|_| self.options.warn_constant_conditions) {
+                        self.warn_at(&t, format!("The condition of the `if` is always {}", taken));
+                    }
+                    if self.options.optimize {
+                        return self.constant_if(taken);
+                    }
+                }
+
                 let i_temp = self.symbol_table.if_temp();
+                let syntax = self.syntax();
                 match self.expression() {
                     ParserState::Continue => {
                         // Get the value of the boolean expression and compare it to 0. If it is
@@ -666,34 +2157,45 @@ impl Parser {
                         let s = match self.last_expression {
                             Some(ref s) => s.clone(),
                             None => {
-                                panic!("Attempted to ge the last expression for an if statement but it isn't there!");
+                                println!("<YASLC/Parser> Attempted to get the last expression for an if statement but it isn't there!");
+                                return ParserState::Done(ParserResult::Unexpected);
                             }
                         };
 
-                        self.commands.push_command(format!("cmpw #0 {}", s.location()));
-                        self.commands.push_command(format!("beq $if_else{}", i_temp));
+                        self.commands.push_command(format!("cmpw {} {}", syntax.immediate(0), s.location()));
+                        self.commands.push_command(format!("beq {}", syntax.label(format!("if_else{}", i_temp))));
                     },
                     _ => return ParserState::Done(ParserResult::Unexpected),
                 };
 
-                match self.check(TokenType::Keyword(KeywordType::Then)) {
-                    ParserState::Continue => {},
-                    _ => return ParserState::Done(ParserResult::Unexpected),
-                };
+                c_exp!(self.expect_keyword(KeywordType::Then, "after the condition of the `if`"));
 
-                match self.statement() {
+                let before = self.assigned.clone();
+                match self.body() {
                     ParserState::Continue => {},
                     _ => return ParserState::Done(ParserResult::Unexpected),
                 };
+                let mut after_branches = std::mem::replace(&mut self.assigned, before.clone());
 
                 // Statements have ended, jump to end,
                 // and prepend next command with $if_else{}
-                self.commands.push_command(format!("jmp $end_if{}", i_temp));
-                self.commands.set_prefix(format!("$if_else{}", i_temp));
+                self.commands.push_command(format!("jmp {}", syntax.label(format!("end_if{}", i_temp))));
+                self.commands.set_prefix(syntax.label(format!("if_else{}", i_temp)));
+
+                // Each elsif is another condition and body in the same chain, ending at the
+                // same $end_if{}
+                while self.tokens.first().is_some_and(|t| t.is_type(TokenType::Keyword(KeywordType::Elsif))) {
+                    c_exp!(self.follow_elsif(i_temp));
+                    let after = std::mem::replace(&mut self.assigned, before.clone());
+                    after_branches = after_branches.intersection(&after).cloned().collect();
+                }
 
                 match self.follow_if() {
                     ParserState::Continue => {
-                        self.commands.set_prefix(format!("$end_if{}", i_temp));
+                        // Only what every branch assigns is assigned after the if
+                        self.assigned = self.assigned.intersection(&after_branches).cloned().collect();
+
+                        self.commands.set_prefix(syntax.label(format!("end_if{}", i_temp)));
                         return ParserState::Continue;
                     },
                     x => return x,
@@ -704,10 +2206,21 @@ impl Parser {
 
         match self.check_token(TokenType::Keyword(KeywordType::While), token.clone()) {
             ParserState::Continue => {
+                let constant = self.constant_condition(KeywordType::Do);
+                if let Some(t) = self.tokens.first().cloned().filter(|_| self.options.warn_constant_conditions) {
+                    match constant {
+                        Some(true) => self.warn_at(&t, "The condition of the `while` is always true, so it never ends".to_string()),
+                        Some(false) => self.warn_at(&t, "The condition of the `while` is always false, so its body never runs".to_string()),
+                        None => {},
+                    };
+                }
+                let mark = self.commands.mark();
+
                 // Setup the starting marker
                 let w_temp = self.symbol_table.while_temp();
+                let syntax = self.syntax();
                 self.push_command(format!("\n: while loop {}", w_temp));
-                self.push_prefix(format!("$b_while{}", w_temp));
+                self.push_prefix(syntax.label(format!("b_while{}", w_temp)));
 
                 // Evaluate the expression
                 match self.expression() {
@@ -717,26 +2230,35 @@ impl Parser {
                         let s = match self.last_expression {
                             Some(ref s) => s.clone(),
                             None => {
-                                panic!("Attempted to ge the last expression for a while statement but it isn't there!");
+                                println!("<YASLC/Parser> Attempted to get the last expression for a while statement but it isn't there!");
+                                return ParserState::Done(ParserResult::Unexpected);
                             }
                         };
 
-                        self.commands.push_command(format!("cmpw #0 {}", s.location()));
-                        self.commands.push_command(format!("beq $e_while{}", w_temp));
+                        self.commands.push_command(format!("cmpw {} {}", syntax.immediate(0), s.location()));
+                        self.commands.push_command(format!("beq {}", syntax.label(format!("e_while{}", w_temp))));
                     },
                     _ => return ParserState::Done(ParserResult::Unexpected),
                 };
 
-                match self.check(TokenType::Keyword(KeywordType::Do)) {
-                    ParserState::Continue => {},
-                    _ => return ParserState::Done(ParserResult::Unexpected),
-                };
+                c_exp!(self.expect_keyword(KeywordType::Do, "after the condition of the `while`"));
 
-                // Code for the statement generates by itself
-                match self.statement() {
+                // Code for the statement generates by itself, the body may never run so
+                // nothing it assigns counts afterwards
+                let before = self.assigned.clone();
+                self.loops += 1;
+                let r = self.body();
+                self.loops -= 1;
+                self.assigned = before;
+                match r {
                     ParserState::Continue => {
-                        self.commands.push_command(format!("jmp $b_while{}", w_temp));
-                        self.commands.set_prefix(format!("$e_while{}", w_temp));
+                        self.commands.push_command(format!("jmp {}", syntax.label(format!("b_while{}", w_temp))));
+                        self.commands.set_prefix(syntax.label(format!("e_while{}", w_temp)));
+
+                        // A loop which never runs has no code at all
+                        if self.options.optimize && constant == Some(false) {
+                            self.commands.rewind(mark);
+                        }
                         return ParserState::Continue;
                     },
                     x => return x,
@@ -745,6 +2267,13 @@ impl Parser {
             _ => {},
         };
 
+        match self.check_token(TokenType::Keyword(KeywordType::For), token.clone()) {
+            ParserState::Continue => {
+                return self.follow_for();
+            },
+            _ => {},
+        };
+
         match self.check_token(TokenType::Keyword(KeywordType::Begin), token.clone()) {
             ParserState::Continue => {
                 return self.follow_begin();
@@ -765,7 +2294,7 @@ impl Parser {
                     ParserState::Continue => {
                         // Output the string
                         let l = self.last_token().unwrap().lexeme();
-                        self.add_print_command(&*l)
+                        self.add_print_command(&*l, true)
                     },
                     _ => return ParserState::Done(ParserResult::Unexpected),
                 };
@@ -778,25 +2307,25 @@ impl Parser {
                         let (c, v) = match self.last_token() {
                             Some(t) => {
                                 // If there's a value then we successfully parsed the Identifier
-                                log!("<YASLC/Parser> Parsed PROMPT with identifier, adding to compiled file.");
-                                match self.symbol_table.get(&*t.lexeme()) {
+                                log!(self.options.verbose, "<YASLC/Parser> Parsed PROMPT with identifier, adding to compiled file.");
+                                match self.symbol_table.get_cloned(&*t.lexeme()) {
                                     Some(s) => {
-                                        ("inw", s.location())
+                                        return self.read_into(&t, &s);
                                     },
                                     None => {
-                                        ("inb", format!("$junk"))
+                                        ("inb", self.options.syntax.label("junk"))
                                     }
                                 }
                             },
                             None => {
                                 // If there's no value, we have no identifier
-                                log!("<YASLC/Parser> Parsed PROMPT without identifier, using $junk and adding to compiled file.");
-                                ("inb", format!("$junk"))
+                                log!(self.options.verbose, "<YASLC/Parser> Parsed PROMPT without identifier, using $junk and adding to compiled file.");
+                                ("inb", self.options.syntax.label("junk"))
                             }
                         };
 
                         // Prompt for the variable
-                        log!("<YASLC/Parser> Adding prompt command for variable {}", v);
+                        log!(self.options.verbose, "<YASLC/Parser> Adding prompt command for variable {}", v);
 
                         self.push_command(format!("{} {}", c, v));
 
@@ -810,20 +2339,193 @@ impl Parser {
 
         match self.check_token(TokenType::Keyword(KeywordType::Print), token.clone()) {
             ParserState::Continue => {
-                return self.follow_print();
+                return self.follow_print(true);
+            },
+            _ => {},
+        };
+
+        match self.check_token(TokenType::Keyword(KeywordType::Write), token.clone()) {
+            ParserState::Continue => {
+                return self.follow_print(false);
+            },
+            _ => {},
+        };
+
+        match self.check_token(TokenType::Keyword(KeywordType::Swap), token.clone()) {
+            ParserState::Continue => {
+                return self.follow_swap();
+            },
+            _ => {},
+        };
+
+        match self.check_token(TokenType::Keyword(KeywordType::Return), token.clone()) {
+            ParserState::Continue => {
+                return self.follow_return(&token);
+            },
+            _ => {},
+        };
+
+        match self.check_token(TokenType::Keyword(KeywordType::Asm), token.clone()) {
+            ParserState::Continue => {
+                return self.follow_asm();
             },
             _ => {},
         };
 
+        self.stray_keyword(&token);
+
         ParserState::Done(ParserResult::Unexpected)
     }
 
+    // BODY rule, the statement after then, do or else
+    fn body(&mut self) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting BODY rule.");
+
+        // In strict mode the body must be a block so an else always belongs to a begin/end
+        if self.options.strict {
+            let token = self.next_token();
+            match self.check_token(TokenType::Keyword(KeywordType::Begin), token.clone()) {
+                ParserState::Continue => self.insert_last_token(),
+                x => {
+                    self.error(&token, format!("Expected `begin` for the body of a control structure in strict mode but found `{}`", token.lexeme()));
+                    return x;
+                },
+            };
+        }
+
+        self.nesting += 1;
+        let state = self.statement();
+        self.nesting -= 1;
+        state
+    }
+
+    /// Returns the value of the condition before the keyword which ends it if it only uses
+    /// literals and constants, so which way it goes is known at compile time.
+    fn constant_condition(&self, end: KeywordType) -> Option<bool> {
+        let mut tokens = Vec::<Token>::new();
+        let mut depth = 0;
+        for t in self.tokens.iter() {
+            if t.is_type(TokenType::Keyword(end.clone())) {
+                break;
+            }
+            match t.token_type() {
+                TokenType::LeftParen => depth += 1,
+                TokenType::RightParen if depth > 0 => depth -= 1,
+                _ if Parser::ends_expression(t, tokens.last()) => return None,
+                _ => {},
+            };
+
+            // Constants are replaced by their values
+            let value = match self.symbol_table.get(&t.lexeme()) {
+                Some(s) if t.is_type(TokenType::Identifier) => {
+                    match (self.constant_values.get(&t.lexeme()), &s.symbol_type) {
+                        (Some((location, v, _)), SymbolType::Constant(v_type)) if *location == s.location() => {
+                            match *v_type {
+                                SymbolValueType::Bool if *v != 0 => Token::new_with(t.line(), t.column(), "true".to_string(), TokenType::Keyword(KeywordType::True)),
+                                SymbolValueType::Bool => Token::new_with(t.line(), t.column(), "false".to_string(), TokenType::Keyword(KeywordType::False)),
+                                // Conditions on reals are always left to the program
+                                SymbolValueType::Real => return None,
+                                _ => Token::new_with(t.line(), t.column(), v.to_string(), TokenType::Number),
+                            }
+                        },
+                        _ => return None,
+                    }
+                },
+                _ => t.clone(),
+            };
+            tokens.push(value);
+        }
+
+        match ExpressionParser::new(self.symbol_table.clone(), tokens)?.constant_value() {
+            Some(ref v) if v == "true" => Some(true),
+            Some(ref v) if v == "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Parses the rest of an if statement whose condition is the constant taken, only keeping
+    /// the code of the branch which is taken.
+    fn constant_if(&mut self, taken: bool) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Compiling if statement with constant condition {}.", taken);
+
+        // Skip the condition
+        while self.tokens.first().is_some_and(|t| !t.is_type(TokenType::Keyword(KeywordType::Then))) {
+            self.next_token();
+        }
+        c_token!(self, TokenType::Keyword(KeywordType::Then));
+
+        let mark = self.commands.mark();
+        c_exp!(self.body());
+        if !taken {
+            self.commands.rewind(mark);
+        }
+
+        // The rest of an elsif chain is an if of its own, which is only kept if this
+        // condition is false
+        if let Some(t) = self.tokens.first().cloned().filter(|t| t.is_type(TokenType::Keyword(KeywordType::Elsif))) {
+            let mut t_if = Token::new_with(t.line(), t.column(), "if".to_string(), TokenType::Keyword(KeywordType::If));
+            if let Some(file) = t.file() {
+                t_if.set_file(file);
+            }
+            self.tokens[0] = t_if;
+            let mark = self.commands.mark();
+            c_exp!(self.statement());
+            if taken {
+                self.commands.rewind(mark);
+            }
+            return ParserState::Continue;
+        }
+
+        match self.check(TokenType::Keyword(KeywordType::Else)) {
+            ParserState::Continue => {
+                let mark = self.commands.mark();
+                c_exp!(self.body());
+                if taken {
+                    self.commands.rewind(mark);
+                }
+            },
+            _ => self.insert_last_token(),
+        };
+
+        ParserState::Continue
+    }
+
+    // FOLLOW-ELSIF rule
+    //
+    // Compiles an elsif of the if chain whose labels are numbered end, jumping past the rest
+    // of the chain after its body.
+    fn follow_elsif(&mut self, end: u32) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting FOLLOW-ELSIF rule.");
+
+        c_token!(self, TokenType::Keyword(KeywordType::Elsif));
+
+        let e_temp = self.symbol_table.if_temp();
+        c_exp!(self.expression());
+        let s = match self.last_expression {
+            Some(ref s) => s.clone(),
+            None => {
+                println!("<YASLC/Parser> Attempted to get the last expression for an elsif but it isn't there!");
+                return ParserState::Done(ParserResult::Unexpected);
+            }
+        };
+        let syntax = self.syntax();
+        self.commands.push_command(format!("cmpw {} {}", syntax.immediate(0), s.location()));
+        self.commands.push_command(format!("beq {}", syntax.label(format!("if_else{}", e_temp))));
+
+        c_exp!(self.expect_keyword(KeywordType::Then, "after the condition of the `elsif`"));
+        c_exp!(self.body());
+
+        self.commands.push_command(format!("jmp {}", syntax.label(format!("end_if{}", end))));
+        self.commands.set_prefix(syntax.label(format!("if_else{}", e_temp)));
+        ParserState::Continue
+    }
+
     // FOLLOW-IF rule
     fn follow_if(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting FOLLOW-IF rule.");
+        log!(self.options.verbose, "<YASLC/Parser> Starting FOLLOW-IF rule.");
 
         match self.check(TokenType::Keyword(KeywordType::Else)) {
-            ParserState::Continue => self.statement(),
+            ParserState::Continue => self.body(),
             _ => {
                 self.insert_last_token();
                 ParserState::Continue
@@ -831,235 +2533,1176 @@ impl Parser {
         }
     }
 
-    // FOLLOW-BEGIN rule
-    fn follow_begin(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting FOLLOW-BEGIN rule.");
+    /// Checks for a keyword which a control structure requires and reports it if it is
+    /// missing. In recovery mode parsing carries on as if it was there.
+    fn expect_keyword(&mut self, keyword: KeywordType, context: &str) -> ParserState {
+        match self.check(TokenType::Keyword(keyword.clone())) {
+            ParserState::Continue => ParserState::Continue,
+            x => {
+                if let Some(t) = self.last_token() {
+                    self.error(&t, format!("Expected `{}` {}", format!("{}", keyword).to_lowercase(), context));
+                }
+                self.recover(x)
+            },
+        }
+    }
+
+    /// Returns Continue after putting back the token in place of a missing one if the
+    /// options ask for recovery, the state otherwise.
+    fn recover(&mut self, state: ParserState) -> ParserState {
+        if self.options.recover {
+            self.insert_last_token();
+            ParserState::Continue
+        } else {
+            state
+        }
+    }
+
+    // FOLLOW-FOR rule
+    //
+    // Counts the int variable up from the start to the bound, running the body once for
+    // each value. The bound is evaluated again before every iteration.
+    fn follow_for(&mut self) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting FOLLOW-FOR rule.");
+
+        c_token!(self, TokenType::Identifier);
+        let name = self.last_token().unwrap();
+        let id = name.lexeme();
+
+        // Both `for i = 1` and `for i := 1` are accepted
+        if let ParserState::Done(_) = self.check(TokenType::Colon) {
+            self.insert_last_token();
+        }
+        c_token!(self, TokenType::Assign);
+
+        let counter = match self.symbol_table.get_cloned(&id) {
+            Some(s) => s,
+            None => {
+                self.error(&name, format!("`{}` has not been declared", id));
+                self.record_undeclared(&id);
+                return ParserState::Done(ParserResult::Unexpected);
+            },
+        };
+        if counter.symbol_type != SymbolType::Variable(SymbolValueType::Int) {
+            self.error(&name, format!("The variable of the `for` must be an int var but `{}` is {}", id, counter.symbol_type));
+            return ParserState::Done(ParserResult::Unexpected);
+        }
+
+        let f_temp = self.symbol_table.for_temp();
+        self.push_command(format!("\n: for loop {}", f_temp));
+
+        c_exp!(self.for_bound(&name, "start"));
+        let start = self.last_expression.take().unwrap();
+        self.push_command(format!("movw {} {}", start.location(), counter.location()));
+        self.assigned.insert(id);
+
+        c_exp!(self.expect_keyword(KeywordType::To, "after the start of the `for`"));
+
+        let syntax = self.syntax();
+        self.push_prefix(syntax.label(format!("b_for{}", f_temp)));
+        c_exp!(self.for_bound(&name, "bound"));
+        let bound = self.last_expression.take().unwrap();
+        self.commands.push_command(format!("cmpw {} {}", counter.location(), bound.location()));
+        self.commands.push_command(format!("bgtr {}", syntax.label(format!("e_for{}", f_temp))));
+
+        c_exp!(self.expect_keyword(KeywordType::Do, "after the bound of the `for`"));
+
+        // The body may never run so nothing it assigns counts afterwards
+        let before = self.assigned.clone();
+        self.loops += 1;
+        let r = self.body();
+        self.loops -= 1;
+        self.assigned = before;
+        c_exp!(r);
+
+        self.commands.push_command(format!("addw {} {}", syntax.immediate(1), counter.location()));
+        self.commands.push_command(format!("jmp {}", syntax.label(format!("b_for{}", f_temp))));
+        self.commands.set_prefix(syntax.label(format!("e_for{}", f_temp)));
+
+        ParserState::Continue
+    }
+
+    /// Evaluates the start or the bound of the `for` loop of the variable, which has to be an
+    /// int, leaving its value as the last expression.
+    fn for_bound(&mut self, name: &Token, which: &str) -> ParserState {
+        c_exp!(self.expression());
+
+        let v_type = match self.last_expression {
+            Some(ref e) => e.symbol_type().clone(),
+            None => {
+                println!("<YASLC/Parser> Attempted to get the last expression for a for statement but it isn't there!");
+                return ParserState::Done(ParserResult::Unexpected);
+            },
+        };
+
+        match v_type {
+            SymbolType::Variable(SymbolValueType::Int) | SymbolType::Constant(SymbolValueType::Int) => ParserState::Continue,
+            t => {
+                self.error(name, format!("The {} of the `for` over `{}` must be an int but it is {}", which, name.lexeme(), t));
+                ParserState::Done(ParserResult::Unexpected)
+            },
+        }
+    }
+
+    // FOLLOW-BEGIN rule
+    fn follow_begin(&mut self) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting FOLLOW-BEGIN rule.");
+        let begin = self.last_token();
+
+        self.open_blocks += 1;
+        let state = self.statement();
+        self.open_blocks -= 1;
+        match state {
+            ParserState::Continue => {},
+            _ => {
+                self.insert_last_token();
+                return self.check(TokenType::Keyword(KeywordType::End));
+            }
+        };
+
+        self.open_blocks += 1;
+        let state = self.statement_tail();
+        self.open_blocks -= 1;
+        c_exp!(state);
+
+        self.check_end(begin)
+    }
+
+    /// Checks for the end of a block after its statements, diagnosing a control structure
+    /// keyword found instead, for example an else after an if which ended with a semicolon,
+    /// or reporting the begin which is not closed.
+    fn check_end(&mut self, begin: Option<Token>) -> ParserState {
+        match self.check(TokenType::Keyword(KeywordType::End)) {
+            ParserState::Continue => ParserState::Continue,
+            x => {
+                if let Some(t) = self.last_token() {
+                    if !self.stray_keyword(&t) {
+                        let message = match begin {
+                            Some(b) => format!("Expected `end` to close the `begin` at ({}, {})", b.line(), b.column()),
+                            None => "Expected `end`".to_string(),
+                        };
+                        self.error(&t, message);
+                    }
+                }
+                self.recover(x)
+            },
+        }
+    }
+
+    // FOLLOW-ID rule
+    fn follow_id(&mut self) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting FOLLOW-ID rule.");
+
+        // Get the identifier
+        let name = self.last_token().unwrap();
+        let id = name.lexeme();
+
+        // An element of an array is assigned through its index
+        if self.tokens.first().is_some_and(|t| t.is_type(TokenType::LeftBracket)) {
+            return self.assign_element(&name);
+        }
+
+        self.expand_compound_assignment(std::slice::from_ref(&name));
+
+        // Are we assigning?
+        match self.check(TokenType::Assign) {
+            ParserState::Continue => {
+                // Strings are copied a character at a time instead of evaluated
+                if let Some(s) = self.symbol_table.get_cloned(&id) {
+                    if s.symbol_type == SymbolType::Variable(SymbolValueType::Str) {
+                        return self.assign_string(&s);
+                    }
+                    if let SymbolType::Variable(SymbolValueType::Array(..)) = s.symbol_type {
+                        self.error(&name, format!("`{}` is an array, only its elements can be assigned", id));
+                        return ParserState::Done(ParserResult::Unexpected);
+                    }
+                }
+
+                let constant = self.constant_assignment(&id);
+
+                match self.expression() {
+                    ParserState::Continue => {
+                        let f = match self.last_expression {
+                            Some(ref e) => {
+                                e.clone()
+                            },
+                            None => {
+                                println!("<YASLC/Parser> Warning: attempted to use expression to set variable but the expression parser is missing!");
+                                return ParserState::Done(ParserResult::Unexpected);
+                            }
+                        };
+
+                        self.last_expression = None;
+
+                        // Move the value of the expression to the identifier
+                        let id_symbol = match self.symbol_table.get_cloned(&id) {
+                            Some(s) => s,
+                            None => {
+                                println!("<YASLC/Parser> Attempted to assign to variable '{}' that has not been declared!", id);
+                                self.record_undeclared(&id);
+                                return ParserState::Done(ParserResult::Unexpected);
+                            }
+                        };
+
+                        // Check that we're assigning to a variable
+                        match id_symbol.symbol_type {
+                            SymbolType::Variable(_) => {},
+                            SymbolType::Constant(_) => {
+                                println!("<YASLC/Parser> Attempted to assign a value to a constant!");
+                                return ParserState::Done(ParserResult::Unexpected);
+                            },
+                            SymbolType::Procedure(_) => {
+                                println!("<YASLC/Parser> Attempted to assign a value to a procedure!");
+                                return ParserState::Done(ParserResult::Unexpected);
+                            },
+                        }
+
+                        // Check that we're assigning to the same type
+                        match &id_symbol.symbol_type {
+                            &SymbolType::Variable(ref v1) | &SymbolType::Constant(ref v1) => {
+                                match &f.symbol_type {
+                                    &SymbolType::Variable(ref v2) | &SymbolType::Constant(ref v2) => {
+                                        if v1 != v2 {
+                                            println!("<YASLC/Parser> Attempted to assign a value to a variable who's type is not the same!");
+                                            println!("<YASLC/Parser> Variable is {} and value is {}.", id_symbol, f);
+                                            return ParserState::Done(ParserResult::Unexpected)
+                                        }
+                                    },
+                                    _ => {}
+                                }
+                            },
+                            _ => {}
+                        };
+
+                        // Add the command
+                        self.push_command(format!("movw {} {}", f.location(), id_symbol.location()));
+
+                        self.assigned.insert(id.clone());
+
+                        // Later expressions can use the value instead of the variable
+                        if let Some(v) = constant {
+                            self.known_constants.insert(id, v);
+                        }
+
+                        return ParserState::Continue;
+                    },
+                    _ => return ParserState::Done(ParserResult::Unexpected),
+                };
+            },
+            _ => {},
+        };
+
+        // All execution around assigning does not reach this point.
+        self.insert_last_token();
+
+        // We're dealing with a proc that may have arguments
+        match self.check(TokenType::LeftParen) {
+            ParserState::Continue => {
+                let mut arguments = Vec::<Symbol>::new();
+                if !self.tokens.first().is_some_and(|t| t.is_type(TokenType::RightParen)) {
+                    c_exp!(self.push_arguments(&mut arguments));
+                }
+                c_token!(self, TokenType::RightParen);
+
+                c_exp!(self.check_arguments(&name, &arguments));
+                self.push_call(&id, arguments.len() as u32);
+                return ParserState::Continue;
+            }
+
+            _ => {
+                // It does not, but it should have a semi
+                self.insert_last_token();
+                match self.check(TokenType::Semicolon) {
+                    ParserState::Continue => {
+                        // Call the procedure
+                        c_exp!(self.check_arguments(&name, &[]));
+                        self.push_call(&id, 0);
+                    },
+                    _ => {
+                        // Check if it is an end token
+                        self.insert_last_token();
+                        match self.check(TokenType::Keyword(KeywordType::End)) {
+                            ParserState::Continue => {
+                                self.insert_last_token();
+
+                                // Call the proc
+                                c_exp!(self.check_arguments(&name, &[]));
+                                self.push_call(&id, 0);
+                            },
+                            x => return x,
+                        };
+                    }
+                };
+            },
+        };
+
+        self.insert_last_token();
+
+        ParserState::Continue
+    }
+
+    /// Evaluates each argument of a call and pushes its value onto the stack, where the
+    /// procedure finds it below its frame pointer, and adds the values to arguments.
+    fn push_arguments(&mut self, arguments: &mut Vec<Symbol>) -> ParserState {
+        c_exp!(self.expression());
+        let a = match self.last_expression.take() {
+            Some(e) => e,
+            None => {
+                println!("<YASLC/Parser> Expected to find an expression parser but it went missing!");
+                return ParserState::Done(ParserResult::Unexpected);
+            },
+        };
+
+        let syntax = self.syntax();
+        self.push_command(format!("movw {} {}", a.location(), syntax.offset(0, &syntax.stack_pointer)));
+        self.push_command(format!("addw {} {}", syntax.immediate(4), syntax.stack_pointer));
+        arguments.push(a);
+
+        match self.check(TokenType::Comma) {
+            ParserState::Continue => self.push_arguments(arguments),
+            _ => {
+                self.insert_last_token();
+                ParserState::Continue
+            },
+        }
+    }
+
+    /// Assigns the string literal, string constant or string variable which follows to the
+    /// string variable, copying its length and then its characters.
+    fn assign_string(&mut self, target: &Symbol) -> ParserState {
+        let t = self.next_token();
+
+        let literal = match t.token_type() {
+            TokenType::String => Some(t.lexeme()),
+            TokenType::Identifier => self.symbol_table.string_constant(&t.lexeme()),
+            _ => None,
+        };
+
+        if let Some(l) = literal {
+            // Skip the quotes at the start and end of the literal
+            let characters: Vec<char> = l.chars().skip(1).take(l.chars().count().saturating_sub(2)).collect();
+            if characters.len() as u32 > STRING_CAPACITY {
+                self.error(&t, format!("The string is {} characters long but a string variable holds at most {}", characters.len(), STRING_CAPACITY));
+                return ParserState::Done(ParserResult::Unexpected);
+            }
+
+            let syntax = self.syntax();
+            self.push_command(format!("movw {} {}", syntax.immediate(characters.len()), target.location()));
+            for (i, c) in characters.iter().enumerate() {
+                self.push_command(format!("movw {} {}", syntax.immediate(*c as u8), target.location_past(4 + 4 * i as u32)));
+            }
+        } else {
+            let source = match self.symbol_table.get_cloned(&t.lexeme()) {
+                Some(s) if t.is_type(TokenType::Identifier) => s,
+                _ => {
+                    self.error(&t, format!("`{}` is string but the value is not", target.identifier()));
+                    return ParserState::Done(ParserResult::Unexpected);
+                },
+            };
+            if source.symbol_type != SymbolType::Variable(SymbolValueType::Str) {
+                self.error(&t, format!("`{}` is string but `{}` is {}", target.identifier(), t.lexeme(), source.symbol_type));
+                return ParserState::Done(ParserResult::Unexpected);
+            }
+
+            for i in 0..STRING_CAPACITY + 1 {
+                self.push_command(format!("movw {} {}", source.location_past(4 * i), target.location_past(4 * i)));
+            }
+        }
+
+        self.assigned.insert(target.identifier().clone());
+        ParserState::Continue
+    }
+
+    /// Checks the arguments of a call of the procedure named by the token against the types
+    /// of its parameters and reports an error if there are not as many or one has another
+    /// type. A procedure which has not been declared is not checked.
+    fn check_arguments(&mut self, name: &Token, arguments: &[Symbol]) -> ParserState {
+        let id = name.lexeme();
+        let parameters = match self.symbol_table.parameters(&id) {
+            Some(p) => p.clone(),
+            None => return ParserState::Continue,
+        };
+
+        if parameters.len() != arguments.len() {
+            self.error(name, format!("`{}` takes {} argument(s) but is given {}", id, parameters.len(), arguments.len()));
+            return ParserState::Done(ParserResult::Unexpected);
+        }
+
+        for (n, (p, a)) in parameters.iter().zip(arguments.iter()).enumerate() {
+            match *a.symbol_type() {
+                SymbolType::Variable(ref v) | SymbolType::Constant(ref v) if v != p => {
+                    self.error(name, format!("Argument {} of `{}` is {} but the parameter is {}", n + 1, id, v, p));
+                    return ParserState::Done(ParserResult::Unexpected);
+                },
+                _ => {},
+            }
+        }
+
+        ParserState::Continue
+    }
+
+    /// Pushes the call of the procedure with the number of arguments which were pushed
+    /// before it, then moves the stack pointer back over them. When the procedure calls
+    /// itself as the last thing it does, the arguments are moved into its own parameters,
+    /// its frame is torn down and it jumps back to its start instead, so the recursion does
+    /// not grow the stack.
+    fn push_call(&mut self, id: &str, arguments: u32) {
+        let tail = self.loops == 0 && self.ends_procedure()
+            && self.parsing_procs.last().is_some_and(|p| p == id);
+
+        let syntax = self.syntax();
+        if tail {
+            // Every argument is evaluated before the first is moved, so an argument such as
+            // n - 1 still reads the parameter it replaces
+            let n = arguments as i32;
+            for i in 0..n {
+                self.push_command(format!("movw {} {}",
+                    syntax.offset(-4 * (n - i), &syntax.stack_pointer),
+                    syntax.offset(-4 * (n - i + 1), &syntax.frame_pointer)));
+            }
+            if arguments > 0 {
+                self.push_command(format!("subw {} {}", syntax.immediate(4 * arguments), syntax.stack_pointer));
+            }
+
+            let frame_size = self.symbol_table.frame_size();
+            self.push_frame_teardown(frame_size);
+            self.push_command(format!("jmp {}", syntax.label(id)));
+        } else {
+            self.record_call(id, 4 * arguments);
+            self.push_command(format!("call {} {}", syntax.immediate(4 * arguments), syntax.label(id)));
+            if arguments > 0 {
+                self.push_command(format!("subw {} {}", syntax.immediate(4 * arguments), syntax.stack_pointer));
+            }
+        }
+    }
+
+    /// Returns true if the tokens left only close the blocks which are open and then the
+    /// procedure, so nothing runs between the current statement and the return. An else or
+    /// another statement after it means something still could.
+    fn ends_procedure(&self) -> bool {
+        if self.parsing_procs.is_empty() {
+            return false;
+        }
+
+        let mut ends = 0;
+        for t in self.tokens.iter() {
+            if t.is_type(TokenType::Keyword(KeywordType::End)) {
+                ends += 1;
+                if ends == self.open_blocks + 1 {
+                    return true;
+                }
+            } else if !t.is_type(TokenType::Semicolon) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Pushes the commands which restore the stack and frame pointers of the caller of a
+    /// procedure with a frame of the size.
+    fn push_frame_teardown(&mut self, frame_size: u32) {
+        let syntax = self.syntax();
+        if frame_size > 0 {
+            self.push_command(format!("subw {} {}", syntax.immediate(frame_size), syntax.stack_pointer));
+        }
+        self.push_command(format!("subw {} {}", syntax.immediate(4), syntax.stack_pointer));
+        self.push_command(format!("movw {} {}", syntax.offset(0, &syntax.stack_pointer), syntax.frame_pointer));
+    }
+
+    // FOLLOW-ASM rule
+    fn follow_asm(&mut self) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting FOLLOW-ASM rule.");
+
+        c_token!(self, TokenType::String);
+
+        let t = self.last_token().unwrap();
+        let l = t.lexeme();
+        let instruction = l.trim_matches('"').trim().to_string();
+        if instruction.is_empty() {
+            self.error(&t, "`asm` needs an instruction to insert".to_string());
+            return ParserState::Done(ParserResult::Unexpected);
+        }
+
+        // The instruction goes into the program exactly as it was written
+        self.push_command(format!("\n: inline asm\n{}", instruction));
+
+        ParserState::Continue
+    }
+
+    // FOLLOW-PROMPT rule
+    fn follow_prompt(&mut self) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting FOLLOW-PROMPT rule.");
+
+        match self.check(TokenType::Comma) {
+            ParserState::Continue => {},
+            _ => {
+                // It was not, we ignore the input
+                self.insert_last_token();
+                return ParserState::Continue;
+            },
+        };
+
+        self.check(TokenType::Identifier)
+    }
+
+    /// Reads the input into the variable a prompt names, a number for an int or a bool, where
+    /// any number other than 0 is true, and a single character for a char.
+    fn read_into(&mut self, name: &Token, s: &Symbol) -> ParserState {
+        let v_type = match s.symbol_type {
+            SymbolType::Variable(ref v) => v.clone(),
+            ref t => {
+                self.error(name, format!("`{}` is {}, a prompt can only read into a variable", name.lexeme(), t.description()));
+                return ParserState::Done(ParserResult::Unexpected);
+            },
+        };
+
+        log!(self.options.verbose, "<YASLC/Parser> Adding prompt command for variable {}", s);
+
+        match v_type {
+            SymbolValueType::Int => self.push_command(format!("inw {}", s.location())),
+            SymbolValueType::Char => self.push_command(format!("inb {}", s.location())),
+            SymbolValueType::Bool => {
+                let n = self.symbol_table.if_temp();
+                let syntax = self.syntax();
+                self.push_command(format!("inw {}", s.location()));
+                self.push_command(format!("cmpw {} {}", syntax.immediate(0), s.location()));
+                self.push_command(format!("beq {}", syntax.label(format!("prompt_bool{}", n))));
+                self.push_command(format!("movw {} {}", syntax.immediate(1), s.location()));
+                self.push_prefix(syntax.label(format!("prompt_bool{}", n)));
+            },
+            t => {
+                self.error(name, format!("`{}` is a variable of type {}, a prompt can only read an int, bool or char", name.lexeme(), t));
+                return ParserState::Done(ParserResult::Unexpected);
+            },
+        }
+
+        self.assigned.insert(name.lexeme());
+
+        ParserState::Continue
+    }
+
+    // FOLLOW-PRINT
+    //
+    // Shared by print and write, which only differ in whether a newline is output after
+    // the values. Several values separated by commas are output one after the other.
+    fn follow_print(&mut self, newline: bool) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting FOLLOW-PRINT rule.");
+
+        loop {
+            c_exp!(self.print_argument());
+
+            match self.check(TokenType::Comma) {
+                ParserState::Continue => {},
+                _ => {
+                    self.insert_last_token();
+                    break;
+                },
+            };
+        }
+
+        if newline {
+            self.push_command(format!("outb {}", self.options.syntax.immediate(10)));
+        }
+
+        ParserState::Continue
+    }
+
+    /// Outputs a single string or the value of an expression of a print or write statement.
+    fn print_argument(&mut self) -> ParserState {
+        match self.check(TokenType::String) {
+            ParserState::Continue => {
+                // It is a String
+
+                let m = self.last_token().unwrap().lexeme();
+                self.add_print_command(&*m, false);
+
+                log!(self.options.verbose, "<YASLC/Parser> Successfully parsed print statement, compiling to file.");
+
+                return ParserState::Continue
+            },
+            _ => self.insert_last_token(),
+        }
+
+        // A string constant prints like the string it names
+        let named = match self.tokens.first() {
+            Some(t) if t.is_type(TokenType::Identifier) => self.symbol_table.string_constant(&t.lexeme()),
+            _ => None,
+        };
+        if let Some(m) = named {
+            self.next_token();
+            self.add_print_command(&m, false);
+            return ParserState::Continue;
+        }
+
+        log!(self.options.verbose, "<YASLC/Parser> Adding print statement waiting for expression.");
+        match self.expression() {
+            ParserState::Continue => {
+                let f = if let Some(ref e) = self.last_expression {
+                    e.clone()
+                } else {
+                    println!("<YASLc/Parser> Expected to find an expression parser but it went missing!");
+                    return ParserState::Done(ParserResult::Unexpected);
+                };
+
+                let is_bool = matches!(*f.symbol_type(), SymbolType::Variable(SymbolValueType::Bool)
+                                       | SymbolType::Constant(SymbolValueType::Bool));
+                let is_char = matches!(*f.symbol_type(), SymbolType::Variable(SymbolValueType::Char)
+                                       | SymbolType::Constant(SymbolValueType::Char));
+                let is_real = matches!(*f.symbol_type(), SymbolType::Variable(SymbolValueType::Real)
+                                       | SymbolType::Constant(SymbolValueType::Real));
+                if *f.symbol_type() == SymbolType::Variable(SymbolValueType::Str) {
+                    self.add_string_print_command(&f);
+                } else if self.options.print_bools && is_bool {
+                    self.add_bool_print_command(&f.location());
+                } else if is_char {
+                    // A char is printed as the character it is the code of
+                    self.push_command(format!("outb {}", f.location()));
+                } else if is_real {
+                    self.push_command(format!("outf {}", f.location()));
+                } else {
+                    self.push_command(format!("outw {}", f.location()));
+                }
+
+                self.last_expression = None;
+
+                return ParserState::Continue;
+            },
+            _ => return ParserState::Done(ParserResult::Unexpected),
+        };
+    }
+
+    // FOLLOW-SWAP
+    //
+    // Exchanges the values of two variables of the same type through a temp.
+    fn follow_swap(&mut self) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting FOLLOW-SWAP rule.");
+
+        c_token!(self, TokenType::Identifier);
+        let first = self.last_token().unwrap();
+        c_token!(self, TokenType::Comma);
+        c_token!(self, TokenType::Identifier);
+        let second = self.last_token().unwrap();
+
+        let mut variables = Vec::<(Symbol, SymbolValueType)>::new();
+        for t in [&first, &second].iter() {
+            match self.symbol_table.get_cloned(&t.lexeme()) {
+                Some(s) => match s.symbol_type.clone() {
+                    SymbolType::Variable(SymbolValueType::Array(..)) => {
+                        self.error(t, format!("`{}` is an array, only its elements can be swapped", t.lexeme()));
+                        return ParserState::Done(ParserResult::Unexpected);
+                    },
+                    SymbolType::Variable(v) => variables.push((s, v)),
+                    _ => {
+                        self.error(t, format!("`{}` is not a variable", t.lexeme()));
+                        return ParserState::Done(ParserResult::Unexpected);
+                    },
+                },
+                None => {
+                    self.record_undeclared(&t.lexeme());
+                    self.error(t, format!("`{}` has not been declared", t.lexeme()));
+                    return ParserState::Done(ParserResult::Unexpected);
+                },
+            }
+        }
+
+        let (b, b_type) = variables.pop().unwrap();
+        let (a, a_type) = variables.pop().unwrap();
+        if a_type != b_type {
+            self.error(&second, format!("`{}` is {} but `{}` is {}", first.lexeme(), a_type, second.lexeme(), b_type));
+            return ParserState::Done(ParserResult::Unexpected);
+        }
+
+        if self.options.warn_unassigned {
+            self.check_assigned(&[first.clone(), second.clone()]);
+        }
+
+        // The temp is on the expression stack, which starts at the stack pointer
+        let t = self.symbol_table.temp_with_name("swap", SymbolType::Variable(a_type));
+        self.symbol_table.reset_offset();
+
+        self.push_command(format!("\n: swap {}, {}", first.lexeme(), second.lexeme()));
+        self.push_command(format!("movw {} {}", self.options.syntax.stack_pointer, self.options.syntax.register(1)));
+        self.push_command(format!("movw {} {}", a.location(), t.location()));
+        self.push_command(format!("movw {} {}", b.location(), a.location()));
+        self.push_command(format!("movw {} {}", t.location(), b.location()));
+
+        ParserState::Continue
+    }
+
+    // FOLLOW-RETURN
+    //
+    // Stores the value of the expression as the result of the function and jumps to the
+    // end of the function.
+    fn follow_return(&mut self, token: &Token) -> ParserState {
+        log!(self.options.verbose, "<YASLC/Parser> Starting FOLLOW-RETURN rule.");
+
+        let function = self.symbol_table.current_proc();
+        let returns = match self.functions.get(&function) {
+            Some(t) if self.proc_depth > 0 => t.clone(),
+            _ => {
+                self.error(token, "`return` outside of a function".to_string());
+                return ParserState::Done(ParserResult::Unexpected);
+            },
+        };
+
+        c_exp!(self.expression());
+        let f = match self.last_expression.take() {
+            Some(e) => e,
+            None => {
+                println!("<YASLc/Parser> Expected to find an expression parser but it went missing!");
+                return ParserState::Done(ParserResult::Unexpected);
+            },
+        };
+
+        match *f.symbol_type() {
+            SymbolType::Variable(ref v) | SymbolType::Constant(ref v) if *v != returns => {
+                self.error(token, format!("`{}` returns {} but the value is {}", function, returns, v));
+                return ParserState::Done(ParserResult::Unexpected);
+            },
+            _ => {},
+        }
+
+        let result = match self.symbol_table.get_cloned(&Parser::result_of(&function)) {
+            Some(s) => s,
+            None => {
+                println!("<YASLC/Parser> Internal error, the result of function {} is missing.", function);
+                return ParserState::Done(ParserResult::Unexpected);
+            },
+        };
+
+        self.push_command(format!("movw {} {}", f.location(), result.location()));
+        self.push_command(format!("jmp {}", self.options.syntax.label(format!("return_{}", function))));
+
+        ParserState::Continue
+    }
+
+    /// Replaces each function named in the tokens of an expression with an operand standing
+    /// for the call, which the expression parser calls the function for where it is in the
+    /// expression, and returns Some((tokens, calls)) with the calls by the names of their
+    /// operands, or None after reporting why a call does not match its function.
+    fn call_functions(&mut self, tokens: Vec<Token>) -> Option<(Vec<Token>, HashMap<String, Call>)> {
+        let mut replaced = Vec::<Token>::new();
+        let mut calls = HashMap::<String, Call>::new();
+
+        let mut i = 0;
+        while i < tokens.len() {
+            let t = tokens[i].clone();
+            i += 1;
+
+            // A variable or constant with the same name hides the function
+            let function = t.is_type(TokenType::Identifier) && self.functions.contains_key(&t.lexeme())
+                && match self.symbol_table.get(&t.lexeme()) {
+                    Some(s) => matches!(s.symbol_type, SymbolType::Procedure(_)),
+                    None => true,
+                };
+
+            if !function {
+                replaced.push(t);
+                continue;
+            }
+
+            let result = match self.symbol_table.get_cloned(&Parser::result_of(&t.lexeme())) {
+                Some(s) => s,
+                None => {
+                    println!("<YASLC/Parser> Internal error, the result of function {} is missing.", t.lexeme());
+                    replaced.push(t);
+                    continue;
+                },
+            };
+
+            // The arguments are in the parentheses after the name, split at the commas which
+            // are not in inner parentheses
+            let mut arguments = Vec::<Vec<Token>>::new();
+            let mut name = format!("{}()", t.lexeme());
+            if let Some(close) = Parser::matching_close(&tokens, i) {
+                name = tokens[i - 1..close + 1].iter().map(|t| t.lexeme()).collect::<Vec<String>>().concat();
+
+                let mut argument = Vec::<Token>::new();
+                let mut depth = 0;
+                for a in tokens[i + 1..close].iter() {
+                    match a.token_type() {
+                        TokenType::LeftParen => depth += 1,
+                        TokenType::RightParen => depth -= 1,
+                        TokenType::Comma if depth == 0 => {
+                            arguments.push(std::mem::take(&mut argument));
+                            continue;
+                        },
+                        _ => {},
+                    }
+                    argument.push(a.clone());
+                }
+                if close > i + 1 {
+                    arguments.push(argument);
+                }
+                i = close + 1;
+            } else if tokens.get(i).is_some_and(|p| p.is_type(TokenType::LeftParen)) {
+                self.error(&t, format!("The arguments of `{}` are missing their `)`", t.lexeme()));
+                return None;
+            }
+
+            let parameters = self.symbol_table.parameters(&t.lexeme()).cloned().unwrap_or_default();
+            if parameters.len() != arguments.len() {
+                self.error(&t, format!("`{}` takes {} argument(s) but is given {}", t.lexeme(), parameters.len(), arguments.len()));
+                return None;
+            }
+
+            let mut replaced_arguments = Vec::<Vec<Token>>::new();
+            for (n, a) in arguments.into_iter().enumerate() {
+                if a.is_empty() {
+                    self.error(&t, format!("Argument {} of `{}` is missing", n + 1, t.lexeme()));
+                    return None;
+                }
+                let (a, inner) = self.call_functions(a)?;
+                calls.extend(inner);
+                replaced_arguments.push(a);
+            }
+
+            // An argument which can not be typed is reported when its code is generated
+            for (n, (p, a)) in parameters.iter().zip(replaced_arguments.iter()).enumerate() {
+                let v_type = match ExpressionParser::new(self.symbol_table.clone(), a.clone()) {
+                    Some(mut e) => {
+                        e.set_calls(calls.clone());
+                        e.value_type()
+                    },
+                    None => continue,
+                };
+                match v_type {
+                    Ok(ref v) if v != p => {
+                        self.error(&t, format!("Argument {} of `{}` is {} but the parameter is {}", n + 1, t.lexeme(), v, p));
+                        return None;
+                    },
+                    _ => {},
+                }
+            }
+
+            // The name can not be declared, so it always stands for the call
+            calls.insert(name.clone(), Call {
+                function: t.lexeme(),
+                arguments: replaced_arguments,
+                result: result,
+                below: Rc::new(Cell::new(0)),
+            });
+            replaced.push(Token::new_with(t.line(), t.column(), name, TokenType::Identifier));
+        }
+
+        Some((replaced, calls))
+    }
+
+    /// Evaluates the tokens of an expression, reading the elements of arrays and calling the
+    /// functions it uses first, and sets last_expression to where the value is.
+    fn evaluate(&mut self, tokens: Vec<Token>) -> ParserState {
+        let (tokens, pushed) = match self.load_elements(tokens) {
+            Some(l) => l,
+            None => return ParserState::Done(ParserResult::Unexpected),
+        };
 
-        match self.statement() {
-            ParserState::Continue => {},
-            _ => {
-                self.insert_last_token();
-                return self.check(TokenType::Keyword(KeywordType::End));
-            }
+        let (tokens, calls) = match self.call_functions(tokens) {
+            Some(c) => c,
+            None => return ParserState::Done(ParserResult::Unexpected),
         };
+        let syntax = self.syntax();
+        self.push_command(format!("movw {} {}", syntax.stack_pointer, syntax.register(1)));
 
-        c_exp!(self.statement_tail());
+        c_exp!(self.parse_expression_tokens(tokens, calls));
 
-        self.check(TokenType::Keyword(KeywordType::End))
-    }
+        if pushed > 0 {
+            self.push_command(format!("subw {} {}", syntax.immediate(4 * pushed), syntax.stack_pointer));
+        }
 
-    // FOLLOW-ID rule
-    fn follow_id(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting FOLLOW-ID rule.");
+        ParserState::Continue
+    }
 
-        // Get the identifier
-        let id = self.last_token().unwrap().lexeme();
+    /// Replaces each element of an array read in the tokens of an expression with the name
+    /// of a symbol holding it and returns Some((tokens, pushed)), or None after reporting
+    /// why an element can not be read. An element at an index which is only known when the
+    /// program runs is pushed onto the stack just below the expression stack, pushed is the
+    /// number of them.
+    fn load_elements(&mut self, tokens: Vec<Token>) -> Option<(Vec<Token>, u32)> {
+        let mut replaced = Vec::<Token>::new();
+        let mut stacked = Vec::<(Token, String, SymbolValueType)>::new();
 
-        // Are we assigning?
-        match self.check(TokenType::Assign) {
-            ParserState::Continue => {
-                match self.expression() {
-                    ParserState::Continue => {
-                        let f = match self.last_expression {
-                            Some(ref e) => {
-                                e.clone()
-                            },
-                            None => {
-                                panic!("<YASLC/Parser> Warning: attempted to use expression to set variable but the expression parser is missing!");
-                            }
-                        };
+        let mut i = 0;
+        while i < tokens.len() {
+            let t = tokens[i].clone();
+            let array = match self.symbol_table.get_cloned(&t.lexeme()) {
+                Some(s) if t.is_type(TokenType::Identifier) => match s.symbol_type.clone() {
+                    SymbolType::Variable(SymbolValueType::Array(e, _)) => Some((s, *e)),
+                    _ => None,
+                },
+                _ => None,
+            };
 
-                        self.last_expression = None;
+            let (s, element) = match array {
+                Some(a) => a,
+                None => {
+                    replaced.push(t);
+                    i += 1;
+                    continue;
+                },
+            };
 
-                        // Move the value of the expression to the identifier
-                        let id_symbol = self.symbol_table.get(&*id).unwrap().clone();
+            let close = match Parser::matching_close(&tokens, i + 1) {
+                Some(c) => c,
+                None => {
+                    self.error(&t, format!("`{}` is an array, only its elements can be used in an expression", t.lexeme()));
+                    return None;
+                },
+            };
 
-                        // Check that we're assigning to a variable
-                        match id_symbol.symbol_type {
-                            SymbolType::Variable(_) => {},
-                            SymbolType::Constant(_) => {
-                                println!("<YASLC/Parser> Attempted to assign a value to a constant!");
-                                return ParserState::Done(ParserResult::Unexpected);
-                            },
-                            SymbolType::Procedure => {
-                                println!("<YASLC/Parser> Attempted to assign a value to a procedure!");
-                                return ParserState::Done(ParserResult::Unexpected);
-                            },
+            let literal = Parser::literal_index(&tokens[i + 2..close]);
+            let (register, offset) = self.element_location(&t, &s, tokens[i + 2..close].to_vec())?;
+            let syntax = self.syntax();
+            let location = syntax.offset(offset, &syntax.register_named(&register));
+
+            let name = match literal {
+                // The element is read where it is, under a name which can not be declared,
+                // since the expression parser writes into symbols whose names start with $
+                Some(n) => {
+                    let name = format!("{}[{}]", t.lexeme(), n);
+                    let known = self.symbol_table.declared(&name).is_some_and(|h| h.location() == location);
+                    if !known {
+                        if let Err(e) = self.symbol_table.add_at(name.clone(), element, &register, offset) {
+                            self.error(&t, e);
+                            return None;
                         }
+                    }
+                    name
+                },
+                None => {
+                    let name = format!("$element_{}", self.elements);
+                    self.elements += 1;
+
+                    self.push_command(format!("movw {} {}", location, syntax.offset(0, &syntax.stack_pointer)));
+                    self.push_command(format!("addw {} {}", syntax.immediate(4), syntax.stack_pointer));
+                    stacked.push((t.clone(), name.clone(), element));
+                    name
+                },
+            };
 
-                        // Check that we're assigning to the same type
-                        match &id_symbol.symbol_type {
-                            &SymbolType::Variable(ref v1) | &SymbolType::Constant(ref v1) => {
-                                match &f.symbol_type {
-                                    &SymbolType::Variable(ref v2) | &SymbolType::Constant(ref v2) => {
-                                        if v1 != v2 {
-                                            println!("<YASLC/Parser> Attempted to assign a value to a variable who's type is not the same!");
-                                            println!("<YASLC/Parser> Variable is type {:?} and value is type {:?}.", id_symbol.symbol_type, f.symbol_type);
-                                            return ParserState::Done(ParserResult::Unexpected)
-                                        }
-                                    },
-                                    _ => {}
-                                }
-                            },
-                            _ => {}
-                        };
-
-                        // Add the command
-                        // TODO: If you wanted to use more registers, this would need to be overriden to use f.register
-                        self.push_command(format!("movw +0@R1 {}", id_symbol.location()));
-
-                        return ParserState::Continue;
-                    },
-                    _ => return ParserState::Done(ParserResult::Unexpected),
-                };
-            },
-            _ => {},
-        };
+            // The element is always assigned, it is only read when the array is
+            self.assigned.insert(name.clone());
+            replaced.push(Token::new_with(t.line(), t.column(), name, TokenType::Identifier));
+            i = close + 1;
+        }
 
-        // All execution around assigning does not reach this point.
-        self.insert_last_token();
+        // The pushed elements are below where R1 will point
+        let pushed = stacked.len() as u32;
+        for (k, (t, name, element)) in stacked.into_iter().enumerate() {
+            let offset = -4 * (pushed as i32 - k as i32);
+            if let Err(e) = self.symbol_table.add_at(name, element, "R1", offset) {
+                self.error(&t, e);
+                return None;
+            }
+        }
 
-        // We're dealing with a proc that may have arguments
-        match self.check(TokenType::LeftParen) {
-            ParserState::Continue => {
-                match self.expression() {
-                    ParserState::Continue => {},
-                    _ => return ParserState::Done(ParserResult::Unexpected),
-                };
+        Some((replaced, pushed))
+    }
 
-                match self.follow_expression() {
-                    ParserState::Continue => {},
-                    _ => return ParserState::Done(ParserResult::Unexpected),
-                };
+    /// Returns Some(c) where c is the position of the `]` or `)` closing the `[` or `(` at
+    /// the position in the tokens, or None if there is neither there or it is never closed.
+    fn matching_close(tokens: &[Token], open: usize) -> Option<usize> {
+        let (left, right) = match tokens.get(open).map(|t| t.token_type()) {
+            Some(TokenType::LeftBracket) => (TokenType::LeftBracket, TokenType::RightBracket),
+            Some(TokenType::LeftParen) => (TokenType::LeftParen, TokenType::RightParen),
+            _ => return None,
+        };
 
-                return self.check(TokenType::RightParen);
+        let mut depth = 0;
+        for (i, t) in tokens.iter().enumerate().skip(open) {
+            match t.token_type() {
+                ref l if *l == left => depth += 1,
+                ref r if *r == right => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                },
+                _ => {},
             }
+        }
 
-            _ => {
-                // It does not, but it should have a semi
-                self.insert_last_token();
-                match self.check(TokenType::Semicolon) {
-                    ParserState::Continue => {
-                        // Call the procedure
-                        self.push_command(format!("call #{} ${}", 0, id));
-                        // TODO: Move the SP, push arguments, etc
-                    },
-                    _ => {
-                        // Check if it is an end token
-                        self.insert_last_token();
-                        match self.check(TokenType::Keyword(KeywordType::End)) {
-                            ParserState::Continue => {
-                                self.insert_last_token();
+        None
+    }
 
-                                // Call the proc
-                                self.push_command(format!("call #{} ${}", 0, id));
-                                // TODO: Move the SP, push arguments, etc
-                            },
-                            x => return x,
-                        };
-                    }
-                };
-            },
-        };
+    /// Returns Some(i) if the index is a single number i, which is known when compiling.
+    fn literal_index(index: &[Token]) -> Option<i32> {
+        match index {
+            [n] => n.number_value(),
+            _ => None,
+        }
+    }
 
-        self.insert_last_token();
+    /// Returns Some((register, offset)) with where the element of the array at the index is,
+    /// or None after reporting why it can not be found. A literal index is checked against
+    /// the size of the array, any other index is evaluated and the address of the element
+    /// is left in R1, which the offset is then from.
+    fn element_location(&mut self, array: &Token, s: &Symbol, index: Vec<Token>) -> Option<(String, i32)> {
+        let size = match s.symbol_type {
+            SymbolType::Variable(SymbolValueType::Array(_, n)) => n,
+            _ => 0,
+        };
+        let (register, offset) = s.base();
 
-        ParserState::Continue
-    }
+        if let Some(i) = Parser::literal_index(&index) {
+            if i >= size as i32 {
+                self.error(&index[0], format!("The index {} is out of bounds, `{}` has {} elements", i, array.lexeme(), size));
+                return None;
+            }
+            return Some((register, offset + 4 * i));
+        }
 
-    // FOLLOW-EXPRESSION rule
-    fn follow_expression(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting FOLLOW-EXPRESSION rule.");
+        if index.is_empty() {
+            self.error(array, format!("The index of `{}` is missing", array.lexeme()));
+            return None;
+        }
 
-        match self.check(TokenType::Comma) {
+        match self.evaluate(index) {
             ParserState::Continue => {},
-            _ => {
-                self.insert_last_token();
-                return ParserState::Continue;
+            _ => return None,
+        }
+
+        let f = self.last_expression.take()?;
+        match f.symbol_type {
+            SymbolType::Variable(SymbolValueType::Int) | SymbolType::Constant(SymbolValueType::Int) => {},
+            SymbolType::Variable(ref t) | SymbolType::Constant(ref t) => {
+                self.error(array, format!("The index of `{}` must be an int but it is {}", array.lexeme(), t));
+                return None;
             },
-        };
+            SymbolType::Procedure(_) => return None,
+        }
 
-        c_exp!(self.expression());
+        let syntax = self.syntax();
+        let r1 = syntax.register(1);
+        self.push_command(format!("movw {} {}", f.location(), r1));
+        self.push_command(format!("mulw {} {}", syntax.immediate(4), r1));
+        self.push_command(format!("addw {} {}", syntax.register_named(&register), r1));
 
-        self.follow_expression()
+        Some(("R1".to_string(), offset))
     }
 
-    // FOLLOW-PROMPT rule
-    fn follow_prompt(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting FOLLOW-PROMPT rule.");
-
-        match self.check(TokenType::Comma) {
-            ParserState::Continue => {},
-            _ => {
-                // It was not, we ignore the input
-                self.insert_last_token();
-                return ParserState::Continue;
+    /// Assigns the value of the expression after `[index] =` to the element of the array
+    /// named by the token. The value is evaluated first and kept on the stack while an
+    /// index which is not a literal is evaluated.
+    fn assign_element(&mut self, name: &Token) -> ParserState {
+        let id = name.lexeme();
+        let (s, element) = match self.symbol_table.get_cloned(&id) {
+            Some(s) => match s.symbol_type.clone() {
+                SymbolType::Variable(SymbolValueType::Array(e, _)) => (s, *e),
+                _ => {
+                    self.error(name, format!("`{}` is not an array, so it can not be indexed", id));
+                    return ParserState::Done(ParserResult::Unexpected);
+                },
+            },
+            None => {
+                self.record_undeclared(&id);
+                self.error(name, format!("`{}` has not been declared", id));
+                return ParserState::Done(ParserResult::Unexpected);
             },
         };
 
-        self.check(TokenType::Identifier)
-    }
+        let close = match Parser::matching_close(&self.tokens, 0) {
+            Some(c) => c,
+            None => {
+                self.error(name, format!("The index of `{}` is missing its `]`", id));
+                return ParserState::Done(ParserResult::Unexpected);
+            },
+        };
+        let mut index: Vec<Token> = self.tokens.drain(..close + 1).collect();
 
-    // FOLLOW-PRINT
-    fn follow_print(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting FOLLOW-PRINT rule.");
+        let mut target = vec![name.clone()];
+        target.extend(index.iter().cloned());
+        self.expand_compound_assignment(&target);
 
-        match self.check(TokenType::String) {
-            ParserState::Continue => {
-                // It is a String
+        index.pop();
+        index.remove(0);
 
-                let m = self.last_token().unwrap().lexeme();
-                self.add_print_command(&*m);
+        c_token!(self, TokenType::Assign);
+        c_exp!(self.expression());
 
-                log!("<YASLC/Parser> Successfully parsed print statement, compiling to file.");
+        let f = match self.last_expression.take() {
+            Some(e) => e,
+            None => {
+                println!("<YASLC/Parser> Warning: attempted to use expression to set an element but the expression parser is missing!");
+                return ParserState::Done(ParserResult::Unexpected);
+            }
+        };
 
-                return ParserState::Continue
+        match f.symbol_type {
+            SymbolType::Variable(ref v) | SymbolType::Constant(ref v) if *v != element => {
+                self.error(name, format!("The elements of `{}` are {}s but the value is {}", id, element, v));
+                return ParserState::Done(ParserResult::Unexpected);
             },
-            _ => self.insert_last_token(),
+            _ => {},
         }
 
-        log!("<YASLC/Parser> Adding print statement waiting for expression.");
-        match self.expression() {
-            ParserState::Continue => {
-                let f = if let Some(ref e) = self.last_expression {
-                    e.clone()
-                } else {
-                    println!("<YASLc/Parser> Expected to find an expression parser but it went missing!");
-                    return ParserState::Done(ParserResult::Unexpected);
-                };
+        let computed = Parser::literal_index(&index).is_none();
+        let syntax = self.syntax();
+        if computed {
+            self.push_command(format!("movw {} {}", f.location(), syntax.offset(0, &syntax.stack_pointer)));
+            self.push_command(format!("addw {} {}", syntax.immediate(4), syntax.stack_pointer));
+        }
 
-                self.push_command(format!("outw {}", f.location()));
-                self.push_command(format!("outb #10"));
+        let (register, offset) = match self.element_location(name, &s, index) {
+            Some(l) => l,
+            None => return ParserState::Done(ParserResult::Unexpected),
+        };
+        let location = syntax.offset(offset, &syntax.register_named(&register));
 
-                self.last_expression = None;
+        if computed {
+            self.push_command(format!("subw {} {}", syntax.immediate(4), syntax.stack_pointer));
+            self.push_command(format!("movw {} {}", syntax.offset(0, &syntax.stack_pointer), location));
+        } else {
+            self.push_command(format!("movw {} {}", f.location(), location));
+        }
 
-                return ParserState::Continue;
-            },
-            _ => return ParserState::Done(ParserResult::Unexpected),
-        };
+        self.assigned.insert(id);
+
+        ParserState::Continue
     }
 
     fn expression(&mut self) -> ParserState {
-        log!("<YASLC/Parser> Starting EXPRESSION rule.");
-
-        if self.commands.prefix.is_none() {
-            self.push_command(format!(""));
-        }
-        self.push_command(format!("movw SP R1"));
+        log!(self.options.verbose, "<YASLC/Parser> Starting EXPRESSION rule.");
 
         let mut stack = Vec::<Token>::new();
 
+        // The number of parentheses which are open, a `)` only ends the expression when
+        // there are none
+        let mut depth = 0;
+
         while self.tokens.is_empty() == false {
             let t = self.tokens.remove(0);
             match t.token_type() {
-                TokenType::Semicolon | TokenType::Keyword(KeywordType::Do)
-                | TokenType::Keyword(KeywordType::Then) | TokenType::Keyword(KeywordType::End)
-                | TokenType::RightParen | TokenType::Keyword(KeywordType::Else) => {
+                TokenType::RightParen if depth > 0 => {
+                    depth -= 1;
+                    stack.push(t);
+                }
+                // A comma in parentheses separates the arguments of a call
+                TokenType::Comma if depth > 0 => {
+                    stack.push(t);
+                }
+                _ if Parser::ends_expression(&t, stack.last()) => {
                     // We can exit because it is the end of the expression
-                    log!("<YASLC/Parser> Exiting EXPRESSION rule because we found a {} token.", t);
+                    log!(self.options.verbose, "<YASLC/Parser> Exiting EXPRESSION rule because we found a {} token.", t);
 
                     self.tokens.insert(0, t);
-                    return self.parse_expression_tokens(stack);
+
+                    if self.commands.prefix.is_none() {
+                        self.push_command(format!(""));
+                    }
+
+                    return self.evaluate(stack);
+                }
+                // len is only built in when no symbol is named len
+                TokenType::Identifier if t.lexeme() == "len" && self.symbol_table.declared("len").is_none()
+                    && self.tokens.first().is_some_and(|p| p.is_type(TokenType::LeftParen)) => {
+                    match self.string_length(&t) {
+                        Some(n) => stack.push(n),
+                        None => return ParserState::Done(ParserResult::Unexpected),
+                    }
+                }
+                TokenType::LeftParen => {
+                    depth += 1;
+                    stack.push(t);
                 }
                 _ => {
                     stack.push(t);
@@ -1067,32 +3710,130 @@ impl Parser {
             };
         }
 
-        log!("<YASLC/Parser> Exiting EXPRESSION rule because unexpectedly we ran out of tokens.");
+        log!(self.options.verbose, "<YASLC/Parser> Exiting EXPRESSION rule because unexpectedly we ran out of tokens.");
 
         ParserState::Done(ParserResult::Unexpected)
     }
 
-    fn parse_expression_tokens(&mut self, tokens: Vec<Token>) -> ParserState {
+    /// Returns true if the token can not continue an expression after the previous token,
+    /// so the expression ends before it. Besides the tokens which follow an expression, such
+    /// as `then` or a semicolon, a statement keyword or two operands in a row end it so a
+    /// missing `then` or `do` is reported where it should be.
+    fn ends_expression(t: &Token, previous: Option<&Token>) -> bool {
+        let operand = |t: &Token| matches!(t.token_type(), TokenType::Identifier | TokenType::Number
+                                           | TokenType::String | TokenType::Char | TokenType::Keyword(KeywordType::True)
+                                           | TokenType::Keyword(KeywordType::False));
+        if operand(t) && previous.is_some_and(|p| operand(p) || p.is_type(TokenType::RightBracket)) {
+            return true;
+        }
+
+        match t.token_type() {
+            TokenType::Keyword(KeywordType::And) | TokenType::Keyword(KeywordType::Or)
+            | TokenType::Keyword(KeywordType::Not) | TokenType::Keyword(KeywordType::True)
+            | TokenType::Keyword(KeywordType::False) | TokenType::Keyword(KeywordType::Div)
+            | TokenType::Keyword(KeywordType::Mod) | TokenType::Keyword(KeywordType::BAnd)
+            | TokenType::Keyword(KeywordType::BOr) | TokenType::Keyword(KeywordType::BXor)
+            | TokenType::Keyword(KeywordType::Shl) | TokenType::Keyword(KeywordType::Shr) => false,
+            TokenType::Keyword(_) | TokenType::Semicolon | TokenType::RightParen | TokenType::Comma => true,
+            _ => false,
+        }
+    }
+
+    /// Reads the rest of `len(...)` after the len token and returns Some(t) where t is a
+    /// number token with the length of the string or string constant in the parentheses, or
+    /// None after reporting why it has no length.
+    fn string_length(&mut self, len: &Token) -> Option<Token> {
+        let argument = match (self.tokens.get(1), self.tokens.get(2)) {
+            (Some(a), Some(r)) if r.is_type(TokenType::RightParen) => a.clone(),
+            _ => {
+                self.error(len, "`len` takes a single string in parentheses".to_string());
+                return None;
+            },
+        };
+        self.tokens.drain(..3);
+
+        let string = match argument.token_type() {
+            TokenType::String => Some(argument.lexeme()),
+            TokenType::Identifier => self.symbol_table.string_constant(&argument.lexeme()),
+            _ => None,
+        };
+
+        match string {
+            // The length does not count the quotes
+            Some(s) => {
+                let n = s.chars().count().saturating_sub(2);
+                Some(Token::new_with(len.line(), len.column(), n.to_string(), TokenType::Number))
+            },
+            None => {
+                self.error(&argument, format!("`{}` is not a string or string constant", argument.lexeme()));
+                None
+            },
+        }
+    }
+
+    fn parse_expression_tokens(&mut self, tokens: Vec<Token>, calls: HashMap<String, Call>) -> ParserState {
+        if self.options.warn_unassigned {
+            self.check_assigned(&tokens);
+        }
+
         let mut comment = String::new();
         comment.push_str(&*"expression: ");
         for t in tokens.iter() {
             comment.push_str(&*format!("{} ", t.lexeme()));
         }
 
+        // The expression can not be parsed if it uses a name which was never declared
+        let arguments = calls.values().flat_map(|c| c.arguments.iter().flatten());
+        let undeclared = tokens.iter().chain(arguments)
+            .find(|t| t.is_type(TokenType::Identifier) && !calls.contains_key(&t.lexeme())
+                  && self.symbol_table.get(&t.lexeme()).is_none())
+            .map(|t| t.lexeme());
+
+        // Substitute the values of known constant variables
+        let tokens = if self.options.aggressive {
+            tokens.into_iter().map(|t| {
+                match self.known_constants.get(&t.lexeme()) {
+                    Some(v) if t.is_type(TokenType::Identifier) => {
+                        Token::new_with(t.line(), t.column(), v.clone(), TokenType::Number)
+                    },
+                    _ => t,
+                }
+            }).collect()
+        } else {
+            tokens
+        };
+
+        // The calls share where they are made with the expression parser
+        let sites: Vec<Call> = calls.values().cloned().collect();
+        let first = tokens.first().cloned();
+
         match ExpressionParser::new(self.symbol_table.clone(), tokens) {
-            Some(e) => {
-                log!("<YASLC/Parser> Expression parser successfully exited!");
+            Some(mut e) => {
+                e.set_folding(self.options.aggressive);
+                e.set_calls(calls);
+
+                log!(self.options.verbose, "<YASLC/Parser> Expression parser successfully exited!");
+
+                // The expression parser numbers its labels from a copy of the table, so the
+                // labels it may take are skipped here for the next expression
+                let labels = e.bool_temps();
 
                 // Parse through the tokens
                 match e.parse() {
                     Ok((f_symbol, commands)) => {
-                        let _ = self.symbol_table.bool_temp();
+                        for _ in 0..labels.max(1) {
+                            let _ = self.symbol_table.bool_temp();
+                        }
 
                         self.commands.push_command(format!(": {}", comment));
 
                         // Add the commands to this list of commands
                         self.commands.push_builder(commands);
 
+                        for c in sites.iter() {
+                            self.record_call(&c.function, c.below.get());
+                        }
+
                         // Reset the symbol table
                         self.symbol_table.reset_offset();
 
@@ -1101,13 +3842,28 @@ impl Parser {
                         ParserState::Continue
                     },
                     Err(e) => {
-                        log!("<YASLC/Parser> Expression parser was not successful: {}", e);
+                        log!(self.options.verbose, "<YASLC/Parser> Expression parser was not successful: {}", e);
+                        match undeclared {
+                            Some(name) => self.record_undeclared(&name),
+                            None => {
+                                // A message which starts with the position of the expression
+                                // already says where it is
+                                let mut error = self.diagnostic(Severity::Error, first.as_ref(), e.clone());
+                                if first.is_some_and(|t| e.starts_with(&format!("({}, {})", t.line(), t.column()))) {
+                                    error.position = None;
+                                }
+                                self.report(error);
+                            },
+                        }
                         ParserState::Done(ParserResult::Unexpected)
                     }
                 }
             },
             None => {
-                log!("<YASLC/Parser> Expression parser was not in initialization!");
+                log!(self.options.verbose, "<YASLC/Parser> Expression parser was not in initialization!");
+                if let Some(name) = undeclared {
+                    self.record_undeclared(&name);
+                }
                 ParserState::Done(ParserResult::Unexpected)
             }
         }
@@ -1124,6 +3880,7 @@ enum ParserState {
 }
 
 /// The result of a finished parser.
+#[derive(Debug)]
 pub enum ParserResult {
     /// The parser should continue parsing starting with the next token.
     Success,
@@ -1140,40 +3897,88 @@ pub struct CommandBuilder {
     commands: Vec<String>,
 
     prefix: Option<String>,
+
+    /// Whether the commands are logged as they are pushed.
+    verbose: bool,
+
+    /// The syntax the commands are written in.
+    syntax: Rc<Syntax>,
 }
 
 impl CommandBuilder {
-    fn new() -> CommandBuilder {
+    fn new(verbose: bool, syntax: Rc<Syntax>) -> CommandBuilder {
         CommandBuilder {
             commands: Vec::<String>::new(),
             prefix: None,
+            verbose: verbose,
+            syntax: syntax,
         }
     }
 
     fn push_command(&mut self, command: String) {
         match self.prefix {
             Some(ref s) => {
-                log!("Pushing prefix with command: {} {}", s, command);
+                log!(self.verbose, "Pushing prefix with command: {} {}", s, command);
                 self.commands.push(format!("{} {}", s, command));
             },
             None => {
-                log!("Pushing command: {}", command);
+                log!(self.verbose, "Pushing command: {}", command);
                 self.commands.push(command);
             }
         };
         self.prefix = None;
     }
 
+    /// Pushes a comment line, leaving any label prefix for the next command since a label
+    /// can not be put on a comment.
+    fn push_comment(&mut self, comment: String) {
+        log!(self.verbose, "Pushing comment: {}", comment);
+        self.commands.push(comment);
+    }
+
     fn set_prefix(&mut self, prefix: String) -> String {
         if self.prefix.is_some() {
-            self.push_command(format!("movw R0 R0"));
+            self.push_useless();
         }
         self.prefix = Some(prefix.clone());
         prefix
     }
 
     fn push_useless(&mut self) {
-        self.push_command(format!("movw R0 R0"));
+        let r0 = self.syntax.register(0);
+        self.push_command(format!("movw {} {}", r0, r0));
+    }
+
+    /// Returns a mark of the current commands and prefix which rewind can return to.
+    fn mark(&self) -> (usize, Option<String>) {
+        (self.commands.len(), self.prefix.clone())
+    }
+
+    /// Discards every command pushed since the mark was taken and restores its prefix.
+    fn rewind(&mut self, mark: (usize, Option<String>)) {
+        self.commands.truncate(mark.0);
+        self.prefix = mark.1;
+    }
+
+    /// Inserts the commands where the mark was taken. The prefix which was waiting at the
+    /// mark goes on the first inserted command instead of the command that took it.
+    fn insert_at(&mut self, mark: (usize, Option<String>), mut commands: Vec<String>) {
+        if mark.0 >= self.commands.len() || commands.is_empty() {
+            for c in commands {
+                self.push_command(c);
+            }
+            return;
+        }
+
+        if let Some(prefix) = mark.1 {
+            let labeled = format!("{} ", prefix);
+            if let Some(rest) = self.commands[mark.0].strip_prefix(&labeled).map(|r| r.to_string()) {
+                self.commands[mark.0] = rest;
+                commands[0] = format!("{}{}", labeled, commands[0]);
+            }
+        }
+
+        self.commands.splice(mark.0..mark.0, commands);
     }
 
     // fn prepend_last(&mut self, prefix: String) {
@@ -1182,7 +3987,7 @@ impl CommandBuilder {
     //             s
     //         },
     //         None => {
-    //             log!("Warning: Command builder tried to prepend the last command but there was none! Setting prefix...");
+    //             log!(self.verbose, "Warning: Command builder tried to prepend the last command but there was none! Setting prefix...");
     //             self.set_prefix(prefix);
     //             return;
     //         }
@@ -1192,6 +3997,12 @@ impl CommandBuilder {
     //     self.commands.push(new);
     // }
 
+    /// Returns the number of commands in the builder.
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
     fn push_builder(&mut self, builder: CommandBuilder) {
         for c in builder.commands {
             self.push_command(c);
@@ -1203,3 +4014,12 @@ impl CommandBuilder {
         };
     }
 }
+
+#[cfg(test)]
+impl Index<usize> for CommandBuilder {
+    type Output = String;
+
+    fn index(&self, index: usize) -> &String {
+        &self.commands[index]
+    }
+}
@@ -0,0 +1,110 @@
+/// parser/optimizer.rs
+///
+/// The optimizer module contains passes which rewrite the final list of instructions
+/// into an equivalent, cheaper list.
+
+use super::instruction::Instruction;
+
+use std::collections::{HashMap, HashSet};
+
+/// Replaces reads of memory locations that are known to hold an immediate value with that
+/// immediate, for example "movw #5 +0@R1" followed by "addw +0@R1 +4@R1" becomes
+/// "addw #5 +4@R1".
+///
+/// Values are only known within straight line code. Everything is forgotten at any label
+/// that is the target of a jump, branch or call and after a call. Every instruction is
+/// kept, so the result has an instruction for each one given at the same position.
+pub fn propagate_constants(instructions: Vec<Instruction>) -> Vec<Instruction> {
+    // Collect every label control can be transferred to
+    let mut targets = HashSet::<String>::new();
+    for i in instructions.iter() {
+        if let Some(t) = i.branch_target() {
+            targets.insert(t.to_string());
+        }
+        if i.mnemonic() == Some("call") {
+            if let Some(t) = i.operands().last() {
+                targets.insert(t.trim_start_matches('$').to_string());
+            }
+        }
+    }
+
+    let mut known = HashMap::<String, String>::new();
+    let mut result = Vec::<Instruction>::new();
+
+    for i in instructions {
+        if let Some(l) = i.label() {
+            if targets.contains(l) {
+                known.clear();
+            }
+        }
+
+        let (label, mnemonic, mut operands) = match i {
+            Instruction::Op { label, mnemonic, operands } => (label, mnemonic, operands),
+            other => {
+                result.push(other);
+                continue;
+            },
+        };
+
+        // Substitute every operand which is only read, the destination of an operation is
+        // read too but has to stay a location
+        let reads = match &*mnemonic {
+            "movw" | "addw" | "subw" | "mulw" | "divw" => 1,
+            "andw" | "orw" | "xorw" | "shlw" | "shrw" => 1,
+            "addf" | "subf" | "mulf" | "divf" => 1,
+            "cmpw" | "cmpf" => 2,
+            "outw" | "outb" | "outf" => 1,
+            _ => 0,
+        };
+        for o in operands.iter_mut().take(reads) {
+            if let Some(v) = known.get(o) {
+                *o = v.clone();
+            }
+        }
+
+        // Update what is known about the destination
+        let written = match &*mnemonic {
+            "movw" | "addw" | "subw" | "mulw" | "divw" => operands.get(1).cloned(),
+            "andw" | "orw" | "xorw" | "shlw" | "shrw" => operands.get(1).cloned(),
+            "addf" | "subf" | "mulf" | "divf" => operands.get(1).cloned(),
+            "inw" | "inb" => operands.first().cloned(),
+            _ => None,
+        };
+        if let Some(dst) = written {
+            forget(&mut known, &dst);
+            if mnemonic == "movw" && operands[0].starts_with('#') {
+                known.insert(dst, operands[0].clone());
+            }
+        }
+
+        if mnemonic == "call" {
+            known.clear();
+        }
+
+        result.push(Instruction::Op {
+            label: label,
+            mnemonic: mnemonic,
+            operands: operands,
+        });
+    }
+
+    result
+}
+
+/// Forgets the known values that may have been changed by writing to dst.
+fn forget(known: &mut HashMap<String, String>, dst: &str) {
+    match dst.find('@') {
+        // A memory location, forget it and anything addressed by another register
+        // because it could be the same memory
+        Some(n) => {
+            let register = &dst[n..];
+            known.retain(|loc, _| loc != dst && loc.ends_with(register));
+        },
+
+        // A register, forget everything addressed by it
+        None => {
+            let register = format!("@{}", dst);
+            known.retain(|loc, _| !loc.ends_with(&*register) && loc != dst);
+        },
+    };
+}
@@ -0,0 +1,796 @@
+/// parser/symbol/mod.rs
+///
+/// The symbol module is responsible for maintaining a symbol tree and making sure
+/// it is valid.
+///
+
+#[cfg(test)]
+mod tests;
+
+use super::syntax::Syntax;
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+/// The most characters a string variable holds.
+pub const STRING_CAPACITY: u32 = 32;
+
+/// Prints the message when verbose is true, without a newline after NNL.
+macro_rules! log {
+    (NNL $verbose:expr, $message:expr $(,$arg:expr)*) => {
+        if $verbose {
+            print!($message, $($arg,)*);
+        }
+    };
+
+    ($verbose:expr, $message:expr $(,$arg:expr)*) => {
+        if $verbose {
+            println!($message, $($arg,)*);
+        }
+    };
+}
+
+///
+/// SymbolTable is a data structure responsible for managing symbols
+/// and pushing and popping scopes, as well as refusing symbols
+/// if they overlap in the current scope.
+///
+/// Enclosing scopes are shared between clones of a table, so cloning only copies the
+/// symbols of the current scope. An enclosing scope is only copied if it is modified
+/// while it is still shared, which happens when exiting a scope.
+///
+#[derive(Clone)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+
+    /// The enclosing scope, shared with any clones of this table.
+    old_table: Option<Rc<SymbolTable>>,
+
+    register: Option<String>,
+
+    register_n: u32,
+
+    next_offset: u32,
+
+    next_temp: u32,
+
+    next_bool_temp: u32,
+
+    next_if_temp: u32,
+
+    next_while_temp: u32,
+
+    next_for_temp: u32,
+
+    proc_stack: Vec<String>,
+
+    /// The number of registers available, R0 and R1 are always used for the globals and
+    /// the expression stack so any after them hold temps.
+    registers: u32,
+
+    /// The next register which is free to hold a temp.
+    next_register: u32,
+
+    /// The most bytes the temps of this scope have used on the expression stack, shared
+    /// with the clones of the table so the temps of the expression parser count.
+    temp_high_water: Rc<Cell<u32>>,
+
+    /// Whether the table logs its progress.
+    verbose: bool,
+
+    /// The procedure this is the scope of, None for the main program and the scopes which
+    /// are not a procedure.
+    scope: Option<String>,
+
+    /// The names of the symbols of this scope which have been looked up with get, shared
+    /// with the clones of the table so the lookups of the expression parser count.
+    used: Rc<RefCell<HashSet<String>>>,
+
+    /// The text of the string constants of this scope with their quotes, by identifier.
+    strings: HashMap<String, String>,
+
+    /// The syntax the locations of the symbols are written in, shared with every scope.
+    syntax: Rc<Syntax>,
+}
+
+impl SymbolTable {
+    /// Returns a new empty symbol table
+    pub fn empty() -> SymbolTable {
+        SymbolTable {
+            symbols: Vec::<Symbol>::new(),
+            old_table: None,
+            register: None,
+            register_n: 0,
+            next_offset: 0,
+            next_temp: 0,
+            next_bool_temp: 0,
+            next_if_temp: 0,
+            next_while_temp: 0,
+            next_for_temp: 0,
+            proc_stack: Vec::<String>::new(),
+            registers: 2,
+            next_register: 2,
+            temp_high_water: Rc::new(Cell::new(0)),
+            verbose: false,
+            scope: None,
+            used: Rc::new(RefCell::new(HashSet::new())),
+            strings: HashMap::new(),
+            syntax: Rc::new(Syntax::default()),
+        }
+    }
+
+    /// Sets the syntax the locations of the symbols added after this are written in.
+    pub fn set_syntax(&mut self, syntax: Syntax) {
+        self.syntax = Rc::new(syntax);
+    }
+
+    /// Returns the syntax the locations of the symbols are written in.
+    pub fn syntax(&self) -> Rc<Syntax> {
+        self.syntax.clone()
+    }
+
+    /// Sets the number of registers available. Temps are held in the registers after R1
+    /// until there are none left, then they are stored on the stack.
+    pub fn set_registers(&mut self, registers: u32) {
+        self.registers = registers;
+    }
+
+    /// Sets whether the table and the expression parsers given it log their progress.
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    /// Returns whether the table logs its progress.
+    pub fn verbose(&self) -> bool {
+        self.verbose
+    }
+
+    /// Consumes self to make it the child of the next scope
+    fn child_table(self) -> SymbolTable {
+        log!(self.verbose, "<YASLC/SymbolTable> Creating child symbol table for table to create new scope.");
+
+        let register_n = self.register_n;
+        let n_o = self.next_offset;
+        let n_t = self.next_temp;
+        let n_bt = self.next_bool_temp;
+        let n_it = self.next_if_temp;
+        let n_wt = self.next_while_temp;
+        let n_ft = self.next_for_temp;
+        let ps = self.proc_stack.clone();
+        let registers = self.registers;
+        let n_r = self.next_register;
+        let verbose = self.verbose;
+        let syntax = self.syntax.clone();
+
+        let pointer_old = Rc::new(self);
+
+        SymbolTable {
+            symbols: Vec::<Symbol>::new(),
+            old_table: Some(pointer_old),
+            register: None,
+            register_n: register_n,
+            next_offset: n_o,
+            next_temp: n_t,
+            next_bool_temp: n_bt,
+            next_if_temp: n_it,
+            next_while_temp: n_wt,
+            next_for_temp: n_ft,
+            proc_stack: ps,
+            registers: registers,
+            next_register: n_r,
+            temp_high_water: Rc::new(Cell::new(0)),
+            verbose: verbose,
+            scope: None,
+            used: Rc::new(RefCell::new(HashSet::new())),
+            strings: HashMap::new(),
+            syntax: syntax,
+        }
+    }
+
+    /// Adds a symbol given the identifer and type, returns Err(e) where e describes the error
+    /// naming the symbol which is already declared with the identifier in the scope.
+    pub fn add(&mut self, identifier: String, t: SymbolType) -> Result<(), String> {
+        if let Some(s) = self.symbols.iter().find(|s| s.identifier == identifier) {
+            return Err(format!("`{}` is already declared in {} as {}", identifier, self.scope_name(), s.symbol_type.description()));
+        }
+
+        if let Some(scope) = self.shadows(&identifier) {
+            log!(self.verbose, "<YASLC/SymbolTable> Symbol {} shadows a symbol in the enclosing scope {}.", identifier, scope);
+        }
+
+        if matches!(t, SymbolType::Procedure(_)) {
+            log!(self.verbose, "Found a procedure!");
+            self.proc_stack.push(identifier.clone());
+        }
+
+        let r = match self.register {
+            Some(ref r) => {
+                Some(r.clone())
+            },
+            None => None,
+        };
+
+        let o = self.next_offset as i32;
+
+        let s = Symbol {
+            identifier: identifier,
+            symbol_type: t,
+            register: r,
+            register_n: 0,
+            offset: o,
+            held: false,
+            syntax: self.syntax.clone(),
+        };
+        self.next_offset += s.size();
+        self.add_symbol(s);
+
+        Ok(())
+    }
+
+    /// Adds a parameter of the procedure whose scope this is, which the caller pushed at the
+    /// offset from FP, returns Err(e) where e describes the error naming the symbol which is
+    /// already declared with the identifier in the scope.
+    pub fn add_parameter(&mut self, identifier: String, v_type: SymbolValueType, offset: i32) -> Result<(), String> {
+        if let Some(s) = self.symbols.iter().find(|s| s.identifier == identifier) {
+            return Err(format!("`{}` is already declared in {} as {}", identifier, self.scope_name(), s.symbol_type.description()));
+        }
+
+        // A parameter is given by every call, so it is not reported when it is unused
+        self.used.borrow_mut().insert(identifier.clone());
+
+        self.add_symbol(Symbol {
+            identifier: identifier,
+            symbol_type: SymbolType::Variable(v_type),
+            register: Some("FP".to_string()),
+            register_n: 0,
+            offset: offset,
+            held: false,
+            syntax: self.syntax.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Adds a variable of the type at the offset from the register, such as an element of an
+    /// array at a known index or a value pushed onto the stack below the expression stack,
+    /// returns Err(e) where e describes the error naming the symbol which is already declared
+    /// with the identifier in the scope.
+    pub fn add_at(&mut self, identifier: String, v_type: SymbolValueType, register: &str, offset: i32) -> Result<(), String> {
+        if let Some(s) = self.symbols.iter().find(|s| s.identifier == identifier) {
+            return Err(format!("`{}` is already declared in {} as {}", identifier, self.scope_name(), s.symbol_type.description()));
+        }
+
+        self.add_symbol(Symbol {
+            identifier: identifier,
+            symbol_type: SymbolType::Variable(v_type),
+            register: Some(register.to_string()),
+            register_n: 0,
+            offset: offset,
+            held: false,
+            syntax: self.syntax.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Returns Some(types) with the types of the parameters of the procedure the name looks
+    /// up, or None if it does not look up a procedure.
+    pub fn parameters(&self, proc_name: &str) -> Option<&Vec<SymbolValueType>> {
+        match self.get(proc_name) {
+            Some(&Symbol { symbol_type: SymbolType::Procedure(ref types), .. }) => Some(types),
+            _ => None,
+        }
+    }
+
+    /// Adds (binds) a new symbol to the table
+    fn add_symbol(&mut self, s: Symbol) {
+        self.symbols.insert(0, s);
+        log!(self.verbose, "<YASLC/SymbolTable> Added new symbol to table, printing...");
+        self.log_table();
+    }
+
+    /// Get (lookup) a symbol on the table, which counts as a use of it
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.find(name, true)
+    }
+
+    /// Sets the text of the string constant with the identifier, which must already be
+    /// declared in this scope.
+    pub fn set_string(&mut self, identifier: &str, text: String) {
+        self.strings.insert(identifier.to_string(), text);
+    }
+
+    /// Returns Some(t) where t is the text of the string constant the name refers to, with
+    /// its quotes, or None if the symbol the name refers to is not a string constant. Counts
+    /// as a use of the symbol like get.
+    pub fn string_constant(&self, name: &str) -> Option<String> {
+        if self.symbols.iter().any(|s| s.identifier == name) {
+            self.used.borrow_mut().insert(name.to_string());
+            return self.strings.get(name).cloned();
+        }
+
+        match self.old_table {
+            Some(ref b) => b.string_constant(name),
+            None => None,
+        }
+    }
+
+    /// Returns the symbol with the name like get without counting it as a use, for looking
+    /// up a symbol which was just declared.
+    pub fn declared(&self, name: &str) -> Option<&Symbol> {
+        self.find(name, false)
+    }
+
+    /// Looks up the symbol with the name through the enclosing scopes, recording that it was
+    /// used in the scope it is in if used is true.
+    fn find(&self, name: &str, used: bool) -> Option<&Symbol> {
+        for s in self.symbols.iter() {
+            if s.identifier == name {
+                if used {
+                    self.used.borrow_mut().insert(name.to_string());
+                }
+                return Some(s);
+            }
+        }
+
+        // If we have a lower table use that
+        if let Some(ref b) = self.old_table {
+            return (*b).find(name, used);
+        }
+
+        None
+    }
+
+    /// Returns the names of the variables and constants of the current scope which have
+    /// never been looked up with get, in the order they were declared.
+    pub fn unused(&self) -> Vec<String> {
+        let used = self.used.borrow();
+        self.symbols.iter().rev()
+            .filter(|s| matches!(s.symbol_type, SymbolType::Variable(_) | SymbolType::Constant(_)))
+            .filter(|s| !s.is_temp() && !s.is_element() && !used.contains(&s.identifier))
+            .map(|s| s.identifier.clone())
+            .collect()
+    }
+
+    /// Returns a copy of the symbol with the name, looking through the enclosing scopes, so
+    /// the table is not borrowed while the symbol is used.
+    pub fn get_cloned(&self, name: &str) -> Option<Symbol> {
+        self.get(name).cloned()
+    }
+
+    /// Returns the symbols of the current scope, most recently added first.
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Returns every symbol which can be used by name here, innermost scope first, leaving
+    /// out temps, the elements of arrays and the symbols of enclosing scopes which are shadowed.
+    pub fn visible(&self) -> Vec<Symbol> {
+        let mut visible = Vec::<Symbol>::new();
+        let mut table = Some(self);
+        while let Some(t) = table {
+            for s in t.symbols.iter() {
+                if !s.is_temp() && !s.is_element() && !visible.iter().any(|v| v.identifier == s.identifier) {
+                    visible.push(s.clone());
+                }
+            }
+            table = t.old_table.as_deref();
+        }
+        visible
+    }
+
+    /// Returns Some(scope) where scope is the name of the nearest enclosing scope with a
+    /// symbol of the name, so adding it to this scope would shadow it, or None if there is
+    /// no such symbol.
+    pub fn shadows(&self, name: &str) -> Option<String> {
+        let mut table = self.old_table.as_ref();
+        while let Some(t) = table {
+            if t.symbols.iter().any(|s| s.identifier == name) {
+                return Some(t.scope_name());
+            }
+            table = t.old_table.as_ref();
+        }
+
+        None
+    }
+
+    /// Returns the name of the procedure of this scope, or mainblock for the outermost scope.
+    fn scope_name(&self) -> String {
+        self.scope.clone().unwrap_or_else(|| "mainblock".to_string())
+    }
+
+    /// Enters the next table
+    pub fn enter(self) -> SymbolTable {
+        self.child_table()
+    }
+
+    /// Enters the scope of the procedure which was declared last.
+    pub fn enter_proc(self) -> SymbolTable {
+        let scope = self.proc_stack.last().cloned();
+        let mut c = self.enter();
+        c.scope = scope;
+
+        c.register = Some(format!("FP"));
+        c.next_offset = 0;
+        c.register_n = 0;
+
+        c
+    }
+
+    /// Exits the current table, returning the previous
+    pub fn exit(self) -> Option<SymbolTable> {
+        log!(self.verbose, "Table attempting to exit and dereference itself. Printing table.");
+        self.log_table();
+
+        let proc_t = self.proc_stack;
+
+        match self.old_table {
+            Some(b) => {
+                // Take the enclosing scope if nothing else shares it, copy it otherwise
+                let mut old = Rc::try_unwrap(b).unwrap_or_else(|shared| (*shared).clone());
+                old.proc_stack = proc_t;
+
+                // Labels are global, so the scope goes on numbering them after this one
+                old.next_bool_temp = self.next_bool_temp;
+                old.next_if_temp = self.next_if_temp;
+                old.next_while_temp = self.next_while_temp;
+                old.next_for_temp = self.next_for_temp;
+                Some(old)
+            },
+            None => None
+        }
+    }
+
+    /// Returns the next temp variable using $(NUMBER) where NUMBER is incremented and
+    /// guarenteed to be unique.
+    pub fn temp(&mut self, s_type: SymbolType) -> Symbol {
+        let name = format!("${}", self.next_temp);
+        self.temp_named(name, s_type)
+    }
+
+    /// Returns the next temp variable using $(HINT)_(NUMBER), where the hint describes what
+    /// the temp holds for readability and NUMBER is the same counter used by temp, so the
+    /// name is still guarenteed to be unique.
+    pub fn temp_with_name(&mut self, hint: &str, s_type: SymbolType) -> Symbol {
+        let name = format!("${}_{}", hint, self.next_temp);
+        self.temp_named(name, s_type)
+    }
+
+    /// Adds a temp variable with the name in the next free register, or at the next offset
+    /// if there is none, and returns it.
+    fn temp_named(&mut self, name: String, s_type: SymbolType) -> Symbol {
+        self.next_temp += 1;
+
+        if self.next_register < self.registers {
+            let s = Symbol {
+                identifier: name,
+                symbol_type: s_type,
+                offset: 0,
+                register_n: self.next_register,
+                register: None,
+                held: true,
+                syntax: self.syntax.clone(),
+            };
+
+            self.next_register += 1;
+            self.add_symbol(s.clone());
+            return s;
+        }
+
+        // Temps are on the expression stack at R1 in every scope, so in a procedure they are
+        // above its frame instead of over its locals
+        let s = Symbol {
+            identifier: name,
+            symbol_type: s_type,
+            offset: self.next_offset as i32,
+            register_n: 1,
+            register: None,
+            held: false,
+            syntax: self.syntax.clone(),
+        };
+
+        self.next_offset += 4;
+        self.temp_high_water.set(self.temp_high_water.get().max(self.next_offset));
+
+        self.add_symbol(s.clone());
+
+        s
+    }
+
+    pub fn up_register(&mut self) {
+        let prev = (self.next_offset, self.next_temp);
+
+        self.register_n += 1;
+        self.next_offset = 0;
+        self.next_temp = 0;
+    }
+
+    pub fn bool_temp(&mut self) -> u32 {
+        self.next_bool_temp += 1;
+        self.next_bool_temp - 1
+    }
+
+    pub fn if_temp(&mut self) -> u32 {
+        self.next_if_temp += 1;
+        self.next_if_temp - 1
+    }
+
+    pub fn while_temp(&mut self) -> u32 {
+        self.next_while_temp += 1;
+        self.next_while_temp - 1
+    }
+
+    pub fn for_temp(&mut self) -> u32 {
+        self.next_for_temp += 1;
+        self.next_for_temp - 1
+    }
+
+    // pub fn down_register(&mut self) {
+    //     if self.register_n <= 0 {
+    //         panic!("<YASLC/SymbolTable> Internal error: attempted to move down a register_n when we were already at 0!");
+    //     }
+    //     self.register_n -= 1;
+    //
+    //     match self.register_saves.pop() {
+    //         Some((offset, temp)) => {
+    //             self.next_offset = offset;
+    //             self.next_temp = temp;
+    //         },
+    //         None => panic!("<YASLC/SymbolTable> Tried to move down a register_n but we did not have save data for the previous register_n!"),
+    //     }
+    // }
+
+    /// Returns the number of bytes from the register of the temps at the next offset, which
+    /// while an expression is reduced are the bytes of the expression stack it is using.
+    pub fn next_offset(&self) -> u32 {
+        self.next_offset
+    }
+
+    /// Resets the next_offset property and frees the registers held by temps.
+    pub fn reset_offset(&mut self) {
+        self.next_offset = 0;
+        self.next_register = 2;
+    }
+
+    /// Returns the number of bytes the frame of a procedure needs for the constants and
+    /// variables of the current scope, which are stored from FP.
+    pub fn frame_size(&self) -> u32 {
+        self.symbols.iter()
+            .filter(|s| !matches!(s.symbol_type, SymbolType::Procedure(_)) && s.register.as_deref() == Some("FP"))
+            .filter(|s| s.offset >= 0)
+            .map(|s| s.offset as u32 + s.size())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of bytes the constants and variables of the current scope need,
+    /// which for the root table are the globals stored from R0.
+    pub fn scope_size(&self) -> u32 {
+        self.symbols.iter()
+            .filter(|s| !matches!(s.symbol_type, SymbolType::Procedure(_)) && !s.is_temp() && s.offset >= 0)
+            .map(|s| s.offset as u32 + s.size())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the most bytes the temps of the current scope have used on the expression
+    /// stack, which is not shared with the enclosing scopes.
+    pub fn temp_high_water(&self) -> u32 {
+        self.temp_high_water.get()
+    }
+
+    pub fn current_proc(&self) -> String {
+        if self.proc_stack.len() == 0 {
+            return format!("mainblock");
+        }
+
+        return self.proc_stack[self.proc_stack.len() - 1].clone();
+    }
+
+    /// Returns the enclosing scopes and then this one, each as a line naming the scope
+    /// followed by a line for each of its symbols in the order they were added with its
+    /// identifier, type, offset and location, for example "    x int 0 +0@R0". A procedure
+    /// has no location, so it is written as "-". The elements of arrays read in an
+    /// expression are left out, they are not declared.
+    pub fn dump(&self) -> String {
+        let mut dump = match self.old_table {
+            Some(ref b) => b.dump(),
+            None => String::new(),
+        };
+
+        dump.push_str(&format!("Scope {}\n", self.scope_name()));
+
+        for s in self.symbols.iter().rev().filter(|s| !s.is_element()) {
+            let location = match s.symbol_type {
+                SymbolType::Procedure(_) => "-".to_string(),
+                _ => s.location(),
+            };
+            dump.push_str(&format!("    {} {} {} {}\n", s.identifier, s.symbol_type, s.offset, location));
+        }
+
+        dump
+    }
+
+    fn log_table(&self) {
+        if self.verbose {
+            print!("{}", self.dump());
+        }
+    }
+}
+
+
+/// A single symbol with an identifier, offset on the stack and register_n, as well as a type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    /// The identifier for this symbol.
+    identifier: String,
+
+    /// The type for this symbol.
+    pub symbol_type: SymbolType,
+
+    /// The offest for this symbol, which is negative for the parameters of a procedure.
+    offset: i32,
+
+    register: Option<String>,
+
+    /// The register_n for which to offset from for this symbol.
+    register_n: u32,
+
+    /// Whether the symbol is held in the register R(register_n) instead of memory.
+    held: bool,
+
+    /// The syntax the location of the symbol is written in.
+    syntax: Rc<Syntax>,
+}
+
+impl Symbol {
+    pub fn is_temp(&self) -> bool {
+        if self.identifier.len() == 0 {
+            println!("<YASLC/SymbolTable> Warning, found a symbol with an empty identifier. This is bad.");
+            return false;
+        }
+        self.identifier.starts_with('$')
+    }
+
+    /// Whether the symbol is an element of an array at a literal index read in an expression,
+    /// such as "a[1]", which is named after the array rather than declared.
+    pub fn is_element(&self) -> bool {
+        self.identifier.contains('[')
+    }
+
+    /// Returns the location of the symbol written in the syntax of its table.
+    pub fn location(&self) -> String {
+        let syntax = &self.syntax;
+        if self.held {
+            return syntax.register(self.register_n);
+        }
+
+        let r = match self.register {
+            Some(ref s) => syntax.register_named(s),
+            None => syntax.register(self.register_n),
+        };
+        syntax.offset(self.offset, &r)
+    }
+
+    /// Returns the location of the word the bytes past the symbol, such as a character of
+    /// a string, written in the syntax of its table.
+    pub fn location_past(&self, bytes: u32) -> String {
+        let mut s = self.clone();
+        s.offset += bytes as i32;
+        s.location()
+    }
+
+    /// Returns the name of the register the symbol is addressed from in the default syntax
+    /// and its offset from it.
+    pub fn base(&self) -> (String, i32) {
+        match self.register {
+            Some(ref r) => (r.clone(), self.offset),
+            None => (format!("R{}", self.register_n), self.offset),
+        }
+    }
+
+    /// Returns the number of bytes the symbol takes, a string is its length followed by a
+    /// word for each character it can hold, an array a word for each element and a
+    /// procedure or a string constant, which only exists at compile time, none.
+    pub fn size(&self) -> u32 {
+        match self.symbol_type {
+            SymbolType::Procedure(_) | SymbolType::Constant(SymbolValueType::Str) => 0,
+            SymbolType::Variable(SymbolValueType::Str) => 4 + 4 * STRING_CAPACITY,
+            SymbolType::Variable(SymbolValueType::Array(_, n)) => 4 * n,
+            _ => 4,
+        }
+    }
+
+    pub fn identifier(&self) -> &String {
+        &self.identifier
+    }
+
+    pub fn symbol_type(&self) -> &SymbolType {
+        &self.symbol_type
+    }
+
+    pub fn set_value_type(&mut self, v_type: SymbolValueType) {
+        self.symbol_type = match self.symbol_type {
+            SymbolType::Variable(_) => SymbolType::Variable(v_type),
+            SymbolType::Constant(_) => SymbolType::Constant(v_type),
+            SymbolType::Procedure(_) => {
+                println!("<YASLC/SymbolTable> Attempted to set value type for a procedure!");
+                return;
+            },
+        };
+    }
+}
+
+/// The type of symbol.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum SymbolType {
+    /// The symbol is a procedure with the types of its parameters in order.
+    Procedure(Vec<SymbolValueType>),
+
+    /// The symbol is a variable.
+    Variable(SymbolValueType),
+
+    /// The symbol is a constant.
+    Constant(SymbolValueType),
+}
+
+impl SymbolType {
+    /// Returns what a symbol of the type is, as in "a variable of type int".
+    pub fn description(&self) -> String {
+        match *self {
+            SymbolType::Procedure(_) => "a procedure".to_string(),
+            SymbolType::Variable(ref v) => format!("a variable of type {}", v),
+            SymbolType::Constant(ref v) => format!("a constant of type {}", v),
+        }
+    }
+}
+
+/// If the symbol type can have a value, it needs to be typed. SymbolValueType
+/// represents different primitive types within YASL.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum SymbolValueType {
+    Int,
+    Bool,
+    Char,
+    Str,
+
+    /// A real number, which is stored as the bits of a 32 bit float.
+    Real,
+
+    /// An array of the number of elements of the type, which are stored one word each.
+    Array(Box<SymbolValueType>, u32),
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.symbol_type {
+            SymbolType::Procedure(_) => write!(f, "{}: {}", self.identifier, self.symbol_type),
+            _ => write!(f, "{}: {} @ {}", self.identifier, self.symbol_type, self.location()),
+        }
+    }
+}
+
+impl fmt::Display for SymbolType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SymbolType::Procedure(_) => write!(f, "proc"),
+            SymbolType::Variable(ref v) => write!(f, "{}", v),
+            SymbolType::Constant(ref v) => write!(f, "const {}", v),
+        }
+    }
+}
+
+impl fmt::Display for SymbolValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SymbolValueType::Int => write!(f, "int"),
+            SymbolValueType::Bool => write!(f, "bool"),
+            SymbolValueType::Char => write!(f, "char"),
+            SymbolValueType::Str => write!(f, "string"),
+            SymbolValueType::Real => write!(f, "real"),
+            SymbolValueType::Array(ref t, n) => write!(f, "array[{}] of {}", n, t),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+/// parser/symbol/tests.rs
+///
+/// This file contains unit tests for the symbol table, including scoping and the
+/// symbols it generates.
+
+use super::*;
+
+/// Builds a table with the given number of nested scopes, each holding the given number of
+/// variables.
+fn nested_table(scopes: u32, symbols: u32) -> SymbolTable {
+    let mut table = SymbolTable::empty();
+    for scope in 0..scopes {
+        table = table.enter();
+        for n in 0..symbols {
+            table.add(format!("v{}_{}", scope, n), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+        }
+    }
+    table
+}
+
+#[test]
+// Checks that cloning a deeply nested table shares the enclosing scopes instead of copying them
+fn clone_shares_scopes() {
+    let table = nested_table(50, 20);
+
+    let clones: Vec<SymbolTable> = (0..1000).map(|_| table.clone()).collect();
+
+    let outer = table.old_table.as_ref().unwrap();
+    for c in clones.iter() {
+        assert!(Rc::ptr_eq(outer, c.old_table.as_ref().unwrap()));
+    }
+    assert_eq!(Rc::strong_count(outer), 1001);
+
+    // Lookups still walk the whole chain
+    assert!(clones[0].get("v0_0").is_some());
+    assert!(clones[999].get("v49_19").is_some());
+}
+
+#[test]
+// Checks that exiting a scope of a clone leaves the original table untouched
+fn exit_copies_shared_scope() {
+    let table = nested_table(3, 2).enter_proc();
+    let mut clone = table.clone();
+    clone.add("p".to_string(), SymbolType::Procedure(Vec::new())).unwrap();
+
+    let exited = clone.exit().unwrap();
+    assert_eq!(exited.current_proc(), "p");
+    assert_eq!(table.current_proc(), "mainblock");
+    assert!(table.get("p").is_none());
+    assert!(exited.get("v2_1").is_some());
+}
+
+#[test]
+// Checks that a named temp includes the hint and shares the counter with unnamed temps
+fn temp_with_name_uses_hint() {
+    let mut table = SymbolTable::empty();
+
+    let first = table.temp(SymbolType::Variable(SymbolValueType::Int));
+    let named = table.temp_with_name("sum", SymbolType::Variable(SymbolValueType::Int));
+
+    assert_eq!(first.identifier(), "$0");
+    assert_eq!(named.identifier(), "$sum_1");
+    assert!(named.is_temp());
+    assert!(table.get("$sum_1").is_some());
+    assert!(first.location() != named.location());
+}
+
+#[test]
+// Checks that the parameters of a procedure are read from its symbol in an enclosing scope
+fn parameters_of_procedure() {
+    let mut table = SymbolTable::empty();
+    table.add("p".to_string(), SymbolType::Procedure(vec![SymbolValueType::Int, SymbolValueType::Bool])).unwrap();
+    table.add("x".to_string(), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+
+    let table = table.enter_proc();
+    assert_eq!(table.parameters("p"), Some(&vec![SymbolValueType::Int, SymbolValueType::Bool]));
+    assert_eq!(table.parameters("x"), None);
+    assert_eq!(table.parameters("q"), None);
+}
+
+#[test]
+// Checks that a parameter or a symbol at a location which is already declared in the scope
+// is an error naming the symbol it would replace, as for any other symbol
+fn duplicate_parameter_and_location() {
+    let mut table = SymbolTable::empty();
+    table.add("p".to_string(), SymbolType::Procedure(vec![SymbolValueType::Int])).unwrap();
+
+    let mut table = table.enter_proc();
+    table.add_parameter("n".to_string(), SymbolValueType::Int, -8).unwrap();
+    assert_eq!(table.add_parameter("n".to_string(), SymbolValueType::Bool, -12),
+               Err("`n` is already declared in p as a variable of type int".to_string()));
+    assert_eq!(table.add_at("n".to_string(), SymbolValueType::Int, "R1", -4),
+               Err("`n` is already declared in p as a variable of type int".to_string()));
+}
+
+#[test]
+// Checks the formatted output for variables, constants and procedures
+fn symbol_display() {
+    let mut table = SymbolTable::empty();
+    table.add("n".to_string(), SymbolType::Constant(SymbolValueType::Int)).unwrap();
+    table.add("x".to_string(), SymbolType::Variable(SymbolValueType::Bool)).unwrap();
+    table.add("p".to_string(), SymbolType::Procedure(Vec::new())).unwrap();
+
+    assert_eq!(format!("{}", table.get("n").unwrap()), "n: const int @ +0@R0");
+    assert_eq!(format!("{}", table.get("x").unwrap()), "x: bool @ +4@R0");
+    assert_eq!(format!("{}", table.get("p").unwrap()), "p: proc");
+}
+
+#[test]
+// Checks that the borrowed and cloned getters find the same symbols through every scope
+fn get_and_get_cloned() {
+    let mut table = nested_table(2, 2);
+
+    let borrowed = table.get("v0_1").unwrap().location();
+    let cloned = table.get_cloned("v0_1").unwrap();
+    assert_eq!(cloned.location(), borrowed);
+
+    // The clone does not borrow the table, so it can still be changed
+    table.add("after".to_string(), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+    assert_eq!(cloned.identifier(), "v0_1");
+
+    assert!(table.get("missing").is_none());
+    assert!(table.get_cloned("missing").is_none());
+}
+
+#[test]
+// Checks that locations are written with the registers and offsets of the syntax of the table
+fn location_with_syntax() {
+    let mut table = SymbolTable::empty();
+    table.add("x".to_string(), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+    assert_eq!(table.get("x").unwrap().location(), "+0@R0");
+
+    table.set_syntax(Syntax {
+        register: "%r".to_string(),
+        stack_pointer: "%sp".to_string(),
+        offset: "".to_string(),
+        signed_offsets: false,
+        ..Syntax::default()
+    });
+    table.add("y".to_string(), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+    table.add_at("z".to_string(), SymbolValueType::Int, "SP", -4).unwrap();
+    assert_eq!(table.get("y").unwrap().location(), "4%r0");
+    assert_eq!(table.get("z").unwrap().location(), "-4%sp");
+
+    // The scopes entered after it share the syntax
+    let mut inner = table.enter();
+    inner.add("w".to_string(), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+    assert_eq!(inner.get("w").unwrap().location(), "8%r0");
+}
+
+#[test]
+// Checks that the dump lists the symbols of every scope with their offsets, including temps
+fn dump_lists_symbols() {
+    let mut table = SymbolTable::empty();
+    table.add("x".to_string(), SymbolType::Variable(SymbolValueType::Int)).unwrap();
+    table.add("y".to_string(), SymbolType::Variable(SymbolValueType::Bool)).unwrap();
+    let t = table.temp(SymbolType::Variable(SymbolValueType::Int));
+
+    let dump = table.dump();
+    assert!(dump.starts_with("Scope mainblock\n"), "{}", dump);
+    assert!(dump.contains("    x int 0 +0@R0\n"), "{}", dump);
+    assert!(dump.contains("    y bool 4 +4@R0\n"), "{}", dump);
+    assert!(dump.contains(&format!("    {} int {} {}\n", t.identifier(), t.offset, t.location())), "{}", dump);
+
+    // The enclosing scope comes before the one inside it
+    let mut inner = table.enter();
+    inner.add("z".to_string(), SymbolType::Variable(SymbolValueType::Char)).unwrap();
+    let dump = inner.dump();
+    assert!(dump.find("    x int").unwrap() < dump.find("    z char").unwrap(), "{}", dump);
+}
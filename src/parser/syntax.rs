@@ -0,0 +1,91 @@
+/// parser/syntax.rs
+///
+/// The syntax module holds the sigils used to write operands, so the textual output can be
+/// retargeted to an assembler with a different syntax. The parser and the symbol table write
+/// every operand through it, the passes which read the program back only know the default.
+
+use std::fmt;
+
+/// Syntax is the profile of how immediates, registers, offsets, constants and labels are
+/// written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Syntax {
+    /// The prefix of an immediate, "#" in "#4".
+    pub immediate: String,
+
+    /// The prefix of a numbered register, "R" in "R0".
+    pub register: String,
+
+    /// The name of the stack pointer.
+    pub stack_pointer: String,
+
+    /// The name of the frame pointer.
+    pub frame_pointer: String,
+
+    /// The separator between an offset and its register, "@" in "+4@R0".
+    pub offset: String,
+
+    /// Write positive offsets with a leading "+".
+    pub signed_offsets: bool,
+
+    /// The prefix of a constant, "^" in "^size".
+    pub constant: String,
+
+    /// The prefix of a label, "$" in "$main".
+    pub label: String,
+}
+
+impl Default for Syntax {
+    fn default() -> Syntax {
+        Syntax {
+            immediate: "#".to_string(),
+            register: "R".to_string(),
+            stack_pointer: "SP".to_string(),
+            frame_pointer: "FP".to_string(),
+            offset: "@".to_string(),
+            signed_offsets: true,
+            constant: "^".to_string(),
+            label: "$".to_string(),
+        }
+    }
+}
+
+impl Syntax {
+    /// Returns the immediate with the value.
+    pub fn immediate<T: fmt::Display>(&self, value: T) -> String {
+        format!("{}{}", self.immediate, value)
+    }
+
+    /// Returns the name of the numbered register.
+    pub fn register(&self, n: u32) -> String {
+        format!("{}{}", self.register, n)
+    }
+
+    /// Returns the location at the offset from the register, which is already written in
+    /// this syntax.
+    pub fn offset(&self, offset: i32, register: &str) -> String {
+        if self.signed_offsets && offset >= 0 {
+            format!("+{}{}{}", offset, self.offset, register)
+        } else {
+            format!("{}{}{}", offset, self.offset, register)
+        }
+    }
+
+    /// Returns the reference to the label.
+    pub fn label<T: fmt::Display>(&self, name: T) -> String {
+        format!("{}{}", self.label, name)
+    }
+
+    /// Returns the register written in the default syntax, such as "R2" or "FP", in this
+    /// syntax.
+    pub fn register_named(&self, name: &str) -> String {
+        match name {
+            "SP" => self.stack_pointer.clone(),
+            "FP" => self.frame_pointer.clone(),
+            _ => match name.strip_prefix('R').and_then(|n| n.parse::<u32>().ok()) {
+                Some(n) => self.register(n),
+                None => name.to_string(),
+            },
+        }
+    }
+}
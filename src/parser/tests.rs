@@ -0,0 +1,2854 @@
+/// parser/tests.rs
+///
+/// This file contains unit tests for the parser, compiling whole programs and checking
+/// the code generated for them.
+
+use super::*;
+use lexer::{read_string_with, preprocess, LexerOptions, LexerResult};
+use parse_tokens;
+
+/// Returns the result of compiling the source and the parser which compiled it.
+fn compile(source: &str) -> (ParserResult, Parser) {
+    compile_with(source, ParserOptions::default())
+}
+
+/// Returns the result of compiling the source using the options and the parser which
+/// compiled it.
+fn compile_with(source: &str, options: ParserOptions) -> (ParserResult, Parser) {
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    let mut parser = Parser::new_with_options(tokens, options);
+    let result = parser.compile();
+    (result, parser)
+}
+
+/// Compiles the source and panics unless it was successful.
+fn compile_ok(source: &str) -> Parser {
+    compile_ok_with(source, ParserOptions::default())
+}
+
+/// Compiles the source using the options and panics unless it was successful.
+fn compile_ok_with(source: &str, options: ParserOptions) -> Parser {
+    match compile_with(source, options) {
+        (ParserResult::Success, p) => p,
+        _ => panic!("Expected the program to compile:\n{}", source),
+    }
+}
+
+/// Returns the options with the optimization passes turned on.
+fn optimized() -> ParserOptions {
+    ParserOptions {
+        optimize: true,
+        ..ParserOptions::default()
+    }
+}
+
+/// **************************************
+/// ****** Control Flow Graph Tests ******
+/// **************************************
+
+#[test]
+// Checks the blocks and edges of the control flow graph for a program with one loop
+fn cfg_single_loop() {
+    let parser = compile_ok("
+        program demo;
+        var x : int;
+        begin
+            x = 0;
+            while x < 5 do
+                x = x + 1
+        end.");
+
+    let cfg = parser.cfg();
+
+    // Setup, main block, loop condition (split three ways by the comparison), loop body
+    // and the exit from the loop
+    assert_eq!(cfg.blocks.len(), 8);
+
+    // The end of the loop body jumps back to the loop condition
+    let top = cfg.blocks.iter().position(|b| b.label == Some("b_while0".to_string())).unwrap();
+    let exit = cfg.blocks.iter().position(|b| b.label == Some("e_while0".to_string())).unwrap();
+    assert!(cfg.edges.iter().any(|&(from, to)| to == top && from > top));
+    assert!(cfg.edges.iter().any(|&(from, to)| to == exit && from < exit));
+
+    let dot = cfg.to_dot();
+    assert_eq!(dot[0], "digraph cfg {");
+    assert_eq!(dot.iter().filter(|l| l.contains("->")).count(), cfg.edges.len());
+}
+
+/// ********************************
+/// ****** Optimization Tests ******
+/// ********************************
+
+#[test]
+// Checks that a constant moved to a temporary is used directly by the following addw
+fn optimize_propagates_into_add() {
+    let source = "
+        program demo;
+        var x : int;
+        var y : int;
+        begin
+            x = y + 5
+        end.";
+
+    let plain = compile_ok(source);
+    assert!(plain.declarations.contains(&"addw +0@R1 +4@R1".to_string()));
+
+    let optimized = compile_ok_with(source, optimized());
+    assert!(optimized.declarations.contains(&"addw #5 +4@R1".to_string()));
+    assert!(!optimized.declarations.contains(&"addw +0@R1 +4@R1".to_string()));
+}
+
+#[test]
+// Checks that optimizing only changes the text of the lines with a propagated constant,
+// every other line is emitted as it was generated
+fn optimize_keeps_unchanged_lines() {
+    let source = "
+        program demo;
+        var x : int;
+        var y : int;
+        begin
+            x = y + 5;
+            print 'a'
+        end.";
+
+    let plain = compile_ok(source);
+    let optimized = compile_ok_with(source, optimized());
+    assert_eq!(plain.declarations.len(), optimized.declarations.len());
+    assert!(optimized.declarations.contains(&"addw #16, SP".to_string()));
+
+    let changed: Vec<(&str, &str)> = plain.declarations.iter().zip(optimized.declarations.iter())
+        .filter(|&(p, o)| p != o)
+        .map(|(p, o)| (&**p, &**o))
+        .collect();
+    assert_eq!(changed, vec![("addw +0@R1 +4@R1", "addw #5 +4@R1"), ("outb +0@R1", "outb #97")]);
+}
+
+#[test]
+// Checks that constants are not propagated past a label which is jumped to
+fn optimize_stops_at_branch_target() {
+    let instructions = Instruction::from_commands(&[
+        "movw #1 +0@R1".to_string(),
+        "$loop addw +0@R1 +4@R1".to_string(),
+        "movw #2 +0@R1".to_string(),
+        "jmp $loop".to_string(),
+    ]);
+
+    let result = propagate_constants(instructions);
+    assert_eq!(format!("{}", result[1]), "$loop addw +0@R1 +4@R1");
+}
+
+#[test]
+// Checks that a variable assigned a number once folds into a later computation
+fn aggressive_folds_known_constant() {
+    let source = "
+        program demo;
+        var x : int;
+        var y : int;
+        begin
+            x = 4;
+            y = x * 2 + 1
+        end.";
+
+    let plain = compile_ok(source);
+    assert!(plain.declarations.iter().any(|c| c.starts_with("mulw")));
+
+    let options = ParserOptions {
+        aggressive: true,
+        ..ParserOptions::default()
+    };
+    let aggressive = compile_ok_with(source, options);
+    assert!(!aggressive.declarations.iter().any(|c| c.starts_with("mulw")));
+    assert!(aggressive.declarations.iter().any(|c| c.starts_with("movw #9")));
+}
+
+#[test]
+// Checks that a variable which is also assigned inside a loop is not folded
+fn aggressive_keeps_reassigned_variable() {
+    let options = ParserOptions {
+        aggressive: true,
+        ..ParserOptions::default()
+    };
+    let parser = compile_ok_with("
+        program demo;
+        var x : int;
+        var y : int;
+        begin
+            x = 4;
+            while x < 10 do
+                x = x + 1;
+            y = x * 2
+        end.", options);
+
+    assert!(parser.declarations.iter().any(|c| c.starts_with("mulw")));
+}
+
+#[test]
+// Checks that the variables of a swap are not folded into the values they were assigned
+// before it
+fn aggressive_keeps_swapped_variables() {
+    let options = ParserOptions {
+        aggressive: true,
+        ..optimized()
+    };
+    let parser = compile_ok_with("
+        program demo;
+        var x : int;
+        var y : int;
+        begin
+            x = 1;
+            y = 2;
+            swap x, y;
+            print x;
+            print y
+        end.", options);
+
+    assert_eq!(run(&parser.declarations, ""), Ok("2\n1\n".to_string()));
+}
+
+/// ***********************************
+/// ****** Expression Only Tests ******
+/// ***********************************
+
+#[test]
+// Checks that "2 + 3 * 4" compiles to a program which prints the result, which is computed
+// when it is compiled
+fn expression_only_program() {
+    let options = ParserOptions {
+        expression_only: true,
+        ..ParserOptions::default()
+    };
+    let parser = compile_ok_with("2 + 3 * 4", options);
+
+    let d = &parser.declarations;
+    assert!(!d.iter().any(|c| c.starts_with("mulw")));
+    let out = d.iter().position(|c| c.starts_with("outw")).unwrap();
+    assert_eq!(d[out + 1], "outb #10");
+    assert_eq!(d[d.len() - 1], "end");
+    assert_eq!(run(d, ""), Ok("14\n".to_string()));
+}
+
+/// *********************************
+/// ****** Shared String Tests ******
+/// *********************************
+
+#[test]
+// Checks that a string printed twice is only output by one routine when strings are shared
+fn shared_string_printed_twice() {
+    let source = "
+        program demo;
+        begin
+            print \"foo\";
+            print \"bar\";
+            print \"foo\"
+        end.";
+
+    let count = |p: &Parser, c: &str| p.declarations.iter().filter(|d| d.ends_with(c)).count();
+
+    let plain = compile_ok(source);
+    assert_eq!(count(&plain, "outb #102"), 2);
+
+    let options = ParserOptions {
+        share_strings: true,
+        ..ParserOptions::default()
+    };
+    let shared = compile_ok_with(source, options);
+    assert_eq!(count(&shared, "outb #102"), 1);
+    assert_eq!(count(&shared, "call #0 $string_0"), 2);
+    assert_eq!(count(&shared, "call #0 $string_1"), 1);
+    assert!(shared.declarations.contains(&"$string_0 outb #102".to_string()));
+
+    // The routines come after the end of the program
+    let end = shared.declarations.iter().position(|d| d == "end").unwrap();
+    let routine = shared.declarations.iter().position(|d| d.starts_with("$string_0")).unwrap();
+    assert!(end < routine);
+}
+
+/// *******************************
+/// ****** Program Arguments ******
+/// *******************************
+
+#[test]
+// Checks that a program can print its argument count, which is read from the first word
+// of the stack, and that its own variables are placed after it
+fn arguments_echo_count() {
+    let source = "
+        program echo;
+        var x : int;
+        begin
+            print argc
+        end.";
+
+    let options = ParserOptions {
+        arguments: true,
+        ..ParserOptions::default()
+    };
+    let parser = compile_ok_with(source, options);
+
+    let d = &parser.declarations;
+    assert!(d.contains(&"movw #0 +4@R0".to_string()));
+    assert!(!d.contains(&"movw #0 +0@R0".to_string()));
+    assert!(d.contains(&"addw #8, SP".to_string()));
+    assert!(d.iter().any(|c| c.starts_with("outw +0@R0")));
+}
+
+/// ***************************
+/// ****** Comment Trace ******
+/// ***************************
+
+#[test]
+// Checks the comment trace of a program with an assignment and a print
+fn comment_trace_two_statements() {
+    let parser = compile_ok("
+        program demo;
+        var x : int;
+        begin
+            x = 1 + 2;
+            print x
+        end.");
+
+    assert_eq!(parser.comment_trace(), vec![
+        ": Initialize junk variable and setup the stack",
+        ": Jump to block mainblock of execution",
+        ": Block mainblock",
+        ": expression: 1 + 2",
+        ": expression: x",
+    ]);
+}
+
+/// *************************
+/// ****** Strict Mode ******
+/// *************************
+
+/// Returns the options requiring blocks for control structure bodies.
+fn strict() -> ParserOptions {
+    ParserOptions {
+        strict: true,
+        ..ParserOptions::default()
+    }
+}
+
+#[test]
+// Checks that a bare if body is only accepted outside of strict mode
+fn strict_rejects_bare_if_body() {
+    let source = "
+        program demo;
+        var x : int;
+        begin
+            if x == 0 then
+                x = 1
+        end.";
+
+    compile_ok(source);
+
+    let (result, p) = compile_with(source, strict());
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors().len(), 1, "{:?}", p.errors());
+    assert!(p.errors()[0].starts_with("(6, 17)"), "{:?}", p.errors());
+    assert!(p.errors()[0].contains("Expected `begin` for the body of a control structure in strict mode but found `x`"), "{:?}", p.errors());
+}
+
+#[test]
+// Checks that if, else and while bodies in blocks are accepted in strict mode
+fn strict_accepts_block_bodies() {
+    compile_ok_with("
+        program demo;
+        var x : int;
+        begin
+            if x == 0 then
+            begin
+                x = 1
+            end
+            else
+            begin
+                while x < 5 do
+                begin
+                    x = x + 1
+                end
+            end
+        end.", strict());
+}
+
+/// **********************
+/// ****** Warnings ******
+/// **********************
+
+#[test]
+// Checks that a procedure variable shadowing a global is only warned about when asked
+fn warn_shadowed_global() {
+    let source = "
+        program demo;
+        var x : int;
+        proc foo;
+            var x : int;
+            begin
+                x = 1
+            end;
+        begin
+            foo
+        end.";
+
+    assert!(compile_ok(source).warnings().is_empty());
+
+    let options = ParserOptions {
+        warn_shadowing: true,
+        ..ParserOptions::default()
+    };
+    let parser = compile_ok_with(source, options);
+    assert_eq!(parser.warnings().len(), 1);
+    assert!(parser.warnings()[0].ends_with("x in foo shadows a declaration in mainblock."));
+}
+
+#[test]
+// Checks that the shadowing warning names the scope of the parameter or local and the
+// nearest enclosing scope it shadows
+fn warn_shadowed_enclosing_scopes() {
+    let source = "
+        program demo;
+        var x : int;
+        proc foo(y : int);
+            var z : int;
+            proc bar(x : int);
+                var z : int;
+                begin
+                    print x + z
+                end;
+            begin
+                z = y;
+                bar(z)
+            end;
+        begin
+            x = 2;
+            foo(x)
+        end.";
+
+    let parser = compile_ok_with(source, ParserOptions { warn_shadowing: true, ..ParserOptions::default() });
+    let warnings = parser.warnings();
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].ends_with("x in bar shadows a declaration in mainblock."));
+    assert!(warnings[1].ends_with("z in bar shadows a declaration in foo."));
+}
+
+/// *************************************
+/// ****** Conditional Compilation ******
+/// *************************************
+
+/// Returns the tokens of the source with the directives evaluated for the defines.
+fn preprocessed(source: &str, defines: &[&str]) -> Result<Vec<Token>, String> {
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    let defines: Vec<String> = defines.iter().map(|d| d.to_string()).collect();
+    preprocess(tokens, &defines)
+}
+
+#[test]
+// Checks that a define changes which branch of an #if is compiled
+fn conditional_compilation_define() {
+    let source = "
+        program demo;
+        begin
+        #if DEBUG
+            print \"debug\"
+        #else
+            print \"release\"
+        #endif
+        end.";
+
+    let count = |defines: &[&str], c: &str| {
+        let tokens = preprocessed(source, defines).unwrap();
+        let mut parser = Parser::new_with_tokens(tokens);
+        match parser.compile() {
+            ParserResult::Success => {},
+            _ => panic!("Expected the program to compile"),
+        };
+        parser.declarations.iter().filter(|d| d.ends_with(c)).count()
+    };
+
+    // "d" is 100 and "r" is 114
+    assert_eq!(count(&["DEBUG"], "outb #100"), 1);
+    assert_eq!(count(&["DEBUG"], "outb #114"), 0);
+    assert_eq!(count(&[], "outb #100"), 0);
+    assert_eq!(count(&[], "outb #114"), 1);
+}
+
+#[test]
+// Checks that parse_tokens evaluates the directives of the tokens it is given
+fn parse_tokens_preprocesses() {
+    let tokens = |source: &str| match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    let source = "program demo; begin #if DEBUG print 1 #else print 2 #endif end.";
+    assert!(matches!(parse_tokens(tokens(source)), ParserResult::Success));
+
+    let source = "program demo; begin #if DEBUG print 1 end.";
+    assert!(matches!(parse_tokens(tokens(source)), ParserResult::Unexpected));
+}
+
+/// ************************
+/// ****** Robustness ******
+/// ************************
+
+/// Returns a token chosen from a pool of every token type by the random number, including
+/// some which the lexer would never produce.
+fn random_token(n: u64) -> Token {
+    let pool: [(&str, TokenType); 36] = [
+        ("x", TokenType::Identifier),
+        ("y", TokenType::Identifier),
+        ("foo", TokenType::Identifier),
+        ("\u{e9}t\u{e9}", TokenType::Identifier),
+        ("", TokenType::Identifier),
+        ("0", TokenType::Number),
+        ("42", TokenType::Number),
+        ("99999999999", TokenType::Number),
+        ("\"hi\"", TokenType::String),
+        ("", TokenType::String),
+        ("#if", TokenType::Directive),
+        (";", TokenType::Semicolon),
+        (":", TokenType::Colon),
+        (".", TokenType::Period),
+        (",", TokenType::Comma),
+        ("(", TokenType::LeftParen),
+        (")", TokenType::RightParen),
+        ("+", TokenType::Plus),
+        ("-", TokenType::Minus),
+        ("*", TokenType::Star),
+        ("=", TokenType::Assign),
+        ("<", TokenType::LessThan),
+        ("==", TokenType::EqualTo),
+        ("?", TokenType::Invalid),
+        ("program", TokenType::Keyword(KeywordType::Program)),
+        ("const", TokenType::Keyword(KeywordType::Const)),
+        ("var", TokenType::Keyword(KeywordType::Var)),
+        ("int", TokenType::Keyword(KeywordType::Int)),
+        ("bool", TokenType::Keyword(KeywordType::Bool)),
+        ("proc", TokenType::Keyword(KeywordType::Proc)),
+        ("begin", TokenType::Keyword(KeywordType::Begin)),
+        ("end", TokenType::Keyword(KeywordType::End)),
+        ("if", TokenType::Keyword(KeywordType::If)),
+        ("while", TokenType::Keyword(KeywordType::While)),
+        ("print", TokenType::Keyword(KeywordType::Print)),
+        ("true", TokenType::Keyword(KeywordType::True)),
+    ];
+
+    let (lexeme, ref t_type) = pool[(n % pool.len() as u64) as usize];
+    Token::new_with(1, 1, lexeme.to_string(), t_type.clone())
+}
+
+#[test]
+// Feeds random token vectors to the parser, which must return a result instead of panicking
+fn parse_tokens_never_panics() {
+    let prefix = "program demo; var x : int; begin";
+
+    // A xorshift generator so the test is the same on every run
+    let mut state: u64 = 0x2545F4914F6CDD1D;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for run in 0..2000 {
+        // Start half of the inputs with a valid program header to reach deeper rules
+        let mut tokens = match read_string_with(prefix.to_string(), &LexerOptions::default()) {
+            LexerResult::Ok(t) if run % 2 == 0 => t,
+            _ => Vec::<Token>::new(),
+        };
+
+        let length = next() % 40;
+        for _ in 0..length {
+            tokens.push(random_token(next()));
+        }
+
+        let _ = parse_tokens(tokens);
+    }
+}
+
+/// ************************
+/// ****** Debug Info ******
+/// ************************
+
+#[test]
+// Checks that the debug table has the location and type of globals and procedure locals
+fn debug_info_locations() {
+    let options = ParserOptions {
+        debug_info: true,
+        ..ParserOptions::default()
+    };
+    let parser = compile_ok_with("
+        program demo;
+        const n = 3;
+        var x : int;
+        var b : bool;
+        proc foo;
+            var y : int;
+            begin
+                y = x + 1
+            end;
+        begin
+            foo
+        end.", options);
+
+    let d = &parser.debug_info;
+    assert!(d.contains(&"mainblock n const int +0@R0".to_string()));
+    assert!(d.contains(&"mainblock x var int +4@R0".to_string()));
+    assert!(d.contains(&"mainblock b var bool +8@R0".to_string()));
+    assert!(d.contains(&"foo y var int +0@FP".to_string()));
+    assert_eq!(d.len(), 4);
+}
+
+/// *********************************
+/// ****** Stray Keyword Tests ******
+/// *********************************
+
+/// Compiles the main block and returns the errors, which must stop compilation.
+fn block_errors(block: &str) -> Vec<String> {
+    let source = format!("
+        program demo;
+        var x : int;
+        begin
+            {}
+        end.", block);
+
+    match compile(&source) {
+        (ParserResult::Success, _) => panic!("Expected the program to fail:\n{}", source),
+        (_, p) => p.errors().to_vec(),
+    }
+}
+
+#[test]
+// Checks that a do outside of a while is diagnosed
+fn stray_do() {
+    let errors = block_errors("x = 1; do x = 2");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].ends_with("`do` without matching `while`"));
+}
+
+#[test]
+// Checks that a then outside of an if is diagnosed
+fn stray_then() {
+    let errors = block_errors("then x = 2");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].ends_with("`then` without matching `if`"));
+}
+
+#[test]
+// Checks that an else after an if which already ended is diagnosed, with its location
+fn stray_else() {
+    let errors = block_errors("if x == 0 then x = 1; else x = 2");
+    assert_eq!(errors, vec!["(5, 35) `else` without matching `if`"]);
+
+    let errors = block_errors("x = 1 else x = 2");
+    assert!(errors[0].ends_with("`else` without matching `if`"));
+}
+
+/// *************************************
+/// ****** Dead Branch Elimination ******
+/// *************************************
+
+#[test]
+// Checks that only the taken branch of an if with a constant condition emits code
+fn constant_condition_false_branch() {
+    let source = "
+        program demo;
+        const debug = false;
+        var x : int;
+        begin
+            if debug then
+                print \"debugging\"
+            else
+                x = 7;
+            x = x + 1
+        end.";
+
+    let count = |p: &Parser, c: &str| p.declarations.iter().filter(|d| d.contains(c)).count();
+
+    let plain = compile_ok(source);
+    assert_eq!(count(&plain, "outb"), 10);
+    assert_eq!(count(&plain, "if_else"), 2);
+
+    let optimized = compile_ok_with(source, optimized());
+    assert_eq!(count(&optimized, "outb"), 0);
+    assert_eq!(count(&optimized, "if_else"), 0);
+    assert_eq!(count(&optimized, "movw #7 +4@R0"), 1);
+}
+
+#[test]
+// Checks that a bool constant, in either case, has the bool type and its value when it is
+// combined with variables in a condition which is not known at compile time
+fn bool_constant_in_condition() {
+    let source = "
+        program demo;
+        const flag = true;
+        const quiet = FALSE;
+        var x : int;
+        var b : bool;
+        begin
+            x = 2;
+            if flag and x > 1 then print 1 else print 0;
+            if quiet or x == 0 then print 2 else print 3;
+            b = not flag;
+            if b then print 4 else print 5
+        end.";
+
+    let p = compile_ok(source);
+    assert!(p.declarations.contains(&"movw #1 +0@R0".to_string()));
+    assert!(p.declarations.contains(&"movw #0 +4@R0".to_string()));
+    assert_eq!(run(&p.declarations, "").unwrap(), "1\n3\n5\n");
+    assert_eq!(run(&compile_ok_with(source, optimized()).declarations, "").unwrap(), "1\n3\n5\n");
+
+    let (result, _) = compile("program demo; const flag = true; var x : int; begin x = flag + 1 end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+}
+
+#[test]
+// Checks that conditions which fold to a constant are warned about when asked for, with or
+// without optimizations, and that a while which never runs emits no code when optimizing
+fn constant_condition_warnings() {
+    let source = "
+        program demo;
+        const limit = 3;
+        var x : int;
+        begin
+            if limit * 2 > 5 then x = 1;
+            while not true or limit < 0 do x = x + 1;
+            while 1 == 1 do x = x - 1;
+            if x > limit then x = 0
+        end.";
+
+    let warn = ParserOptions { warn_constant_conditions: true, ..ParserOptions::default() };
+    assert!(compile_ok(source).warnings().is_empty());
+
+    let p = compile_ok_with(source, warn.clone());
+    assert_eq!(p.warnings(), &[
+        "(6, 16) The condition of the `if` is always true".to_string(),
+        "(7, 19) The condition of the `while` is always false, so its body never runs".to_string(),
+        "(8, 19) The condition of the `while` is always true, so it never ends".to_string(),
+    ]);
+    let loops = |p: &Parser| p.declarations.iter().filter(|d| d.starts_with("$b_while")).count();
+    assert_eq!(loops(&p), 2);
+
+    let optimized = compile_ok_with(source, ParserOptions { optimize: true, ..warn });
+    assert_eq!(optimized.warnings().len(), 3);
+    assert_eq!(loops(&optimized), 1);
+}
+
+/// *************************************
+/// ******** Register Allocation ********
+/// *************************************
+
+#[test]
+// Checks that with more registers the temporaries of an expression are held in them
+fn registers_hold_temporaries() {
+    let source = "
+        program demo;
+        var x : int;
+        var y : int;
+        begin
+            x = y * 2 + 5
+        end.";
+
+    let options = ParserOptions {
+        registers: 4,
+        ..ParserOptions::default()
+    };
+    let p = compile_ok_with(source, options);
+    assert!(p.declarations.contains(&"mulw R2 R3".to_string()));
+    assert!(p.declarations.contains(&"movw R3 +0@R0".to_string()));
+
+    // Only R0 and R1 are used by default
+    let plain = compile_ok(source);
+    assert!(!plain.declarations.iter().any(|d| d.contains("R2")));
+}
+
+/// *************************************
+/// ********** Instruction Set **********
+/// *************************************
+
+/// The instructions of a target without multiply.
+fn no_multiply() -> ParserOptions {
+    let set = ["movw", "addw", "subw", "divw", "cmpw", "beq", "jmp", "outw", "outb", "inb", "end"];
+    ParserOptions {
+        instruction_set: Some(set.iter().map(|s| s.to_string()).collect()),
+        ..ParserOptions::default()
+    }
+}
+
+#[test]
+// Checks that a program which multiplies fails on a target without mulw
+fn instruction_set_rejects_multiply() {
+    let source = "
+        program demo;
+        var x : int;
+        begin
+            x = x * 3
+        end.";
+
+    let (result, p) = compile_with(source, no_multiply());
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["The target does not support the instruction `mulw`".to_string()]);
+
+    let (result, _) = compile_with("program demo; var x : int; begin x = x + 3 end.", no_multiply());
+    assert!(matches!(result, ParserResult::Success));
+}
+
+/// *************************************
+/// ********** Indented Listing *********
+/// *************************************
+
+#[test]
+// Checks that the indented listing puts labels on their own lines and separates blocks
+fn indented_listing_loop() {
+    let source = "
+        program demo;
+        var x : int;
+        begin
+            while x < 3 do
+                x = x + 1
+        end.";
+
+    let flat = compile_ok(source).listing();
+    assert!(flat.contains(&"$b_while0 movw SP R1".to_string()));
+    assert!(!flat.iter().any(|l| l.starts_with("    ")));
+
+    let indent = compile_ok_with(source, ParserOptions { indent: true, ..ParserOptions::default() }).listing();
+    let head = indent.iter().position(|l| l == "$b_while0").unwrap();
+    assert_eq!(indent[head - 1], "");
+    assert_eq!(indent[head + 1], "    movw SP R1");
+
+    // The loop ends its block and the exit label starts a new one
+    let exit = indent.iter().position(|l| l == "$e_while0").unwrap();
+    assert_eq!(indent[exit - 2], "    jmp $b_while0");
+    assert_eq!(indent[exit - 1], "");
+    assert_eq!(indent[exit + 1], "    inb $junk");
+}
+
+/// *************************************
+/// ********* Unassigned Reads **********
+/// *************************************
+
+/// Returns the options which warn about reads before assignments.
+fn warn_unassigned() -> ParserOptions {
+    ParserOptions {
+        warn_unassigned: true,
+        ..ParserOptions::default()
+    }
+}
+
+#[test]
+// Checks that reading a variable which is never assigned warns once where it is read
+fn unassigned_variable_read() {
+    let source = "
+        program demo;
+        var x : int;
+        var y : int;
+        begin
+            y = 1;
+            print x + y;
+            print x
+        end.";
+
+    let p = compile_ok_with(source, warn_unassigned());
+    assert_eq!(p.warnings(), &["(7, 19) `x` is read before it is assigned".to_string()]);
+}
+
+#[test]
+// Checks that an assignment in only one branch of an if or inside a loop does not count
+fn unassigned_after_branches() {
+    let source = "
+        program demo;
+        var x : int;
+        var y : int;
+        var z : int;
+        begin
+            if true then x = 1 else x = 2;
+            if true then y = 1;
+            while false do z = 1;
+            print x;
+            print y;
+            print z
+        end.";
+
+    let p = compile_ok_with(source, warn_unassigned());
+    let warned: Vec<&str> = p.warnings().iter()
+        .map(|w| w.split('`').nth(1).unwrap())
+        .collect();
+    assert_eq!(warned, vec!["y", "z"]);
+}
+
+/// *************************************
+/// ************ Inline Asm *************
+/// *************************************
+
+#[test]
+// Checks that the text of an asm statement appears verbatim and marked in the program
+fn asm_passthrough() {
+    let source = "
+        program demo;
+        var x : int;
+        begin
+            x = 1;
+            asm \"addw #41 +0@R0\";
+            print x
+        end.";
+
+    let p = compile_ok(source);
+    let lines: Vec<&str> = p.declarations.iter().flat_map(|d| d.split('\n')).collect();
+    let n = lines.iter().position(|l| *l == "addw #41 +0@R0").unwrap();
+    assert_eq!(lines[n - 1], ": inline asm");
+
+    let (result, p) = compile("program demo; begin asm \"  \" end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 25) `asm` needs an instruction to insert".to_string()]);
+}
+
+/// *************************************
+/// ********** Section Ordering *********
+/// *************************************
+
+#[test]
+// Checks that a var after a proc reports that variables come before procedures
+fn var_after_proc() {
+    let source = "
+        program demo;
+        proc p;
+        begin
+            print 1
+        end;
+        var x : int;
+        begin
+            x = 1
+        end.";
+
+    let (result, p) = compile(source);
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(7, 9) Variable declarations must come before procedures".to_string()]);
+
+    let (_, p) = compile("program demo; var x : int; const c = 1; begin x = c end.");
+    assert_eq!(p.errors(), &["(1, 28) Constant declarations must come before variable declarations".to_string()]);
+}
+
+/// *************************************
+/// ********** Source Listing ***********
+/// *************************************
+
+#[test]
+// Checks that a source line appears as a comment right above the code generated for it
+fn source_listing_line_above_code() {
+    let source = "program demo;
+        var x : int;
+        begin
+            x = 4;
+            print x * 2
+        end.";
+
+    let tokens = match read_string_with(source.to_string(), &LexerOptions::default()) {
+        LexerResult::Ok(t) => t,
+        LexerResult::Err(_) => panic!("Unable to read the test source:\n{}", source),
+    };
+
+    let mut p = Parser::new_with_options(tokens, ParserOptions { source_listing: true, ..ParserOptions::default() });
+    p.set_source(source);
+    assert!(matches!(p.compile(), ParserResult::Success));
+
+    let lines: Vec<&str> = p.declarations.iter().flat_map(|d| d.split('\n')).collect();
+    let n = lines.iter().position(|l| *l == ": 5: print x * 2").unwrap();
+    assert_eq!(lines[n + 1..].iter().find(|l| !l.is_empty()), Some(&"movw SP R1"));
+    assert!(lines[n + 1..].iter().take_while(|l| !l.starts_with("outw")).any(|l| l.starts_with("mulw")));
+    assert!(lines[..n].iter().any(|l| *l == ": 4: x = 4;"));
+}
+
+#[test]
+// Checks that the line and column of a statement are written as a comment right above the
+// code generated for it without needing the source
+fn line_comments_above_statement() {
+    let source = "program demo;
+        var x : int;
+        begin
+            print 1;
+            x = 4
+        end.";
+
+    let p = compile_ok_with(source, ParserOptions { line_comments: true, ..ParserOptions::default() });
+    let n = p.declarations.iter().position(|l| l == ": line 5, column 13").unwrap();
+    let code: Vec<&String> = p.declarations[n + 1..].iter().filter(|l| !l.is_empty() && !l.starts_with(':')).collect();
+    assert_eq!(code[..3], ["movw SP R1", "movw #4 +0@R1", "movw +0@R1 +0@R0"]);
+    assert!(p.declarations[..n].iter().any(|l| l == ": line 4, column 13"));
+    assert_eq!(run(&p.declarations, ""), Ok("1\n".to_string()));
+
+    assert!(!compile_ok(source).declarations.iter().any(|l| l.starts_with(": line")));
+}
+
+/// *************************************
+/// ********** Write Statement **********
+/// *************************************
+
+#[test]
+// Checks that write outputs a string or expression like print but without the newline
+fn write_omits_newline() {
+    let newlines = |source: &str| {
+        compile_ok(source).declarations.iter()
+            .flat_map(|d| d.split('\n').map(|l| l.to_string()).collect::<Vec<String>>())
+            .filter(|l| l.ends_with("outb #10"))
+            .count()
+    };
+
+    assert_eq!(newlines("program demo; begin print \"a\"; print 1 end."), 2);
+    assert_eq!(newlines("program demo; begin write \"a\"; write 1 end."), 0);
+    assert_eq!(newlines("program demo; begin write \"a\"; print 1 end."), 1);
+}
+
+/// *************************************
+/// *********** JSON IR Output **********
+/// *************************************
+
+#[test]
+// Checks that writing the program as JSON and reading it back emits the same lines as
+// emitting the program directly
+fn ir_json_round_trip() {
+    let source = "
+        program demo;
+        const k = 3;
+        var x : int;
+        var b : bool;
+        proc show;
+        begin
+            print \"x is big\"
+        end;
+        begin
+            x = k * 2;
+            b = x > 5;
+            while b do
+            begin
+                x = x - 1;
+                b = x > 5
+            end;
+            show;
+            print x
+        end.";
+
+    let p = compile_ok(source);
+
+    let json = p.to_ir_json();
+    assert!(json.starts_with("[\n  {\"kind\": "));
+    assert!(json.contains("\"mnemonic\": \"movw\""));
+
+    // Each line comes back as it was emitted, up to the spacing and the comma which may
+    // separate the operands
+    let emitted = from_ir_json(&json).unwrap();
+    let words = |l: &str| l.replace(',', " ").split_whitespace().map(|w| w.to_string()).collect::<Vec<String>>();
+    let lines: Vec<&str> = p.declarations.iter().flat_map(|c| c.split('\n')).collect();
+    assert_eq!(emitted.len(), lines.len());
+    for (e, l) in emitted.iter().zip(lines.iter()) {
+        assert_eq!(words(e), words(l));
+    }
+    assert_eq!(run(&emitted, ""), run(&p.declarations, ""));
+
+    assert!(from_ir_json("[{\"kind\": \"jump\"}]").is_err());
+    assert!(from_ir_json("[{\"kind\": \"op\"").is_err());
+}
+
+/// *************************************
+/// ********** Boolean Printing *********
+/// *************************************
+
+#[test]
+// Checks that a boolean expression prints as text when print_bools is set and as a number
+// otherwise, while integers always print as numbers
+fn print_bool_as_text() {
+    let source = "
+        program demo;
+        var x : int;
+        begin
+            x = 3;
+            print x > 2;
+            print x < 2;
+            print x
+        end.";
+
+    let options = ParserOptions {
+        print_bools: true,
+        ..ParserOptions::default()
+    };
+    let p = compile_ok_with(source, options);
+    assert_eq!(run(&p.declarations, ""), Ok("true\nfalse\n3\n".to_string()));
+
+    let p = compile_ok(source);
+    assert_eq!(run(&p.declarations, ""), Ok("1\n0\n3\n".to_string()));
+}
+
+/// *************************************
+/// ********** Constant Chains **********
+/// *************************************
+
+/// Returns a program declaring a chain of constants, each defined as the one before it,
+/// which prints the last one.
+fn constant_chain(length: usize) -> String {
+    let mut source = "program demo; const c0 = 7;".to_string();
+    for n in 1..length {
+        source.push_str(&format!(" const c{} = c{};", n, n - 1));
+    }
+    source.push_str(&format!(" begin print c{} end.", length - 1));
+    source
+}
+
+#[test]
+// Checks that a constant can be defined through other constants up to the depth limit
+fn constant_chain_depth_limit() {
+    let p = compile_ok(&constant_chain(4));
+    assert_eq!(run(&p.declarations, ""), Ok("7\n".to_string()));
+
+    let (result, p) = compile(&constant_chain(70));
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors().len(), 1);
+    assert!(p.errors()[0].ends_with("Constant `c64` is defined through more than 64 constants"));
+
+    let options = ParserOptions {
+        max_constant_depth: 100,
+        ..ParserOptions::default()
+    };
+    compile_ok_with(&constant_chain(70), options);
+
+    let (result, p) = compile("program demo; const k = x; begin end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 25) `x` is not a constant".to_string()]);
+}
+
+#[test]
+// Checks that a constant can be defined by an expression of literals and other constants,
+// which is folded into its value
+fn constant_expressions() {
+    let p = compile_ok("
+        program demo;
+        const x = 2 * 3 + 1;
+        const y = (x + 1) div -2;
+        const big = x > 5 and true;
+        begin
+            print x;
+            print y;
+            if big then print 1
+        end.");
+    assert!(p.declarations.contains(&"movw #7 +0@R0".to_string()), "{:?}", p.declarations);
+    assert!(p.declarations.contains(&"movw #-4 +4@R0".to_string()), "{:?}", p.declarations);
+    assert!(p.declarations.contains(&"movw #1 +8@R0".to_string()), "{:?}", p.declarations);
+    assert_eq!(run(&p.declarations, "").unwrap(), "7\n-4\n1\n");
+
+    let (result, p) = compile("program demo; const a = 1; const k = a + b; begin end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 42) `b` is not a constant".to_string()]);
+
+    let (result, p) = compile("program demo; const k = 1 + true; begin end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 25) The value of constant `k` can not be computed at compile time".to_string()]);
+}
+
+/// *************************************
+/// ********** Procedure Frames *********
+/// *************************************
+
+#[test]
+// Checks that a procedure moves the stack pointer past its locals for the call and back
+// before it returns, so every call of a recursive procedure has its own locals
+fn procedure_frame_size() {
+    let source = "
+        program demo;
+        var x : int;
+        proc down;
+        var n : int;
+        var m : int;
+        begin
+            n = x;
+            x = x - 1;
+            if x > 0 then down;
+            print n
+        end;
+        begin
+            x = 3;
+            down
+        end.";
+
+    let p = compile_ok(source);
+    let lines: Vec<&str> = p.declarations.iter().flat_map(|d| d.split('\n')).collect();
+    let start = lines.iter().position(|l| *l == "$down movw FP +0@SP").unwrap();
+    assert_eq!(lines[start + 1..start + 6], ["addw #4 SP", "movw SP FP", "movw #0 +0@FP", "movw #0 +4@FP", "addw #8 SP"]);
+
+    let end = lines.iter().position(|l| *l == "ret").unwrap();
+    assert_eq!(lines[end - 3..end], ["subw #8 SP", "subw #4 SP", "movw +0@SP FP"]);
+
+    assert_eq!(run(&p.declarations, ""), Ok("1\n2\n3\n".to_string()));
+    assert_eq!(run(&compile_ok_with(source, optimized()).declarations, ""), Ok("1\n2\n3\n".to_string()));
+}
+
+/// *************************************
+/// ********** Procedure Arguments **********
+/// *************************************
+
+#[test]
+// Checks that each argument is pushed before the call, read below the frame pointer of the
+// procedure and popped after it returns
+fn procedure_arguments() {
+    let source = "
+        program demo;
+        var x : int;
+        proc add(a : int, b : int);
+        begin
+            print a - b
+        end;
+        begin
+            x = 7;
+            add(x + 3, 4);
+            add(1, x * 2)
+        end.";
+
+    let p = compile_ok(source);
+    let lines: Vec<&str> = p.declarations.iter().flat_map(|d| d.split('\n')).collect();
+    assert!(lines.contains(&"movw -12@FP +0@R1"));
+    assert!(lines.contains(&"subw -8@FP +0@R1"));
+
+    let call = lines.iter().position(|l| *l == "call #8 $add").unwrap();
+    assert_eq!(lines[call - 2..call + 2], ["movw +0@R1 +0@SP", "addw #4 SP", "call #8 $add", "subw #8 SP"]);
+
+    assert_eq!(run(&p.declarations, ""), Ok("6\n-13\n".to_string()));
+
+    // Each recursive call has its own argument
+    let source = "
+        program demo;
+        proc down(n : int);
+        begin
+            if n > 0 then begin print n; down(n - 1) end
+        end;
+        begin
+            down(3)
+        end.";
+    assert_eq!(run(&compile_ok(source).declarations, ""), Ok("3\n2\n1\n".to_string()));
+
+    let (result, p) = compile("program demo; proc p(a : int, a : int); begin print a end; begin p(1, 2) end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors().len(), 1, "{:?}", p.errors());
+    assert!(p.errors()[0].ends_with("(1, 31) `a` is already declared in p as a variable of type int"), "{:?}", p.errors());
+}
+
+#[test]
+// Checks that the arguments of a call must match the parameters of the procedure in number
+// and type, naming the procedure when they do not
+fn procedure_argument_types() {
+    let declarations = "
+        program demo;
+        var x : int;
+        var b : bool;
+        proc show(n : int, flag : bool);
+        begin
+            if flag then print n
+        end;
+        begin
+            ";
+
+    let source = format!("{}show(x + 1, true); show(2, b and true) end.", declarations);
+    assert_eq!(run(&compile_ok(&source).declarations, ""), Ok("1\n".to_string()));
+
+    let errors = |calls: &str| {
+        let (result, p) = compile(&format!("{}{} end.", declarations, calls));
+        assert!(matches!(result, ParserResult::Unexpected));
+        p.errors().to_vec()
+    };
+
+    let e = errors("show(1)");
+    assert!(e[0].contains("`show` takes 2 argument(s) but is given 1"), "{:?}", e);
+
+    let e = errors("show");
+    assert!(e[0].contains("`show` takes 2 argument(s) but is given 0"), "{:?}", e);
+
+    let e = errors("x = 1; show(b, x)");
+    assert!(e[0].contains("Argument 1 of `show` is bool but the parameter is int"), "{:?}", e);
+}
+
+/// *************************************
+/// ********** Elsif Chains **********
+/// *************************************
+
+#[test]
+// Checks that an if with two elsif arms and an else runs one arm and shares one end label
+fn elsif_chain() {
+    let p = compile_ok("
+        program demo;
+        var i : int;
+        begin
+            for i = 1 to 4 do
+                if i == 1 then print 10
+                elsif i == 2 then print 20
+                elsif i == 3 then print 30
+                else print 40
+        end.");
+    assert_eq!(run(&p.declarations, ""), Ok("10\n20\n30\n40\n".to_string()));
+
+    let labels: Vec<&String> = p.declarations.iter().filter(|c| c.starts_with("$end_if")).collect();
+    assert_eq!(labels.len(), 1);
+    let jumps = p.declarations.iter().filter(|c| c.as_str() == "jmp $end_if0").count();
+    assert_eq!(jumps, 3);
+
+    // A constant condition keeps only the arm which is taken
+    let p = compile_ok_with("program demo; begin if false then print 1 elsif true then print 2 else print 3 end.", optimized());
+    assert_eq!(run(&p.declarations, ""), Ok("2\n".to_string()));
+
+    let (result, p) = compile("program demo; begin print 1; elsif true then print 2 end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert!(p.errors()[0].contains("`elsif` without matching `if`"), "{:?}", p.errors());
+}
+
+/// *************************************
+/// ********** Short Circuit **********
+/// *************************************
+
+#[test]
+// Checks the values of and/or which skip their second operand, with several in a program
+fn short_circuit_values() {
+    let p = compile_ok("
+        program demo;
+        var a : bool;
+        var b : bool;
+        var x : int;
+        begin
+            a = true;
+            b = false;
+            x = 5;
+            print a or b and x > 9;
+            print b and (x > 3 or a);
+            print (a or b) and (b or x > 3);
+            print not a or (b and x < 9)
+        end.");
+    assert_eq!(run(&p.declarations, ""), Ok("1\n0\n1\n0\n".to_string()));
+}
+
+#[test]
+// Checks that a function on the right of and/or is only called when the left does not
+// decide the value, which its side effect on a global shows
+fn short_circuit_skips_call() {
+    let source = "
+        program demo;
+        var a : bool;
+        var n : int;
+        proc f : bool;
+        begin
+            n = n + 1;
+            return true
+        end;
+        begin
+            a = false;
+            print a and f;
+            print n;
+            print not a or f;
+            print n;
+            print not a and f;
+            print n
+        end.";
+
+    let p = compile_ok(source);
+    assert_eq!(run(&p.declarations, ""), Ok("0\n0\n1\n0\n1\n1\n".to_string()));
+    assert_eq!(run(&compile_ok_with(source, optimized()).declarations, ""), Ok("0\n0\n1\n0\n1\n1\n".to_string()));
+}
+
+/// *************************************
+/// ********** Unused Symbols **********
+/// *************************************
+
+#[test]
+// Checks that the variables and constants a block never uses are reported at its end, but
+// not the parameters of a procedure or anything when the warning is off
+fn unused_symbols() {
+    let source = "
+        program demo;
+        const limit = 3;
+        const unused = 4;
+        var x : int;
+        var y : int;
+        proc foo(n : int);
+        var z : int;
+        begin
+            print 1
+        end;
+        begin
+            x = limit;
+            foo(x)
+        end.";
+
+    let p = compile_ok_with(source, ParserOptions { warn_unused: true, ..ParserOptions::default() });
+    let warnings = p.warnings();
+    assert_eq!(warnings.len(), 3);
+    assert!(warnings[0].ends_with("`z` is declared in foo but never used"));
+    assert!(warnings[1].ends_with("`unused` is declared in mainblock but never used"));
+    assert!(warnings[2].ends_with("`y` is declared in mainblock but never used"));
+
+    assert!(compile_ok(source).warnings().is_empty());
+}
+
+/// *************************************
+/// ******** Compound Assignment ********
+/// *************************************
+
+#[test]
+// Checks that a compound assignment gives the same result as writing the operation out,
+// with the whole value as the right operand, and that it is still type checked
+fn compound_assignment() {
+    let p = compile_ok("
+        program demo;
+        var x : int;
+        var y : int;
+        begin
+            x = 10;
+            y = 10;
+            x += 5;
+            y = y + 5;
+            print x;
+            print y;
+            x -= 2 * 3;
+            print x;
+            x *= 1 + 1;
+            print x
+        end.");
+    assert_eq!(run(&p.declarations, ""), Ok("15\n15\n9\n18\n".to_string()));
+
+    // A variable assigned again by a compound assignment is not a constant
+    let p = compile_ok_with("program demo; var x : int; begin x = 1; x += 1; print x end.", ParserOptions { aggressive: true, ..optimized() });
+    assert_eq!(run(&p.declarations, ""), Ok("2\n".to_string()));
+
+    let (result, _) = compile("program demo; var b : bool; begin b = true; b += true end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+}
+
+#[test]
+// Checks that an element of an array can be the target of a compound assignment, at a
+// literal index and at one computed when the program runs
+fn compound_assignment_to_element() {
+    let source = "
+        program demo;
+        var a : array[3] of int;
+        var i : int;
+        begin
+            a[0] = 4;
+            a[0] += 1;
+            i = 1;
+            a[i] = 3;
+            a[i] *= a[0] - 1;
+            a[i + 1] -= 2;
+            print a[0];
+            print a[i];
+            print a[2]
+        end.";
+
+    let p = compile_ok(source);
+    assert_eq!(run(&p.declarations, ""), Ok("5\n12\n-2\n".to_string()));
+    assert_eq!(run(&compile_ok_with(source, optimized()).declarations, ""), Ok("5\n12\n-2\n".to_string()));
+}
+
+/// *************************************
+/// ********** For Loop **********
+/// *************************************
+
+#[test]
+// Checks that a for loop counts its variable up to the bound, including it, and runs no
+// iterations when the start is past the bound
+fn for_loop() {
+    let source = "
+        program demo;
+        var i : int;
+        var n : int;
+        begin
+            n = 3;
+            for i := 1 to n + 1 do print i * 10;
+            for i = 5 to 4 do print 0;
+            print i
+        end.";
+
+    let p = compile_ok(source);
+    let lines: Vec<&str> = p.declarations.iter().flat_map(|d| d.split('\n')).collect();
+    assert!(lines.contains(&"bgtr $e_for0"));
+    assert!(lines.contains(&"addw #1 +0@R0"));
+    assert!(lines.contains(&"jmp $b_for1"));
+
+    assert_eq!(run(&p.declarations, ""), Ok("10\n20\n30\n40\n5\n".to_string()));
+    assert_eq!(run(&compile_ok_with(source, optimized()).declarations, ""), Ok("10\n20\n30\n40\n5\n".to_string()));
+}
+
+#[test]
+// Checks that the variable of a for loop must be a declared int var, and its start and bound
+// ints
+fn for_loop_errors() {
+    let errors = |source: &str| {
+        let (result, p) = compile(source);
+        assert!(matches!(result, ParserResult::Unexpected));
+        p.errors().to_vec()
+    };
+
+    let e = errors("program demo; var b : bool; begin for b := 1 to 3 do print 1 end.");
+    assert!(e[0].contains("The variable of the `for` must be an int var but `b` is bool"), "{:?}", e);
+
+    let e = errors("program demo; const k = 2; begin for k := 1 to 3 do print 1 end.");
+    assert!(e[0].contains("The variable of the `for` must be an int var but `k` is const int"), "{:?}", e);
+
+    let e = errors("program demo; begin for i := 1 to 3 do print 1 end.");
+    assert!(e[0].contains("`i` has not been declared"), "{:?}", e);
+
+    let e = errors("program demo; var i : int; begin for i := 1 to true do print 1 end.");
+    assert!(e[0].contains("The bound of the `for` over `i` must be an int but it is"), "{:?}", e);
+
+    let e = errors("program demo; var i : int; begin for i := 1 do print 1 end.");
+    assert!(e[0].contains("Expected `to` after the start of the `for`"), "{:?}", e);
+}
+
+/// *************************************
+/// ********** Variable Lists **********
+/// *************************************
+
+#[test]
+// Checks that several variables of one type can be declared together and each has its own
+// location
+fn variable_lists() {
+    let source = "
+        program demo;
+        var a, b, c : int;
+        var d : bool;
+        begin
+            a = 1;
+            b = a + 1;
+            c = a + b;
+            d = c > b;
+            print a;
+            print b;
+            print c;
+            print d
+        end.";
+
+    let p = compile_ok(source);
+    for location in ["+0@R0", "+4@R0", "+8@R0", "+12@R0"].iter() {
+        assert!(p.declarations.contains(&format!("movw #0 {}", location)), "{}", location);
+    }
+    assert_eq!(run(&p.declarations, ""), Ok("1\n2\n3\n1\n".to_string()));
+    assert_eq!(run(&compile_ok_with(source, optimized()).declarations, ""), Ok("1\n2\n3\n1\n".to_string()));
+
+    let (result, _) = compile("program demo; var a, a : int; begin a = 1 end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+}
+
+/// *************************************
+/// ********** String Variables **********
+/// *************************************
+
+#[test]
+// Checks that string variables can be declared, assigned literals, constants and other
+// strings, and printed
+fn string_variables() {
+    let source = "
+        program demo;
+        const greeting = \"hey\";
+        var s, t : string;
+        var x : int;
+        begin
+            print s;
+            s = \"hello\";
+            t = s;
+            s = greeting;
+            print t;
+            print s;
+            print x
+        end.";
+
+    let p = compile_ok(source);
+
+    // The length is at the start of the string and the characters after it
+    assert!(p.declarations.contains(&"movw #0 +0@R0".to_string()));
+    assert!(p.declarations.contains(&"movw #0 +132@R0".to_string()));
+    assert!(p.declarations.contains(&"movw #0 +264@R0".to_string()));
+    assert!(p.declarations.contains(&"movw #5 +0@R0".to_string()));
+    assert!(p.declarations.contains(&"movw #104 +4@R0".to_string()));
+
+    assert_eq!(run(&p.declarations, ""), Ok("\nhello\nhey\n0\n".to_string()));
+    assert_eq!(run(&compile_ok_with(source, optimized()).declarations, ""), Ok("\nhello\nhey\n0\n".to_string()));
+}
+
+#[test]
+// Checks that strings can only hold strings, fit their capacity and are not used in
+// operations
+fn string_variable_errors() {
+    let errors = |body: &str| {
+        let (result, p) = compile(&format!("program demo; var s, t : string; var x : int; begin {} end.", body));
+        assert!(matches!(result, ParserResult::Unexpected));
+        p.errors().to_vec()
+    };
+
+    let e = errors("s = x");
+    assert!(e[0].contains("`s` is string but `x` is int"), "{:?}", e);
+
+    let e = errors("s = \"abcdefghijklmnopqrstuvwxyz0123456789\"");
+    assert!(e[0].contains("The string is 36 characters long but a string variable holds at most 32"), "{:?}", e);
+
+    errors("x = s");
+    errors("print s + t");
+    errors("print s < t");
+}
+
+/// *************************************
+/// ********** Identifiers **************
+/// *************************************
+
+#[test]
+// Checks that an identifier with an underscore can be declared and used
+fn identifier_characters() {
+    let p = compile_ok("program demo; var my_var : int; begin my_var = 2; print my_var end.");
+    assert_eq!(run(&p.declarations, ""), Ok("2\n".to_string()));
+}
+
+/// *************************************
+/// ********** String Literals **********
+/// *************************************
+
+#[test]
+// Checks that a program with a string which is never closed does not compile
+fn unterminated_string() {
+    let (result, _) = compile("program demo; begin print \"hello end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+}
+
+#[test]
+// Checks that the escape sequences of a string print the characters they stand for
+fn string_escapes() {
+    let p = compile_ok(r#"program demo; begin print "line1\nline2\t end" end."#);
+    assert_eq!(run(&p.declarations, ""), Ok("line1\nline2\t end\n".to_string()));
+}
+
+/// *************************************
+/// ********** Boolean Synonym **********
+/// *************************************
+
+#[test]
+// Checks that both bool and boolean declare a boolean variable, also with strict blocks
+fn boolean_synonym() {
+    for t in ["bool", "boolean", "BOOLEAN"].iter() {
+        let source = format!("program demo; var b : {}; begin b = true; print b end.", t);
+        let p = compile_ok(&source);
+        let b = p.symbol_table.get("b").unwrap();
+        assert!(*b.symbol_type() == SymbolType::Variable(SymbolValueType::Bool));
+    }
+
+    // Strict blocks leave the synonym alone
+    compile_ok_with("program demo; var b : boolean; begin b = true end.", strict());
+}
+
+/// *************************************
+/// ******** Warnings As Errors *********
+/// *************************************
+
+#[test]
+// Checks that a warning fails the compile when warnings are errors and only warns otherwise
+fn warnings_as_errors() {
+    let source = "
+        program demo;
+        var x : int;
+        proc foo;
+        var x : int;
+        begin
+            x = 1
+        end;
+        begin
+            foo
+        end.";
+
+    let warn = ParserOptions {
+        warn_shadowing: true,
+        ..ParserOptions::default()
+    };
+    let p = compile_ok_with(source, warn.clone());
+    assert_eq!(p.warnings().len(), 1);
+    assert!(p.errors().is_empty());
+
+    let (result, p) = compile_with(source, ParserOptions { warnings_as_errors: true, ..warn });
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors().len(), 1);
+    assert!(p.errors()[0].ends_with("x in foo shadows a declaration in mainblock. (warnings are errors)"));
+
+    // Without any warnings the compile still succeeds
+    compile_ok_with(source, ParserOptions { warnings_as_errors: true, ..ParserOptions::default() });
+}
+
+/// *************************************
+/// ********** String Constants *********
+/// *************************************
+
+#[test]
+// Checks that len of a string or string constant is its length as an integer constant
+fn string_length() {
+    let source = "
+        program demo;
+        const greeting = \"hello\";
+        var x : int;
+        begin
+            x = len(greeting) * 2 + len(\"abc\");
+            print greeting;
+            write x;
+            print len(\"\")
+        end.";
+
+    let p = compile_ok(source);
+    assert_eq!(run(&p.declarations, ""), Ok("hello\n130\n".to_string()));
+
+    let (result, p) = compile("program demo; var x : int; begin x = len(x) end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 42) `x` is not a string or string constant".to_string()]);
+}
+
+#[test]
+// Checks that a string constant of a procedure shadows one of the same name in the program
+// only inside the procedure, and is not visible after it
+fn string_constant_scopes() {
+    let source = "
+        program demo;
+        const name = \"main\";
+        proc greet;
+            const name = \"greet\";
+            const other = \"hidden\";
+            begin
+                print name;
+                print len(name)
+            end;
+        begin
+            greet;
+            print name;
+            print len(name)
+        end.";
+
+    let p = compile_ok(source);
+    assert_eq!(run(&p.declarations, ""), Ok("greet\n5\nmain\n4\n".to_string()));
+
+    let (result, _) = compile("
+        program demo;
+        proc greet;
+            const other = \"hidden\";
+            begin
+                print other
+            end;
+        begin
+            print other
+        end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+
+    let (result, p) = compile("program demo; const s = \"a\"; var s : int; begin end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors().len(), 1);
+}
+
+#[test]
+// Checks that len is only built in when no symbol is named len
+fn len_named_by_user() {
+    let source = "
+        program demo;
+        proc len(n : int) : int;
+        begin
+            return n + 1
+        end;
+        begin
+            print len(41)
+        end.";
+
+    let p = compile_ok(source);
+    assert_eq!(run(&p.declarations, ""), Ok("42\n".to_string()));
+}
+
+/// *************************************
+/// ********** Label Manifest ***********
+/// *************************************
+
+#[test]
+// Checks that the manifest has the program's labels at the index of the instruction each
+// one marks
+fn label_manifest_indices() {
+    let source = "
+        program demo;
+        var x : int;
+        begin
+            while x < 3 do
+                x = x + 1;
+            print x
+        end.";
+
+    let p = compile_ok(source);
+    let labels = p.labels();
+    let index = |name: &str| labels.iter().find(|l| l.0 == name).map(|l| l.1).unwrap();
+
+    // Only the operations and data count, so $junk is first and $main follows it
+    assert_eq!(index("junk"), 0);
+    assert_eq!(index("main"), 1);
+    assert!(index("mainblock") <= index("b_while0"));
+    assert!(index("b_while0") < index("e_while0"));
+
+    let ops: Vec<Instruction> = Instruction::from_commands(&p.declarations).into_iter()
+        .filter(|i| matches!(*i, Instruction::Op { .. } | Instruction::Data { .. }))
+        .filter(|i| !i.mnemonic().is_some_and(|m| m.starts_with('$')) || !i.operands().is_empty())
+        .collect();
+    assert_eq!(ops[index("b_while0")].label(), Some("b_while0"));
+    assert_eq!(ops[index("e_while0")].label(), Some("e_while0"));
+}
+
+/// *************************************
+/// ************* Functions *************
+/// *************************************
+
+#[test]
+// Checks that a function returns the value of its return statement to the expression
+// calling it, including from a recursive call
+fn function_return_value() {
+    let source = "
+        program demo;
+        var x : int;
+        proc square : int;
+        begin
+            return x * x
+        end;
+        proc fact : int;
+        var n : int;
+        begin
+            n = x;
+            if n < 2 then
+                return 1;
+            x = x - 1;
+            return n * fact
+        end;
+        begin
+            x = 7;
+            print square + 1;
+            x = 5;
+            print fact
+        end.";
+
+    let p = compile_ok(source);
+    assert_eq!(run(&p.declarations, ""), Ok("50\n120\n".to_string()));
+    assert_eq!(run(&compile_ok_with(source, optimized()).declarations, ""), Ok("50\n120\n".to_string()));
+
+    let (result, p) = compile("program demo; var x : int; begin return x end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 34) `return` outside of a function".to_string()]);
+
+    let (result, p) = compile("program demo; proc f : bool; begin return 1 end; begin end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 36) `f` returns bool but the value is int".to_string()]);
+}
+
+#[test]
+// Checks that a function used twice in one expression gives each use its own result, and
+// that a function with a side effect is called once for each use, from left to right
+fn function_called_twice() {
+    let source = "
+        program demo;
+        var n : int;
+        proc three : int;
+        begin
+            return 3
+        end;
+        proc next : int;
+        begin
+            n = n + 1;
+            return n
+        end;
+        begin
+            print three * 10 + three;
+            print next * 10 + next;
+            print (next + 1) * next
+        end.";
+
+    let p = compile_ok(source);
+    assert_eq!(run(&p.declarations, ""), Ok("33\n12\n16\n".to_string()));
+    assert_eq!(run(&compile_ok_with(source, optimized()).declarations, ""), Ok("33\n12\n16\n".to_string()));
+}
+
+#[test]
+// Checks that the arguments of a function are pushed for its call in an expression, with
+// the temps of the expression kept, and that a call which does not match is reported
+fn function_arguments() {
+    let source = "
+        program demo;
+        var x : int;
+        proc sq(n : int) : int;
+        begin
+            return n * n
+        end;
+        proc sub(a : int, b : int) : int;
+        begin
+            return a - b
+        end;
+        proc pos(n : int) : bool;
+        begin
+            return n > 0
+        end;
+        proc fact(n : int) : int;
+        begin
+            if n < 2 then
+                return 1;
+            return n * fact(n - 1)
+        end;
+        begin
+            x = 3;
+            print sq(x) + 1;
+            print 100 - sub(sq(x + 1), sq(x)) * 2;
+            print pos(x - 4) or pos(x);
+            print fact(5)
+        end.";
+
+    let p = compile_ok(source);
+    assert_eq!(run(&p.declarations, ""), Ok("10\n86\n1\n120\n".to_string()));
+    assert_eq!(run(&compile_ok_with(source, optimized()).declarations, ""), Ok("10\n86\n1\n120\n".to_string()));
+
+    let (result, p) = compile("program demo; proc sq(n : int) : int; begin return n * n end; begin print sq + 1 end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 75) `sq` takes 1 argument(s) but is given 0".to_string()]);
+
+    let (result, p) = compile("program demo; proc sq(n : int) : int; begin return n * n end; begin print sq(true) end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 75) Argument 1 of `sq` is bool but the parameter is int".to_string()]);
+}
+
+/// *************************************
+/// ******* Missing Keyword Recovery ****
+/// *************************************
+
+/// Returns options which recover from a missing then, do or end.
+fn recovering() -> ParserOptions {
+    ParserOptions {
+        recover: true,
+        ..ParserOptions::default()
+    }
+}
+
+#[test]
+// Checks that a missing then is reported where it should be
+fn missing_then() {
+    let source = "program demo; var x : int; begin if x > 0 print x end.";
+
+    let (result, p) = compile(source);
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 43) Expected `then` after the condition of the `if`".to_string()]);
+
+    let (result, p) = compile_with(source, recovering());
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors().len(), 1);
+}
+
+#[test]
+// Checks that a missing do is reported and that recovery finds the errors after it
+fn missing_do() {
+    let source = "
+        program demo;
+        var x : int;
+        begin
+            while x < 3
+                x = x + 1;
+            if x > 3 print x
+        end.";
+
+    let (result, p) = compile(source);
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(6, 17) Expected `do` after the condition of the `while`".to_string()]);
+
+    let (result, p) = compile_with(source, recovering());
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &[
+        "(6, 17) Expected `do` after the condition of the `while`".to_string(),
+        "(7, 22) Expected `then` after the condition of the `if`".to_string(),
+    ]);
+}
+
+#[test]
+// Checks that a begin which is never closed is reported with where it started
+fn missing_end() {
+    let source = "
+        program demo;
+        var x : int;
+        begin
+            while x < 3 do
+            begin
+                x = x + 1
+            ;
+            print x
+        end.";
+
+    let (result, p) = compile(source);
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(10, 12) Expected `end` to close the `begin` at (4, 9)".to_string()]);
+
+    let (result, p) = compile_with(source, recovering());
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors().len(), 1);
+
+    // Nothing is reported for a correct program
+    let (result, p) = compile_with("program demo; var x : int; begin if x > 0 then print x end.", recovering());
+    assert!(matches!(result, ParserResult::Success));
+    assert!(p.errors().is_empty());
+}
+
+/// *************************************
+/// ********** Swap Statement **********
+/// *************************************
+
+#[test]
+// Checks that swap exchanges two variables through a temp on the expression stack
+fn swap_variables() {
+    let (result, p) = compile("
+        program demo;
+        var x : int;
+        var y : int;
+        begin
+            x = 1;
+            y = 2;
+            swap x, y;
+            print x;
+            print y
+        end.");
+    assert!(matches!(result, ParserResult::Success));
+
+    let lines: Vec<String> = p.listing().iter().map(|l| l.trim().to_string()).collect();
+    let n = lines.iter().position(|l| l == "movw +0@R0 +0@R1").unwrap();
+    assert_eq!(&lines[n - 1..n + 3], &[
+        "movw SP R1".to_string(),
+        "movw +0@R0 +0@R1".to_string(),
+        "movw +4@R0 +0@R0".to_string(),
+        "movw +0@R1 +4@R0".to_string(),
+    ]);
+    assert_eq!(run(&p.declarations, "").unwrap(), "2\n1\n");
+}
+
+#[test]
+// Checks that only two declared variables of the same type can be swapped
+fn swap_errors() {
+    let (result, p) = compile("program demo; var x : int; var b : bool; begin swap x, b end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 56) `x` is int but `b` is bool".to_string()]);
+
+    let (result, p) = compile("program demo; const c = 1; var x : int; begin swap c, x end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 52) `c` is not a variable".to_string()]);
+
+    let (result, p) = compile("program demo; var x : int; begin swap x, z end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 42) `z` has not been declared".to_string()]);
+}
+
+/// *************************************
+/// ********** Syntax Profile **********
+/// *************************************
+
+#[test]
+// Checks that the program is written in an alternate syntax
+fn alternate_syntax() {
+    let syntax = Syntax {
+        immediate: "$".to_string(),
+        register: "%r".to_string(),
+        stack_pointer: "%sp".to_string(),
+        frame_pointer: "%fp".to_string(),
+        offset: "".to_string(),
+        signed_offsets: false,
+        constant: "=".to_string(),
+        label: ".".to_string(),
+    };
+    let source = "program demo; var x : int; proc twice(n : int); begin print n * 2 end; begin x = 7; if x > 3 then twice(x); print x end.";
+    let (result, p) = compile_with(source, ParserOptions {
+        syntax: syntax.clone(),
+        ..ParserOptions::default()
+    });
+    assert!(matches!(result, ParserResult::Success));
+
+    let listing = p.listing();
+    for line in [".junk $1", ".main movw %sp %r0", "addw $12, %sp", ".twice movw %fp 0%sp", "movw -8%fp 4%r1",
+                 "bgtr .b_true2", ".b_true2 movw $1 4%r1", "call $4 .twice", "subw $4 %sp", "inb .junk"] {
+        assert!(listing.iter().any(|l| l == line), "missing `{}`", line);
+    }
+    let text = listing.join("\n");
+    assert!(!text.contains("#") && !text.contains("@") && !text.contains("R0") && !text.contains("SP"));
+    assert_eq!(listing.len(), compile_ok(source).listing().len());
+
+    // The labels are told apart from the mnemonics in the syntax
+    let set = ["movw", "addw", "subw", "mulw", "cmpw", "bgtr", "beq", "jmp", "call", "ret", "outw", "outb", "inb", "end"];
+    compile_ok_with(source, ParserOptions {
+        syntax: syntax.clone(),
+        instruction_set: Some(set.iter().map(|s| s.to_string()).collect()),
+        ..ParserOptions::default()
+    });
+
+    // The optimizer only reads the default syntax
+    let (result, p) = compile_with(source, ParserOptions {
+        syntax: syntax,
+        optimize: true,
+        ..ParserOptions::default()
+    });
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["The optimizer, indentation, the control flow graph and the label manifest only read the default syntax".to_string()]);
+}
+
+/// *************************************
+/// ********** Stack Depth **********
+/// *************************************
+
+#[test]
+// Checks the stack depth of the globals, the temps of a nested expression and a call
+fn stack_depth() {
+    let (result, p) = compile_with("
+        program demo;
+        var a : int;
+        var b : int;
+        var c : int;
+        begin
+            c = a * b + b * a * b;
+            print c
+        end.", ParserOptions {
+        emit_stack_depth: true,
+        ..ParserOptions::default()
+    });
+    assert!(matches!(result, ParserResult::Success));
+
+    // The stack pointer is moved past 12 bytes of globals and 8 for the two temps of the
+    // expression, which are then used from the stack pointer
+    assert_eq!(p.stack_depth(), Some(28));
+    assert_eq!(p.declarations[0], ": Maximum stack depth of 28 bytes");
+
+    // A call adds the frame of the procedure, its saved frame pointer and local, and the
+    // temps of its expression
+    let (result, p) = compile("
+        program demo;
+        var a : int;
+        proc p;
+            var l : int;
+            begin
+                l = a * a + a * a;
+                print l
+            end;
+        begin
+            p
+        end.");
+    assert!(matches!(result, ParserResult::Success));
+    assert_eq!(p.stack_depth(), Some(4 + 8 + 8));
+
+    // A function called in an expression is called above the temps still in use and its
+    // argument. The stack pointer is moved past 8 bytes of globals and 20 of temps, then
+    // 16 bytes of temps are live at the call, 4 are the argument, 4 the saved frame pointer
+    // and 4 the temp of the function
+    let (result, p) = compile("
+        program demo;
+        var x : int;
+        proc sq(n : int) : int;
+        begin
+            return n * n
+        end;
+        begin
+            x = 2;
+            print x * 3 + sq(x + 1)
+        end.");
+    assert!(matches!(result, ParserResult::Success));
+    assert_eq!(p.stack_depth(), Some(28 + 16 + 4 + 4 + 4));
+
+    // There is no limit when a procedure calls itself
+    let (result, p) = compile("
+        program demo;
+        proc p;
+            begin
+                p;
+                print 1
+            end;
+        begin
+            p
+        end.");
+    assert!(matches!(result, ParserResult::Success));
+    assert_eq!(p.stack_depth(), None);
+}
+
+#[test]
+// Checks that a semicolon right before an end is an empty statement which emits nothing
+fn trailing_semicolon() {
+    let (result, p) = compile("
+        program demo;
+        var a : int;
+        begin
+            a = 1;
+            while a < 3 do
+            begin
+                print a;
+                a = a + 1;
+            end;
+        end.");
+    assert!(matches!(result, ParserResult::Success));
+    assert_eq!(run(&p.declarations, "").unwrap(), "1\n2\n");
+
+    let (_, without) = compile("
+        program demo;
+        var a : int;
+        begin
+            a = 1;
+            while a < 3 do
+            begin
+                print a;
+                a = a + 1
+            end
+        end.");
+    assert_eq!(p.declarations, without.declarations);
+
+    // Only one is allowed, two in a row are still an error
+    let (result, _) = compile("program demo; begin print 1;; end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+}
+
+/// *************************************
+/// ********** Diagnostics **********
+/// *************************************
+
+#[test]
+// Checks the fields of the JSON diagnostics of a broken program
+fn json_diagnostics() {
+    let (result, p) = compile_with("program demo; var x : int; begin swap x, z end.", ParserOptions {
+        json_diagnostics: true,
+        ..ParserOptions::default()
+    });
+    assert!(matches!(result, ParserResult::Unexpected));
+
+    let d = &p.diagnostics()[0];
+    assert_eq!(d.severity, Severity::Error);
+    assert_eq!(d.position, Some((1, 42)));
+    assert_eq!(d.to_json(),
+               "{\"severity\": \"error\", \"message\": \"`z` has not been declared\", \"file\": null, \"line\": 1, \"column\": 42}");
+
+    // The human readable form is the same as the recorded error
+    assert_eq!(p.errors(), &[d.to_string()]);
+
+    let (_, p) = compile_with("program demo; var x : int; proc p; var x : int; begin print x end; begin p end.", ParserOptions {
+        warn_shadowing: true,
+        ..ParserOptions::default()
+    });
+    let d = &p.diagnostics()[0];
+    assert_eq!(d.severity, Severity::Warning);
+    assert!(d.to_json().starts_with("{\"severity\": \"warning\", \"message\": \""));
+    assert_eq!(p.warnings(), &[d.to_string()]);
+}
+
+#[test]
+// Checks that declaring a name twice in one scope is an error at the second declaration
+// naming the first instead of aborting the compiler
+fn duplicate_declaration() {
+    let (result, p) = compile("program demo; var a : int; var a : bool; begin a = 1 end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors().len(), 1);
+    assert!(p.errors()[0].ends_with("(1, 32) `a` is already declared in mainblock as a variable of type int"), "{:?}", p.errors());
+
+    let (result, p) = compile("program demo; proc p; const n = 1; var n : int; begin print n end; begin p end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert!(p.errors()[0].ends_with("`n` is already declared in p as a constant of type int"), "{:?}", p.errors());
+}
+
+#[test]
+// Checks that a program without its final period is an error at the last `end`
+fn missing_final_period() {
+    let (result, p) = compile("program demo;\nbegin\n    print 1\nend");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.diagnostics()[0].position, Some((4, 1)));
+    assert!(p.errors()[0].contains("must end with a period"), "{:?}", p.errors());
+
+    let (result, p) = compile("program demo; begin print 1 end;");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert!(p.errors()[0].contains("Expected a period to end the program but found `;`"), "{:?}", p.errors());
+}
+
+#[test]
+// Checks that a program cut off anywhere is an error instead of a panic, and that one
+// ending right after `begin` reports the end of the file
+fn truncated_program() {
+    let (result, p) = compile("program demo;\nbegin");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.diagnostics()[0].position, Some((2, 1)));
+    assert!(p.errors()[0].contains("Unexpected end of file"), "{:?}", p.errors());
+
+    let source = "program demo; var x : int; begin x = 1; if x > 0 then print x else print 0 end.";
+    let words: Vec<&str> = source.split(' ').collect();
+    for n in 1..words.len() {
+        let (result, p) = compile(&words[..n].join(" "));
+        assert!(matches!(result, ParserResult::Unexpected));
+        assert!(!p.errors().is_empty(), "{:?}", &words[..n]);
+    }
+}
+
+#[test]
+// Checks that tokens after the final period are an error instead of being ignored
+fn trailing_tokens_after_period() {
+    let (result, p) = compile("program demo; begin print 1 end. print 2");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.diagnostics()[0].position, Some((1, 34)));
+    assert!(p.errors()[0].contains("Found `print` after the period"), "{:?}", p.errors());
+}
+
+/// *************************************
+/// ********** Tail Calls **********
+/// *************************************
+
+#[test]
+// Checks that a procedure calling itself as the last thing it does jumps back to its start
+fn tail_call() {
+    let (result, p) = compile("
+        program demo;
+        var n : int;
+        proc count;
+            var step : int;
+            begin
+                step = 1;
+                print n;
+                n = n + step;
+                if n < 4 then
+                    count
+            end;
+        begin
+            n = 1;
+            count
+        end.");
+    assert!(matches!(result, ParserResult::Success));
+
+    let calls: Vec<&String> = p.declarations.iter().filter(|c| c.contains("call #0 $count")).collect();
+    assert_eq!(calls.len(), 1, "Only the main program should call count");
+    assert!(p.declarations.iter().any(|c| c.trim() == "jmp $count"));
+    assert_eq!(run(&p.declarations, "").unwrap(), "1\n2\n3\n");
+    // The recursion reuses the frame, so the stack holds a single call of count
+    assert_eq!(p.stack_depth(), Some(4 + 8 + 8 + 8));
+
+    // A call followed by another statement or inside a loop still has to return
+    let (result, p) = compile("
+        program demo;
+        var n : int;
+        proc count;
+            begin
+                n = n + 1;
+                if n < 3 then
+                    count;
+                print n
+            end;
+        proc again;
+            begin
+                while n < 5 do
+                begin
+                    n = n + 1;
+                    again
+                end
+            end;
+        begin
+            count;
+            again
+        end.");
+    assert!(matches!(result, ParserResult::Success));
+    assert!(p.declarations.iter().any(|c| c.contains("call #0 $count")));
+    assert_eq!(p.declarations.iter().filter(|c| c.contains("call #0 $again")).count(), 2);
+    assert!(!p.declarations.iter().any(|c| c.trim() == "jmp $count" || c.trim() == "jmp $again"));
+}
+
+#[test]
+// Checks that a procedure calling itself with arguments as the last thing it does moves them
+// into its parameters and jumps back to its start, even when an argument reads a parameter
+// another one replaces
+fn tail_call_with_arguments() {
+    let source = "
+        program demo;
+        proc count(n : int);
+            begin
+                print n;
+                if n > 1 then
+                    count(n - 1)
+            end;
+        proc alternate(a : int, b : int, k : int);
+            begin
+                print a;
+                if k > 1 then
+                    alternate(b, a, k - 1)
+            end;
+        begin
+            count(3);
+            alternate(1, 2, 4)
+        end.";
+
+    let (result, p) = compile(source);
+    assert!(matches!(result, ParserResult::Success));
+    assert_eq!(p.declarations.iter().filter(|c| c.contains("$count")).filter(|c| c.starts_with("call")).count(), 1);
+    assert_eq!(p.declarations.iter().filter(|c| c.contains("$alternate")).filter(|c| c.starts_with("call")).count(), 1);
+    assert!(p.declarations.iter().any(|c| c.trim() == "jmp $count"));
+    assert!(p.declarations.iter().any(|c| c.trim() == "jmp $alternate"));
+    assert!(p.declarations.contains(&"movw -4@SP -8@FP".to_string()), "{:?}", p.declarations);
+    assert_eq!(run(&p.declarations, "").unwrap(), "3\n2\n1\n1\n2\n1\n2\n");
+
+    let p = compile_ok_with(source, optimized());
+    assert_eq!(run(&p.declarations, "").unwrap(), "3\n2\n1\n1\n2\n1\n2\n");
+}
+
+/// *************************************
+/// ********** Chars **********
+/// *************************************
+
+#[test]
+// Checks that a char variable holds the code of a char literal and prints as the character
+fn char_variable() {
+    let (result, p) = compile("
+        program demo;
+        var c : char;
+        var d : char;
+        begin
+            c = 'A';
+            d = c;
+            print d;
+            print c == 'A';
+            print c < 'B'
+        end.");
+    assert!(matches!(result, ParserResult::Success));
+    assert!(p.declarations.iter().any(|c| c.contains("movw #65 ")));
+    assert!(p.declarations.iter().any(|c| c.starts_with("outb ") && c != "outb #10"));
+    assert_eq!(run(&p.declarations, "").unwrap(), "A\n1\n1\n");
+}
+
+#[test]
+// Checks that chars can only be compared and only with chars
+fn char_errors() {
+    let (result, _) = compile("program demo; var c : char; begin c = 'A' + 'B' end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+
+    let (result, _) = compile("program demo; var c : char; begin c = 65 end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+
+    let (result, _) = compile("program demo; var n : int; begin n = 'A' end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+}
+
+/// *************************************
+/// ********** Stack Adjustment *********
+/// *************************************
+
+#[test]
+// Checks that the stack pointer is moved past the globals and the most the temps of the
+// main block use
+fn stack_adjustment() {
+    let p = compile_ok("
+        program demo;
+        const k = 2;
+        var a : int;
+        var b : int;
+        begin
+            a = k * b + b * a;
+            print a
+        end.");
+    // 12 bytes for k, a and b and 8 for the two products
+    assert!(p.declarations.iter().any(|d| d == "addw #20, SP"));
+    assert_eq!(run(&p.declarations, ""), Ok("0\n".to_string()));
+}
+
+/// *************************************
+/// ********** Visible Symbols **********
+/// *************************************
+
+#[test]
+// Checks that the symbols visible inside a procedure are its locals and the globals, and
+// that its locals are not visible in the main block while the procedure is
+fn symbols_at_position() {
+    let source = "
+        program demo;
+        const limit = 3;
+        var total : int;
+        proc add;
+            var doubled : int;
+            begin
+                doubled = limit * 2;
+                total = total + doubled
+            end;
+        begin
+            add
+        end.";
+
+    let options = ParserOptions {
+        record_scopes: true,
+        ..ParserOptions::default()
+    };
+    let p = compile_ok_with(source, options);
+
+    let names = |line: u32, column: u32| -> Vec<String> {
+        let mut names: Vec<String> = p.symbols_at(line, column).iter()
+            .map(|s| s.identifier().clone())
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(names(9, 30), vec!["add", "doubled", "limit", "total"]);
+    assert_eq!(names(12, 17), vec!["add", "limit", "total"]);
+    assert!(p.symbols_at(1, 1).is_empty());
+
+    let doubled = p.symbols_at(9, 30).iter().find(|s| s.identifier() == "doubled").unwrap();
+    assert_eq!(doubled.symbol_type().to_string(), "int");
+}
+
+/// *************************************
+/// ********** Parentheses **************
+/// *************************************
+
+#[test]
+// Checks that parentheses group a subexpression in a program, including inside the
+// condition of an if
+fn parenthesized_expressions() {
+    let p = compile_ok("
+        program demo;
+        var a : int;
+        var b : int;
+        begin
+            a = 2;
+            b = (a + 3) * 4;
+            print b;
+            if (b - 10) * 2 > 15 then
+                print (a + 1) * (a + 2)
+        end.");
+    assert_eq!(run(&p.declarations, ""), Ok("20\n12\n".to_string()));
+
+    let (result, _) = compile("program demo; var a : int; begin a = (1 + 2 end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+}
+
+#[test]
+// Checks that a minus with no operand before it negates in a program
+fn unary_minus_program() {
+    let p = compile_ok("
+        program demo;
+        var a : int;
+        var b : int;
+        begin
+            a = -5 + 3;
+            b = a * -2;
+            print b;
+            print -(a + b)
+        end.");
+    assert_eq!(run(&p.declarations, ""), Ok("4\n-2\n".to_string()));
+}
+
+#[test]
+// Checks that operators of the same precedence are worked from the left, in variables,
+// literals and a constant
+fn left_associative_program() {
+    let p = compile_ok("
+        program demo;
+        const c = 10 - 2 - 1;
+        var a : int;
+        var b : int;
+        var d : int;
+        begin
+            a = 10;
+            b = 2;
+            d = 1;
+            print a - b - d;
+            print a - b * 3 - d;
+            a = 64;
+            b = 4;
+            d = 2;
+            print a div b div d;
+            print 64 div 4 div 2;
+            print c
+        end.");
+    assert_eq!(run(&p.declarations, ""), Ok("7\n3\n8\n8\n7\n".to_string()));
+}
+
+/// *************************************
+/// ****** Hex And Binary Numbers *******
+/// *************************************
+
+#[test]
+// Checks that hexadecimal and binary numbers can be used as constants and in expressions
+fn hex_and_binary_values() {
+    let p = compile_ok("
+        program demo;
+        const mask = 0x0F;
+        var x : int;
+        begin
+            x = 0b1010 + mask;
+            print x;
+            print 0x10 * 2
+        end.");
+    assert_eq!(run(&p.declarations, ""), Ok("25\n32\n".to_string()));
+}
+
+/// *************************************
+/// ********* Bitwise Operators *********
+/// *************************************
+
+#[test]
+// Checks that the bitwise operators compute the same values when the program runs as when
+// they are folded, and that they are not allowed on booleans
+fn bitwise_operators() {
+    let p = compile_ok("
+        program demo;
+        var x : int;
+        begin
+            x = 6;
+            print x band 3;
+            print x bor 9;
+            print x bxor 5;
+            print 1 shl x;
+            print x shr 1;
+            print 6 band 3 + 1
+        end.");
+    assert_eq!(run(&p.declarations, "").unwrap(), "2\n15\n3\n64\n3\n4\n");
+
+    let (result, _) = compile("program demo; var b : bool; begin b = true; print b band false end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+}
+
+/// *************************************
+/// ************** Prompts **************
+/// *************************************
+
+#[test]
+// Checks that a prompt reads a number into an int, any number other than 0 as true into a
+// bool and a single character into a char
+fn prompt_reads_by_type() {
+    let p = compile_ok("
+        program demo;
+        var n : int;
+        var b : bool;
+        var c : char;
+        begin
+            prompt \"n?\", n;
+            prompt \"b?\", b;
+            prompt \"c?\", c;
+            print n + 1, \" \", b, \" \", c
+        end.");
+    assert!(p.declarations.contains(&"inw +0@R0".to_string()), "{:?}", p.declarations);
+    assert!(p.declarations.contains(&"movw #1 +4@R0".to_string()), "{:?}", p.declarations);
+    assert!(p.declarations.contains(&"inb +8@R0".to_string()), "{:?}", p.declarations);
+
+    assert_eq!(run(&p.declarations, "41 7x").unwrap(), "n?\nb?\nc?\n42 1 x\n");
+    assert_eq!(run(&p.declarations, "-3 0y").unwrap(), "n?\nb?\nc?\n-2 0 y\n");
+}
+
+#[test]
+// Checks that a prompt into a constant or a real is a compile error
+fn prompt_errors() {
+    let (result, p) = compile("program demo; const k = 1; begin prompt \"k?\", k end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 47) `k` is a constant of type int, a prompt can only read into a variable".to_string()]);
+
+    let (result, p) = compile("program demo; var r : real; begin prompt \"r?\", r end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    assert_eq!(p.errors(), &["(1, 48) `r` is a variable of type real, a prompt can only read an int, bool or char".to_string()]);
+}
+
+/// *************************************
+/// ******** Print Several Values *******
+/// *************************************
+
+#[test]
+// Checks that the strings and expressions separated by commas are output in order with a
+// single newline after all of them, and none after a write
+fn print_several_values() {
+    let p = compile_ok("
+        program demo;
+        var x, y : int;
+        begin
+            x = 3;
+            y = 4;
+            print \"x = \", x, \" y = \", y;
+            write \"a\", x + y, 'b';
+            print \"\"
+        end.");
+
+    let outputs: Vec<String> = p.declarations.iter()
+        .filter(|c| c.starts_with("out"))
+        .map(|c| if c.starts_with("outw") { "outw".to_string() } else { c.clone() })
+        .collect();
+    let expected = ["outb #120", "outb #32", "outb #61", "outb #32", "outw",
+                    "outb #32", "outb #121", "outb #32", "outb #61", "outb #32", "outw", "outb #10",
+                    "outb #97", "outw"];
+    assert_eq!(&outputs[..expected.len()], &expected);
+
+    assert_eq!(run(&p.declarations, "").unwrap(), "x = 3 y = 4\na7b\n");
+}
+
+/// *************************************
+/// *************** Arrays **************
+/// *************************************
+
+#[test]
+// Checks that an array takes a word for each element, which all start as 0, and that its
+// elements are stored and loaded at literal indexes and at indexes computed when the
+// program runs, including an index read from another element
+fn arrays() {
+    let source = "
+        program demo;
+        var a : array[4] of int;
+        var i, x : int;
+        begin
+            a[1] = 5;
+            i = 2;
+            a[i + 1] = a[1] * 2;
+            x = a[3] + a[i - 1];
+            print x;
+            print a[0];
+            i = 0;
+            while i < 4 do
+            begin
+                a[i] = a[i] + i;
+                i = i + 1
+            end;
+            print a[a[1] - 5] + a[2] + a[3]
+        end.";
+
+    let p = compile_ok(source);
+    for offset in [0, 4, 8, 12].iter() {
+        assert!(p.declarations.contains(&format!("movw #0 +{}@R0", offset)), "{:?}", p.declarations);
+    }
+    assert!(p.declarations.contains(&"movw #0 +16@R0".to_string()), "{:?}", p.declarations);
+    assert_eq!(run(&p.declarations, "").unwrap(), "15\n0\n21\n");
+
+    let p = compile_ok_with(source, optimized());
+    assert_eq!(run(&p.declarations, "").unwrap(), "15\n0\n21\n");
+}
+
+#[test]
+// Checks that the elements of an array declared in a procedure are on its frame
+fn array_in_procedure() {
+    let p = compile_ok("
+        program demo;
+        proc squares(n : int);
+        var s : array[3] of int;
+        var i : int;
+        begin
+            i = 0;
+            while i < 3 do
+            begin
+                s[i] = (i + n) * (i + n);
+                i = i + 1
+            end;
+            print s[0] + s[1] + s[2]
+        end;
+        begin
+            squares(1)
+        end.");
+    assert_eq!(run(&p.declarations, "").unwrap(), "14\n");
+}
+
+#[test]
+// Checks that the elements of an array read in an expression are not symbols in the visible
+// symbols, the symbol dump or the debug table
+fn array_elements_not_declared() {
+    let options = ParserOptions {
+        record_scopes: true,
+        debug_info: true,
+        emit_symbols: true,
+        ..ParserOptions::default()
+    };
+    let p = compile_ok_with("
+        program demo;
+        var a : array[2] of int;
+        var x : int;
+        begin
+            x = a[1] + a[0];
+            print x
+        end.", options);
+
+    let mut names: Vec<String> = p.symbols_at(6, 13).iter().map(|s| s.identifier().clone()).collect();
+    names.sort();
+    assert_eq!(names, vec!["a", "x"]);
+
+    assert!(p.symbol_dumps.iter().all(|d| !d.contains("a[")), "{:?}", p.symbol_dumps);
+    assert_eq!(p.debug_info, vec!["mainblock a var array[2] of int +0@R0", "mainblock x var int +8@R0"]);
+}
+
+#[test]
+// Checks that a literal index out of bounds, an index which is not an int, a value of the
+// wrong type and an array used as a whole are compile errors
+fn array_errors() {
+    let errors = |source: &str| {
+        let (result, p) = compile(source);
+        assert!(matches!(result, ParserResult::Unexpected));
+        p.errors().to_vec()
+    };
+
+    assert_eq!(errors("program demo; var a : array[3] of int; begin a[3] = 1 end."),
+               vec!["(1, 48) The index 3 is out of bounds, `a` has 3 elements".to_string()]);
+    assert_eq!(errors("program demo; var a : array[3] of int; begin print a[5] end."),
+               vec!["(1, 54) The index 5 is out of bounds, `a` has 3 elements".to_string()]);
+    assert_eq!(errors("program demo; var a : array[3] of int; begin print a[true] end."),
+               vec!["(1, 52) The index of `a` must be an int but it is bool".to_string()]);
+    assert_eq!(errors("program demo; var a : array[3] of bool; begin a[0] = 1 end."),
+               vec!["(1, 47) The elements of `a` are bools but the value is int".to_string()]);
+    assert_eq!(errors("program demo; var a : array[3] of int; begin print a + 1 end."),
+               vec!["(1, 52) `a` is an array, only its elements can be used in an expression".to_string()]);
+    assert_eq!(errors("program demo; var a : array[0] of int; begin end."),
+               vec!["(1, 23) The size of an array must be a positive number in brackets, as in `array[10] of int`".to_string()]);
+}
+
+/// *************************************
+/// ********** Writing Commands *********
+/// *************************************
+
+#[test]
+// Checks that commands can be written to a buffer instead of a file, each ending a line
+fn write_commands_to_buffer() {
+    let mut buffer = Vec::<u8>::new();
+    let commands = vec!["movw #1 +0@R0".to_string(), "".to_string(), "end".to_string()];
+    super::file_generator::write_commands(&mut buffer, commands).unwrap();
+    assert_eq!(buffer, b"movw #1 +0@R0\n\nend\n".to_vec());
+
+    let mut buffer = Vec::<u8>::new();
+    super::file_generator::write_commands(&mut buffer, Vec::new()).unwrap();
+    assert!(buffer.is_empty());
+}
+
+/// *************************************
+/// *********** Real Numbers ************
+/// *************************************
+
+#[test]
+// Checks that reals are computed, compared and printed as floats, and that mixing them
+// with integers is an error
+fn real_values() {
+    let p = compile_ok("
+        program demo;
+        const half = 0.5;
+        var r : real;
+        begin
+            r = 1.5 + 2.5;
+            print r;
+            print r * half - 3.25;
+            if r > 3.9 then print -r;
+            r = r div 0.5;
+            print r
+        end.");
+    assert_eq!(run(&p.declarations, ""), Ok("4.0\n-1.25\n-4.0\n8.0\n".to_string()));
+
+    let (result, _) = compile("program demo; var r : real; begin r = 1.5 + 2 end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    let (result, _) = compile("program demo; var r : real; begin r = 2 end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+    let (result, _) = compile("program demo; var r : real; begin r = 2.5 mod 2.0 end.");
+    assert!(matches!(result, ParserResult::Unexpected));
+}
+
+/// *************************************
+/// ********** Percent Operator *********
+/// *************************************
+
+#[test]
+// Checks that % computes the same remainder as mod
+fn percent_operator() {
+    let p = compile_ok("program demo; var x : int; begin x = 17; print x % 5; print 1 + x % 5 end.");
+    assert_eq!(run(&p.declarations, ""), Ok("2\n3\n".to_string()));
+}
+
+#[test]
+// Checks that mod and % of a literal, which is folded, and of an expression, which is not,
+// print the same remainder at every optimization level
+fn remainder_at_every_level() {
+    let source = "program demo; var x : int; begin x = 7; print 7 mod 3; print 7 % 3; print (x + 0) mod 3 end.";
+    for level in 0..3 {
+        let mut options = ParserOptions::default();
+        options.set_optimization_level(level);
+        let p = compile_ok_with(source, options);
+        assert_eq!(run(&p.declarations, ""), Ok("1\n1\n1\n".to_string()), "level {}", level);
+    }
+}
+
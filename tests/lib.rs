@@ -132,3 +132,280 @@
 // // fn fail10() {
 // //     test_file!("fail10.txt");
 // // }
+
+extern crate yasl_compiler;
+
+use yasl_compiler::{compile_file, compile_file_with, compile_sources, compile_string, compile_string_to_asm, compile_with_options, run_file, tokenize, tokenize_filtered, CompileError, CompileOptions, ParserOptions, ParserResult, RunError, TokenType, KeywordType, LexerError};
+
+#[test]
+// Checks that several options given together all apply to the compile
+fn compile_with_strict_optimized_dry_run() {
+    let options = CompileOptions {
+        parser: ParserOptions {
+            optimize: true,
+            strict: true,
+            ..ParserOptions::default()
+        },
+        output: "never_written.pal".to_string(),
+        dry_run: true,
+    };
+
+    let source = "program demo; var x : int; begin x = 2 + 3; print x end.";
+    let output = compile_with_options(source.to_string(), options.clone()).unwrap();
+    assert!(output.program.contains(&"movw #5 +0@R1".to_string()));
+    assert!(!std::path::Path::new("never_written.pal").exists());
+
+    let source = "program demo; var x : int; begin if x < 1 then x = 1 end.";
+    match compile_with_options(source.to_string(), options) {
+        Err(CompileError::Program(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0].contains("strict mode but found `x`"), "{:?}", errors);
+        },
+        _ => panic!("A bare if body should not compile in strict mode"),
+    }
+}
+
+#[test]
+// Checks the number of instructions each optimization level emits for a program which propagates
+// a constant, only level 2 replaces x with the 10 it is assigned
+fn optimization_levels() {
+    let source = "
+        program demo;
+        var x : int;
+        var y : int;
+        begin
+            x = 10;
+            y = x * 2;
+            print y
+        end.";
+
+    let counts: Vec<usize> = (0..3).map(|level| {
+        let mut options = CompileOptions {
+            dry_run: true,
+            ..CompileOptions::default()
+        };
+        options.set_optimization_level(level);
+        assert_eq!(options.optimization_level(), level);
+
+        let output = compile_with_options(source.to_string(), options).unwrap();
+        output.program.iter().filter(|l| !l.trim().is_empty() && !l.starts_with(':')).count()
+    }).collect();
+
+    assert_eq!(counts, vec![20, 20, 17]);
+}
+
+#[test]
+// Checks that a source string compiles without reading a file
+fn compile_string_without_files() {
+    let result = compile_string("program demo; var x : int; begin x = 1; print x end.".to_string());
+    assert!(matches!(result, ParserResult::Success));
+
+    let result = compile_string("program demo; begin x = 1 end.".to_string());
+    assert!(matches!(result, ParserResult::Unexpected));
+}
+
+#[test]
+// Checks that the compiled text is the same as the file which is written for the source
+fn compile_string_to_asm_matches_file() {
+    let source = "program demo; var x : int; begin x = 4 * 5; print x end.";
+    let asm = compile_string_to_asm(source.to_string()).unwrap();
+    assert!(asm.starts_with(": Initialize junk variable and setup the stack\n"));
+    assert!(asm.contains("\noutw +0@R0\n"));
+    assert!(asm.ends_with("end\n"));
+
+    let output = std::env::temp_dir().join("yasl_asm_matches.pal").to_string_lossy().into_owned();
+    let options = CompileOptions {
+        output: output.clone(),
+        ..CompileOptions::default()
+    };
+    compile_with_options(source.to_string(), options).unwrap();
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), asm);
+
+    assert!(compile_string_to_asm("program demo; begin x = 1 end.".to_string()).is_err());
+}
+
+#[test]
+// Checks that a failed compile says whether it was an invalid token, an undeclared name or
+// an unexpected token and where
+fn compile_error_kinds() {
+    let options = CompileOptions {
+        dry_run: true,
+        ..CompileOptions::default()
+    };
+    let error = |source: &str| compile_with_options(source.to_string(), options.clone()).err().unwrap();
+
+    match error("program demo;\nvar x : int;\nbegin x = 0x end.") {
+        CompileError::Lexical { line, column, lexeme } => {
+            assert_eq!((line, column, lexeme.as_str()), (3, 11, "0x"));
+        },
+        e => panic!("Expected a lexical error but found {:?}", e),
+    }
+
+    match error("program demo; var x : int; begin x = y + 1 end.") {
+        CompileError::UndeclaredSymbol { name } => assert_eq!(name, "y"),
+        e => panic!("Expected an undeclared symbol but found {:?}", e),
+    }
+
+    match error("program demo; var x : int; begin x := 1 end.") {
+        CompileError::UnexpectedToken { line, column, found } => {
+            assert_eq!((line, column, found.as_str()), (1, 36, ":"));
+        },
+        e => panic!("Expected an unexpected token but found {:?}", e),
+    }
+
+    // A divisor which is zero, also once it is folded, is reported where the expression starts
+    for source in &["program demo; var x : int; begin x = 5 div 0 end.", "program demo; var x : int; begin x = 4 div (2 - 2) end."] {
+        match error(source) {
+            CompileError::Program(errors) => {
+                assert_eq!(errors.len(), 1);
+                assert!(errors[0].starts_with("(1, 38)") && errors[0].contains("divides by zero"), "{}", errors[0]);
+            },
+            e => panic!("Expected the division by zero to be reported but found {:?}", e),
+        }
+    }
+
+    let file = source_file("yasl_compile_error.txt", "program demo; var x : int; begin x := 1 end.");
+    assert!(matches!(compile_file(file), Err(CompileError::UnexpectedToken { .. })));
+}
+
+/// Writes the source to a file in the temporary directory and returns its name.
+fn source_file(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, source).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+// Checks that running a program returns what it prints
+fn run_file_prints_sum() {
+    let file = source_file("yasl_run_sum.txt", "
+        program sum;
+        var i : int;
+        var total : int;
+        begin
+            i = 1;
+            while i <= 5 do
+            begin
+                total = total + i;
+                i = i + 1
+            end;
+            print total
+        end.");
+
+    assert_eq!(run_file(file).unwrap(), "15\n");
+
+    let file = source_file("yasl_run_invalid.txt", "program sum; begin print missing end.");
+    match run_file(file) {
+        Err(RunError::Compile(_)) => {},
+        _ => panic!("An undeclared variable should be a compile error"),
+    }
+}
+
+#[test]
+// Checks that the error the OS gives for a file which can not be read is returned instead
+// of compiling an empty source
+fn missing_file_error() {
+    let file = "this/file/does/not/exist.yasl".to_string();
+    let not_found = std::fs::read_to_string(&file).unwrap_err().to_string();
+
+    match compile_file(file.clone()) {
+        Err(CompileError::Input(e)) => assert_eq!(e, not_found),
+        r => panic!("Expected the file error but found {:?}", r),
+    }
+    match run_file(file.clone()) {
+        Err(RunError::Compile(CompileError::Input(e))) => assert_eq!(e, not_found),
+        r => panic!("Expected the file error but found {:?}", r),
+    }
+    assert!(matches!(compile_file_with(file, ParserOptions::default()), ParserResult::Unexpected));
+}
+
+#[test]
+// Checks that tokenize classifies keywords, identifiers and numbers and keeps invalid tokens
+fn tokenize_classifies_tokens() {
+    let tokens = tokenize("program p;\nbegin print count + 12 ? end.".to_string()).unwrap();
+
+    let types: Vec<TokenType> = tokens.iter().map(|t| t.token_type()).collect();
+    assert!(types == vec![
+        TokenType::Keyword(KeywordType::Program), TokenType::Identifier, TokenType::Semicolon,
+        TokenType::Keyword(KeywordType::Begin), TokenType::Keyword(KeywordType::Print),
+        TokenType::Identifier, TokenType::Plus, TokenType::Number, TokenType::Invalid,
+        TokenType::Keyword(KeywordType::End), TokenType::Period,
+    ]);
+
+    assert_eq!((tokens[5].lexeme(), tokens[5].line(), tokens[5].column()), ("count".to_string(), 2, 13));
+    assert_eq!((tokens[8].lexeme(), tokens[8].column()), ("?".to_string(), 24));
+
+    match tokenize("x (* open".to_string()) {
+        Err(LexerError::UnterminatedComment(1, 3)) => {},
+        _ => panic!("An unterminated comment should be an error"),
+    }
+}
+
+#[test]
+// Checks that invalid tokens are left out of the tokens and listed separately
+fn tokenize_filtered_splits_invalid() {
+    let (tokens, invalid) = tokenize_filtered("x = 1 ? 2 ~ y;".to_string()).unwrap();
+
+    let lexemes: Vec<String> = tokens.iter().map(|t| t.lexeme()).collect();
+    assert_eq!(lexemes, vec!["x", "=", "1", "2", "y", ";"]);
+
+    assert_eq!(invalid.len(), 2);
+    assert!(invalid.iter().all(|t| t.is_type(TokenType::Invalid)));
+    assert_eq!((invalid[0].lexeme(), invalid[0].column()), ("?".to_string(), 7));
+
+    // Without invalid tokens nothing is left out
+    let (tokens, invalid) = tokenize_filtered("x = 1;".to_string()).unwrap();
+    assert_eq!(tokens.len(), 4);
+    assert!(invalid.is_empty());
+}
+
+#[test]
+// Checks that programs compile on several threads at once, where only some of them log
+fn compile_on_threads() {
+    assert!(!ParserOptions::default().verbose);
+
+    let source = "program demo; var x : int; begin x = 2 + 3; print x end.";
+    let threads: Vec<_> = (0..4).map(|n| {
+        std::thread::spawn(move || {
+            let options = CompileOptions {
+                parser: ParserOptions {
+                    verbose: n % 2 == 0,
+                    ..ParserOptions::default()
+                },
+                dry_run: true,
+                ..CompileOptions::default()
+            };
+            compile_with_options(source.to_string(), options).unwrap().text
+        })
+    }).collect();
+
+    let texts: Vec<String> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    assert!(texts.iter().all(|t| *t == texts[0]));
+}
+
+#[test]
+// Checks that an error in the second of two sources names that file and its own line
+fn compile_sources_names_files() {
+    let options = CompileOptions {
+        dry_run: true,
+        ..CompileOptions::default()
+    };
+    let sources = vec![
+        ("header.yasl".to_string(), "program demo;\nvar x : int;\n".to_string()),
+        ("body.yasl".to_string(), "begin\n    x = 1;\n    swap x, y\nend.".to_string()),
+    ];
+
+    match compile_sources(sources, options.clone()) {
+        Err(CompileError::Program(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0].starts_with("body.yasl (3, 13) "), "{}", errors[0]);
+        },
+        _ => panic!("The undeclared variable should be an error"),
+    }
+
+    let sources = vec![
+        ("header.yasl".to_string(), "program demo;\nvar x : int;\n".to_string()),
+        ("body.yasl".to_string(), "begin\n    x = 1;\n    print x\nend.".to_string()),
+    ];
+    assert!(compile_sources(sources, options).is_ok());
+}